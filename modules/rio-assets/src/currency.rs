@@ -131,6 +131,7 @@ where
         new_balance: Self::Balance,
     ) -> dispatch::Result {
         <Module<T>>::ensure_can_withdraw(&U::asset_id(), who, amount, reasons, new_balance)
+            .map_err(Into::into)
     }
 
     fn transfer(
@@ -139,7 +140,7 @@ where
         value: Self::Balance,
         _: ExistenceRequirement, // no existential deposit policy for generic asset
     ) -> dispatch::Result {
-        <Module<T>>::make_transfer(&U::asset_id(), transactor, dest, value)
+        <Module<T>>::make_transfer(&U::asset_id(), transactor, dest, value).map_err(Into::into)
     }
 
     fn slash(
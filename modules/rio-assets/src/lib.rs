@@ -16,10 +16,10 @@ use sp_runtime::traits::{
     CheckedAdd, CheckedSub, MaybeSerializeDeserialize, Member, Saturating, SimpleArithmetic, Zero,
 };
 use support::{
-    decl_event, decl_module, decl_storage, dispatch::Result, ensure, weights::SimpleDispatchInfo,
+    decl_error, decl_event, decl_module, decl_storage, dispatch::DispatchResult, ensure,
+    weights::SimpleDispatchInfo,
 };
 use support::{
-    dispatch,
     traits::{Currency, ExistenceRequirement, Imbalance, WithdrawReason, WithdrawReasons},
     Parameter,
 };
@@ -172,9 +172,43 @@ decl_storage! {
 }
 
 // The module's dispatchable functions.
+decl_error! {
+    /// Error for the rio-assets module.
+    pub enum Error for Module<T: Trait> {
+        /// the asset id is used already
+        AssetIdExists,
+        /// the asset id is not used yet
+        AssetIdNotExists,
+        /// origin does not have enough permission to update permissions
+        NoUpdatePermission,
+        /// origin does not have permission to mint the asset
+        NoMintPermission,
+        /// origin does not have permission to burn the asset
+        NoBurnPermission,
+        /// balance too low to send amount
+        BalanceTooLow,
+        /// balance too high to receive amount
+        BalanceTooHigh,
+        /// total issuance got overflow after minting
+        TotalIssuanceOverflow,
+        /// free balance got overflow after minting
+        FreeBalanceOverflow,
+        /// total issuance got underflow after burning
+        TotalIssuanceUnderflow,
+        /// free balance got underflow after burning
+        FreeBalanceUnderflow,
+        /// not enough free funds to reserve
+        NotEnoughFreeFunds,
+        /// the asset is restricted for this action
+        Restricted,
+    }
+}
+
 decl_module! {
     /// The module declaration.
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
         // Initializing events
         // this is needed only if you are using events in your module
         pub fn deposit_event() = default;
@@ -182,7 +216,7 @@ decl_module! {
         /// create a new asset with full permissions granted to whoever make the call
         /// *sudo or proposal approved only*
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn create(origin, #[compact] initial_balance: T::Balance, #[compact] asset_id: T::AssetId, symbol: Vec<u8>) -> Result {
+        pub fn create(origin, #[compact] initial_balance: T::Balance, #[compact] asset_id: T::AssetId, symbol: Vec<u8>) -> DispatchResult {
             ensure_root(origin)?;
 
             let root_account_id = T::RootKey::root_key();
@@ -205,7 +239,7 @@ decl_module! {
         /// generic_asset<T>::make_transfer_with_event delegation
         /// wrap 2 hooks around "make_transfer_with_event": T::BeforeAssetTransfer & T::OnAssetTransfer
         #[weight = SimpleDispatchInfo::FixedNormal(0)]
-        pub fn transfer(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount: T::Balance) -> Result {
+        pub fn transfer(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount: T::Balance) -> DispatchResult {
             let o = ensure_signed(origin)?;
             T::BeforeAssetTransfer::before_asset_transfer(&asset_id, &o, &to, &amount)?;
 
@@ -216,7 +250,7 @@ decl_module! {
         }
 
         // generic_asset<T>::update_permission delegation
-        pub fn update_permission(origin, #[compact] asset_id: T::AssetId, new_permission: PermissionLatest<T::AccountId>) -> Result {
+        pub fn update_permission(origin, #[compact] asset_id: T::AssetId, new_permission: PermissionLatest<T::AccountId>) -> DispatchResult {
             ensure_root(origin)?;
 
             let permissions: PermissionVersions<T::AccountId> = new_permission.into();
@@ -229,7 +263,7 @@ decl_module! {
 
                 Ok(())
             } else {
-                Err("Origin does not have enough permission to update permissions.")
+                Err(Error::<T>::NoUpdatePermission.into())
             }
         }
 
@@ -246,7 +280,7 @@ decl_module! {
 
         /// generic_asset<T>::mint delegation
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn mint(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount: T::Balance) -> Result {
+        pub fn mint(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount: T::Balance) -> DispatchResult {
             ensure_root(origin)?;
             let root_account_id = T::RootKey::root_key();
             T::BeforeAssetMint::before_asset_mint(&asset_id, &to, &amount)?;
@@ -261,7 +295,7 @@ decl_module! {
 
         /// generic_asset<T>::burn delegation
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn burn(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount:T::Balance) -> Result {
+        pub fn burn(origin, #[compact] asset_id: T::AssetId, to: T::AccountId, #[compact] amount:T::Balance) -> DispatchResult {
             ensure_root(origin)?;
             let root_account_id = T::RootKey::root_key();
             T::BeforeAssetBurn::before_asset_burn(&asset_id, &to, &amount)?;
@@ -275,7 +309,7 @@ decl_module! {
         }
 
         // #[weight = SimpleDispatchInfo::FreeOperational]
-        // pub fn create_reserved(origin, asset_id: T::AssetId, options: AssetOptions<T::Balance, T::AccountId>) -> Result {
+        // pub fn create_reserved(origin, asset_id: T::AssetId, options: AssetOptions<T::Balance, T::AccountId>) -> DispatchResult {
         //     ensure_root(origin)?;
         //     let root_account_id = T::RootKey::root_key();
         //     Self::create_asset(Some(asset_id), None, options)
@@ -283,7 +317,7 @@ decl_module! {
 
         /// generic_asset<T>::burn delegation
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_reserve(origin, #[compact] asset_id: T::AssetId, who: T::AccountId, #[compact] amount:T::Balance) -> Result {
+        pub fn set_reserve(origin, #[compact] asset_id: T::AssetId, who: T::AccountId, #[compact] amount:T::Balance) -> DispatchResult {
             ensure_root(origin)?;
 
             Self::reserve(&asset_id, &who, amount)?;
@@ -292,7 +326,7 @@ decl_module! {
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_unreserve(origin, #[compact] asset_id: T::AssetId, who: T::AccountId, #[compact] amount:T::Balance) -> Result {
+        pub fn set_unreserve(origin, #[compact] asset_id: T::AssetId, who: T::AccountId, #[compact] amount:T::Balance) -> DispatchResult {
             ensure_root(origin)?;
             Self::unreserve(&asset_id, &who, amount);
             Ok(())
@@ -309,14 +343,14 @@ impl<T: Trait> Module<T> {
     }
     /// check the asset id is not existed
     #[inline]
-    pub fn should_not_exist(asset_id: &T::AssetId) -> dispatch::Result {
-        ensure!(Self::symbols(asset_id).is_none(), "AssetId is exist.");
+    pub fn should_not_exist(asset_id: &T::AssetId) -> DispatchResult {
+        ensure!(Self::symbols(asset_id).is_none(), Error::<T>::AssetIdExists);
         Ok(())
     }
     /// check the asset id is existed
     #[inline]
-    pub fn should_exist(asset_id: &T::AssetId) -> dispatch::Result {
-        ensure!(Self::symbols(asset_id).is_some(), "AssetId is not exist.");
+    pub fn should_exist(asset_id: &T::AssetId) -> DispatchResult {
+        ensure!(Self::symbols(asset_id).is_some(), Error::<T>::AssetIdNotExists);
         Ok(())
     }
 
@@ -347,7 +381,7 @@ impl<T: Trait> Module<T> {
         symbol: Vec<u8>,
         from_account: Option<T::AccountId>,
         options: AssetOptions<T::Balance, T::AccountId>,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         // make sure the asset id is not exist
         Self::should_not_exist(&asset_id)?;
 
@@ -377,12 +411,12 @@ impl<T: Trait> Module<T> {
         from: &T::AccountId,
         to: &T::AccountId,
         amount: T::Balance,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         Self::should_exist(asset_id)?;
 
         let new_balance = Self::free_balance(asset_id, from)
             .checked_sub(&amount)
-            .ok_or_else(|| "balance too low to send amount")?;
+            .ok_or(Error::<T>::BalanceTooLow)?;
         Self::ensure_can_withdraw(
             asset_id,
             from,
@@ -414,7 +448,7 @@ impl<T: Trait> Module<T> {
         from: &T::AccountId,
         to: &T::AccountId,
         amount: T::Balance,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         Self::make_transfer(asset_id, from, to, amount)?;
 
         if from != to {
@@ -436,17 +470,17 @@ impl<T: Trait> Module<T> {
         to: &T::AccountId,
         reasons: WithdrawReasons,
         amount: T::Balance,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         Self::should_exist(from_asset_id)?;
         Self::should_exist(to_asset_id)?;
 
         let new_balance = Self::free_balance(from_asset_id, from)
             .checked_sub(&amount)
-            .ok_or_else(|| "balance too low to send amount")?;
+            .ok_or(Error::<T>::BalanceTooLow)?;
 
         let _to_balance = Self::free_balance(to_asset_id, to)
             .checked_add(&amount)
-            .ok_or_else(|| "balance too height to receive amount")?;
+            .ok_or(Error::<T>::BalanceTooHigh)?;
 
         Self::ensure_can_withdraw(from_asset_id, from, amount, reasons, new_balance)?;
 
@@ -478,7 +512,7 @@ impl<T: Trait> Module<T> {
         who: &T::AccountId,
         to: &T::AccountId,
         amount: &T::Balance,
-    ) -> Result {
+    ) -> DispatchResult {
         Self::should_exist(asset_id)?;
 
         if Self::check_permission(asset_id, who, &PermissionType::Mint) {
@@ -486,10 +520,10 @@ impl<T: Trait> Module<T> {
             let current_total_issuance = <TotalIssuance<T>>::get(asset_id);
             let new_total_issuance = current_total_issuance
                 .checked_add(&amount)
-                .ok_or_else(|| "total_issuance got overflow after minting.")?;
+                .ok_or(Error::<T>::TotalIssuanceOverflow)?;
             let value = original_free_balance
                 .checked_add(&amount)
-                .ok_or_else(|| "free balance got overflow after minting.")?;
+                .ok_or(Error::<T>::FreeBalanceOverflow)?;
             <TotalIssuance<T>>::insert(asset_id, new_total_issuance);
             Self::set_free_balance(&asset_id, &to, value);
 
@@ -505,7 +539,7 @@ impl<T: Trait> Module<T> {
 
             Ok(())
         } else {
-            Err("The origin does not have permission to mint an asset.")
+            Err(Error::<T>::NoMintPermission.into())
         }
     }
 
@@ -515,7 +549,7 @@ impl<T: Trait> Module<T> {
         who: &T::AccountId,
         to: &T::AccountId,
         amount: &T::Balance,
-    ) -> Result {
+    ) -> DispatchResult {
         Self::should_exist(asset_id)?;
 
         if Self::check_permission(&asset_id, &who, &PermissionType::Burn) {
@@ -524,10 +558,10 @@ impl<T: Trait> Module<T> {
             let current_total_issuance = <TotalIssuance<T>>::get(asset_id);
             let new_total_issuance = current_total_issuance
                 .checked_sub(&amount)
-                .ok_or_else(|| "total_issuance got underflow after burning")?;
+                .ok_or(Error::<T>::TotalIssuanceUnderflow)?;
             let value = original_free_balance
                 .checked_sub(&amount)
-                .ok_or_else(|| "free_balance got underflow after burning")?;
+                .ok_or(Error::<T>::FreeBalanceUnderflow)?;
 
             <TotalIssuance<T>>::insert(asset_id, new_total_issuance);
 
@@ -545,7 +579,7 @@ impl<T: Trait> Module<T> {
 
             Ok(())
         } else {
-            Err("The origin does not have permission to burn an asset.")
+            Err(Error::<T>::NoBurnPermission.into())
         }
     }
 
@@ -557,12 +591,12 @@ impl<T: Trait> Module<T> {
         asset_id: &T::AssetId,
         who: &T::AccountId,
         amount: T::Balance,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         // Do we need to consider that this is an atomic transaction?
         let original_reserve_balance = Self::reserved_balance(asset_id, who);
         let original_free_balance = Self::free_balance(asset_id, who);
         if original_free_balance < amount {
-            return Err("not enough free funds");
+            return Err(Error::<T>::NotEnoughFreeFunds.into());
         }
         let new_reserve_balance = original_reserve_balance + amount;
         Self::set_reserved_balance(asset_id, who, new_reserve_balance);
@@ -719,7 +753,7 @@ impl<T: Trait> Module<T> {
         _amount: T::Balance,
         reasons: WithdrawReasons,
         _new_balance: T::Balance,
-    ) -> dispatch::Result {
+    ) -> DispatchResult {
         if reasons.contains(WithdrawReason::Transfer) {
             // check transfer restriction
             permissions::can_do::<T>(asset_id, permissions::Restriction::Transferable)?;
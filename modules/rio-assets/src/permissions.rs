@@ -4,7 +4,7 @@ use codec::{Decode, Encode, Error, Input, Output};
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 use sp_runtime::RuntimeDebug;
-use support::dispatch::Result;
+use support::dispatch::DispatchResult;
 
 use crate::{attributes::Owner, Module, Trait};
 
@@ -103,9 +103,9 @@ pub enum Restriction {
     Transferable,
 }
 
-pub fn can_do<T: Trait>(asset_id: &T::AssetId, restriction: Restriction) -> Result {
+pub fn can_do<T: Trait>(asset_id: &T::AssetId, restriction: Restriction) -> DispatchResult {
     if <Module<T>>::get_restrictions(asset_id).contains_key(&restriction) {
-        Err("The asset is restricted for this action.")
+        Err(crate::Error::<T>::Restricted.into())
     } else {
         Ok(())
     }
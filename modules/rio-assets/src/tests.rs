@@ -22,3 +22,98 @@ use mock::*;
 fn unittest_works() {
     dbg!("hello world");
 }
+
+thread_local! {
+    static HOOK_CALLS: std::cell::RefCell<Vec<&'static str>> = std::cell::RefCell::new(vec![]);
+}
+
+fn hook_calls() -> Vec<&'static str> {
+    HOOK_CALLS.with(|c| c.borrow_mut().drain(..).collect())
+}
+
+struct HookOk;
+impl BeforeAssetTransfer<u32, u64, u128> for HookOk {
+    fn before_asset_transfer(_: &u32, _: &u64, _: &u64, _: &u128) -> DispatchResult {
+        HOOK_CALLS.with(|c| c.borrow_mut().push("ok"));
+        Ok(())
+    }
+}
+impl OnAssetTransfer<u32, u64, u128> for HookOk {
+    fn on_asset_transfer(_: &u32, _: &u64, _: &u64, _: &u128) -> DispatchResult {
+        HOOK_CALLS.with(|c| c.borrow_mut().push("ok"));
+        Ok(())
+    }
+}
+
+struct HookErr;
+impl BeforeAssetTransfer<u32, u64, u128> for HookErr {
+    fn before_asset_transfer(_: &u32, _: &u64, _: &u64, _: &u128) -> DispatchResult {
+        HOOK_CALLS.with(|c| c.borrow_mut().push("err"));
+        Err("hook failed".into())
+    }
+}
+impl OnAssetTransfer<u32, u64, u128> for HookErr {
+    fn on_asset_transfer(_: &u32, _: &u64, _: &u64, _: &u128) -> DispatchResult {
+        HOOK_CALLS.with(|c| c.borrow_mut().push("err"));
+        Err("hook failed".into())
+    }
+}
+
+#[test]
+fn tuple_hooks_work() {
+    type Hooks = (HookOk, HookErr, HookOk);
+
+    // before hooks stop at the first error
+    assert_eq!(
+        <Hooks as BeforeAssetTransfer<u32, u64, u128>>::before_asset_transfer(&1, &2, &3, &4),
+        Err("hook failed".into())
+    );
+    assert_eq!(hook_calls(), vec!["ok", "err"]);
+
+    // on hooks are all called
+    assert_eq!(
+        <Hooks as OnAssetTransfer<u32, u64, u128>>::on_asset_transfer(&1, &2, &3, &4),
+        Err("hook failed".into())
+    );
+    assert_eq!(hook_calls(), vec!["ok", "err", "ok"]);
+
+    type Many = (HookOk, HookOk, HookOk, HookOk, HookOk, HookOk, HookOk, HookOk, HookOk, HookOk);
+    assert_ok!(<Many as OnAssetTransfer<u32, u64, u128>>::on_asset_transfer(&1, &2, &3, &4));
+    assert_eq!(hook_calls().len(), 10);
+}
+
+#[test]
+fn typed_errors_work() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            RioAssets::make_transfer(&1, &2, &3, 10),
+            Error::<TestRuntime>::AssetIdNotExists
+        );
+        let options = AssetOptions {
+            initial_issuance: 5,
+            permissions: PermissionLatest {
+                update: Owner::Address(2),
+                mint: Owner::Address(2),
+                burn: Owner::Address(2),
+            },
+        };
+        assert_ok!(RioAssets::create_asset(
+            1,
+            b"RBTC".to_vec(),
+            Some(2),
+            options.clone()
+        ));
+        assert_noop!(
+            RioAssets::create_asset(1, b"RBTC".to_vec(), Some(2), options),
+            Error::<TestRuntime>::AssetIdExists
+        );
+        assert_noop!(
+            RioAssets::make_transfer(&1, &2, &3, 10),
+            Error::<TestRuntime>::BalanceTooLow
+        );
+        assert_noop!(
+            RioAssets::mint_free(&1, &3, &3, &10),
+            Error::<TestRuntime>::NoMintPermission
+        );
+    });
+}
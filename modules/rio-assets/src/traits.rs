@@ -1,4 +1,4 @@
-use super::Result;
+use super::DispatchResult as Result;
 
 pub trait AssetIdProvider {
     type AssetId;
@@ -85,3 +85,107 @@ impl<A, B, C> BeforeAssetMint<A, B, C> for () {}
 impl<A, B, C> OnAssetMint<A, B, C> for () {}
 impl<A, B, C> BeforeAssetTransfer<A, B, C> for () {}
 impl<A, B, C> OnAssetTransfer<A, B, C> for () {}
+
+/// implement the hooks for a tuple, so that a runtime can subscribe multiple modules to one hook
+/// `Before*` hooks are called in order and stop at the first error,
+/// `On*` hooks are all called and the first error is returned
+macro_rules! impl_hooks_for_tuple {
+    ($($hook:ident),+) => {
+        impl<AssetId, $($hook: BeforeAssetCreate<AssetId>),+> BeforeAssetCreate<AssetId>
+            for ($($hook,)+)
+        {
+            fn before_asset_create(asset_id: &AssetId) -> Result {
+                $($hook::before_asset_create(asset_id)?;)+
+                Ok(())
+            }
+        }
+
+        impl<AssetId, $($hook: OnAssetCreate<AssetId>),+> OnAssetCreate<AssetId> for ($($hook,)+) {
+            fn on_asset_create(asset_id: &AssetId) -> Result {
+                let mut result = Ok(());
+                $(result = result.and($hook::on_asset_create(asset_id));)+
+                result
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: BeforeAssetTransfer<AssetId, AccountId, Balance>),+>
+            BeforeAssetTransfer<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn before_asset_transfer(
+                asset_id: &AssetId,
+                from: &AccountId,
+                to: &AccountId,
+                balance: &Balance,
+            ) -> Result {
+                $($hook::before_asset_transfer(asset_id, from, to, balance)?;)+
+                Ok(())
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: OnAssetTransfer<AssetId, AccountId, Balance>),+>
+            OnAssetTransfer<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn on_asset_transfer(
+                asset_id: &AssetId,
+                from: &AccountId,
+                to: &AccountId,
+                balance: &Balance,
+            ) -> Result {
+                let mut result = Ok(());
+                $(result = result.and($hook::on_asset_transfer(asset_id, from, to, balance));)+
+                result
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: BeforeAssetMint<AssetId, AccountId, Balance>),+>
+            BeforeAssetMint<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn before_asset_mint(asset_id: &AssetId, to: &AccountId, balance: &Balance) -> Result {
+                $($hook::before_asset_mint(asset_id, to, balance)?;)+
+                Ok(())
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: OnAssetMint<AssetId, AccountId, Balance>),+>
+            OnAssetMint<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn on_asset_mint(asset_id: &AssetId, to: &AccountId, balance: &Balance) -> Result {
+                let mut result = Ok(());
+                $(result = result.and($hook::on_asset_mint(asset_id, to, balance));)+
+                result
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: BeforeAssetBurn<AssetId, AccountId, Balance>),+>
+            BeforeAssetBurn<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn before_asset_burn(asset_id: &AssetId, to: &AccountId, balance: &Balance) -> Result {
+                $($hook::before_asset_burn(asset_id, to, balance)?;)+
+                Ok(())
+            }
+        }
+
+        impl<AssetId, AccountId, Balance, $($hook: OnAssetBurn<AssetId, AccountId, Balance>),+>
+            OnAssetBurn<AssetId, AccountId, Balance> for ($($hook,)+)
+        {
+            fn on_asset_burn(asset_id: &AssetId, to: &AccountId, balance: &Balance) -> Result {
+                let mut result = Ok(());
+                $(result = result.and($hook::on_asset_burn(asset_id, to, balance));)+
+                result
+            }
+        }
+    };
+}
+
+/// implement the hooks for the tuple and all the shorter ones
+macro_rules! impl_hooks_for_tuples {
+    ($first:ident) => {
+        impl_hooks_for_tuple!($first);
+    };
+    ($first:ident, $($rest:ident),+) => {
+        impl_hooks_for_tuple!($first, $($rest),+);
+        impl_hooks_for_tuples!($($rest),+);
+    };
+}
+
+impl_hooks_for_tuples!(H1, H2, H3, H4, H5, H6, H7, H8, H9, H10, H11, H12);
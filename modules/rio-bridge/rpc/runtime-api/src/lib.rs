@@ -0,0 +1,37 @@
+//! Runtime API definition for the rio-bridge module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use rstd::prelude::*;
+use rstd::vec;
+use sp_runtime::RuntimeDebug;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub use rio_support::breaker::{Breaker, TripReason};
+
+/// functions of the bridge which can be paused by their own circuit breaker
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum BridgeFunction {
+    /// deposits and the pending deposits released by KYC, which mint SBTC
+    Deposit,
+    /// withdraw requests and their completion
+    Withdraw,
+}
+impl BridgeFunction {
+    pub fn all() -> Vec<BridgeFunction> {
+        vec![BridgeFunction::Deposit, BridgeFunction::Withdraw]
+    }
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait BridgeApi<BlockNumber> where
+        BlockNumber: Codec,
+    {
+        /// breakers which are still tripped
+        fn breakers() -> Vec<(BridgeFunction, Breaker<BlockNumber>)>;
+    }
+}
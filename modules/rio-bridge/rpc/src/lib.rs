@@ -0,0 +1,79 @@
+//! RPC interface for the rio-bridge module.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use rio_bridge_rpc_runtime_api::{BridgeApi as BridgeRuntimeApi, BridgeFunction, Breaker};
+
+#[rpc]
+pub trait BridgeApi<BlockHash, BlockNumber> {
+    /// circuit breakers which are still tripped
+    #[rpc(name = "riobridge_breakers")]
+    fn breakers(
+        &self,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(BridgeFunction, Breaker<BlockNumber>)>>;
+}
+
+/// A struct that implements the [`BridgeApi`].
+pub struct Bridge<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Bridge<C, B> {
+    /// Create new `Bridge` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Bridge {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+    /// The call to runtime failed.
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+fn runtime_error<E: std::fmt::Debug>(message: &'static str) -> impl FnOnce(E) -> RpcError {
+    move |e| RpcError {
+        code: ErrorCode::ServerError(Error::RuntimeError.into()),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, BlockNumber> BridgeApi<<Block as BlockT>::Hash, BlockNumber> for Bridge<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+    C::Api: BridgeRuntimeApi<Block, BlockNumber>,
+    BlockNumber: Codec,
+{
+    fn breakers(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(BridgeFunction, Breaker<BlockNumber>)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.breakers(&at)
+            .map_err(runtime_error("Unable to query bridge breakers."))
+    }
+}
@@ -6,10 +6,17 @@ use primitives::H256;
 use rstd::prelude::*;
 use sp_runtime::RuntimeDebug;
 use support::{
-    decl_event, decl_module, decl_storage, dispatch::Result, ensure, weights::SimpleDispatchInfo,
+    decl_error, decl_event, decl_module, decl_storage, dispatch::DispatchResult, ensure,
+    traits::Get, weights::SimpleDispatchInfo,
 };
 use system::{ensure_root, ensure_signed};
 
+pub use rio_bridge_rpc_runtime_api::BridgeFunction;
+pub use rio_support::breaker::{Breaker, TripReason};
+use rio_support::breaker::{Breakers, TripBreakers};
+pub use rio_support::timelock::{ParamChange, ParamChangeId};
+use rio_support::timelock::Timelock;
+
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
@@ -60,8 +67,42 @@ pub struct Deposit<AccountId, Balance> {
     pub amount: Balance,
 }
 
+/// admin changes, which only apply through the timelocked queue
+#[derive(Encode, Decode, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum BridgeParam<AccountId> {
+    /// add the admin or change its auth
+    UpdateAdmin(AccountId, Auth),
+    DeleteAdmin(AccountId),
+}
+
 pub trait Trait: system::Trait + rio_assets::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// blocks an admin change waits in the queue
+    type ParamChangeDelay: Get<Self::BlockNumber>;
+}
+
+type BridgeBreakers<T> =
+    Breakers<FunctionBreakers<T>, BridgeFunction, <T as system::Trait>::BlockNumber>;
+type BridgeTimelock<T> = Timelock<
+    PendingParamChanges<T>,
+    NextParamChangeId,
+    BridgeParam<<T as system::Trait>::AccountId>,
+    <T as system::Trait>::BlockNumber,
+>;
+
+/// KYC status of an account for other modules, None if it's not in the list
+pub trait KycStatus<AccountId> {
+    fn kyc_status(_who: &AccountId) -> Option<BlackOrWhite> {
+        None
+    }
+}
+impl<AccountId> KycStatus<AccountId> for () {}
+
+impl<T: Trait> KycStatus<T::AccountId> for Module<T> {
+    fn kyc_status(who: &T::AccountId) -> Option<BlackOrWhite> {
+        Self::check_list(who)
+    }
 }
 
 decl_storage! {
@@ -70,6 +111,10 @@ decl_storage! {
         AssetId get(asset_id) config() : T::AssetId;
         /// module level switch
         Paused get(paused) : bool = false;
+        /// circuit breakers of each function, a tripped function is paused while the others keep running
+        FunctionBreakers get(function_breaker) : map BridgeFunction => Option<Breaker<T::BlockNumber>>;
+        /// tripped breakers resume by themselves after this many blocks if set
+        BreakerTimeout get(breaker_timeout) : Option<T::BlockNumber>;
         /// KYC list
         List get(list) : linked_map T::AccountId => BlackOrWhite;
         /// deposit amount above this amount will trigger KYC
@@ -86,6 +131,9 @@ decl_storage! {
         PendingWithdraws get(pending_withdraws) : linked_map T::AccountId => Vec<T::Balance>;
         /// withdraw balance should be put here
         PendingWithdrawVault get(pending_withdraw_vault) config() : T::AccountId;
+        /// admin changes waiting for their block
+        PendingParamChanges get(pending_param_change) : linked_map ParamChangeId => Option<ParamChange<BridgeParam<T::AccountId>, T::BlockNumber>>;
+        NextParamChangeId get(next_param_change_id) : ParamChangeId;
     }
 
     add_extra_genesis {
@@ -93,52 +141,112 @@ decl_storage! {
     }
 }
 
+decl_error! {
+    /// Error for the rio-bridge module.
+    pub enum Error for Module<T: Trait> {
+        /// the deposit transaction has been seen already
+        RepeatedTransaction,
+        /// the account has no deposit auth
+        NoDepositAuth,
+        /// the account has no refund auth
+        NoRefundAuth,
+        /// the account has no withdraw auth
+        NoWithdrawAuth,
+        /// the account has no mark auth
+        NoMarkAuth,
+        /// no pending withdraw of the amount
+        PendingWithdrawNotFound,
+        /// no pending admin change of the id
+        InvalidParamChangeId,
+        /// the circuit breaker of the function is tripped
+        FunctionTripped,
+    }
+}
+
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
         fn deposit_event() = default;
 
+        const ParamChangeDelay: T::BlockNumber = T::ParamChangeDelay::get();
+
+        fn on_initialize(height: T::BlockNumber) {
+            for (id, param) in BridgeTimelock::<T>::take_due(height) {
+                Self::apply_param_change(param);
+                Self::deposit_event(RawEvent::ParamChangeApplied(id));
+            }
+        }
+
         #[weight = SimpleDispatchInfo::MaxNormal]
-        pub fn pause(origin) -> Result {
+        pub fn pause(origin) -> DispatchResult {
             ensure_root(origin)?;
             Paused::mutate(|v| *v = true);
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::MaxNormal]
-        pub fn resume(origin) -> Result {
+        pub fn resume(origin) -> DispatchResult {
             ensure_root(origin)?;
             Paused::mutate(|v| *v = false);
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn trip_breaker(origin, function: BridgeFunction) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::trip(function, TripReason::Manual);
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn reset_breaker(origin, function: BridgeFunction) -> DispatchResult {
+            ensure_root(origin)?;
+            BridgeBreakers::<T>::reset(function);
+            Self::deposit_event(RawEvent::BreakerReset(function));
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_breaker_timeout(origin, timeout: Option<T::BlockNumber>) -> DispatchResult {
+            ensure_root(origin)?;
+            match timeout {
+                Some(t) => <BreakerTimeout<T>>::put(t),
+                None => <BreakerTimeout<T>>::kill(),
+            }
+            Ok(())
+        }
+
+        /// the change applies after ParamChangeDelay blocks, pause and resume stay immediate
         #[weight = SimpleDispatchInfo::MaxOperational]
-        pub fn delete_admin(origin, who: T::AccountId) -> Result {
+        pub fn schedule_param_change(origin, param: BridgeParam<T::AccountId>) -> DispatchResult {
             ensure_root(origin)?;
-            <Admins<T>>::remove(who);
+            let (id, eta) = BridgeTimelock::<T>::schedule(
+                param,
+                <system::Module<T>>::block_number(),
+                T::ParamChangeDelay::get(),
+            );
+            Self::deposit_event(RawEvent::ParamChangeScheduled(id, eta));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::MaxOperational]
-        pub fn update_admin(origin, who: T::AccountId, auth: Auth) -> Result {
+        pub fn cancel_param_change(origin, id: ParamChangeId) -> DispatchResult {
             ensure_root(origin)?;
-            if <Admins<T>>::exists(&who) {
-                <Admins<T>>::mutate(&who, |v| {
-                    *v = auth;
-                });
-            } else {
-                <Admins<T>>::insert(&who, auth);
-            }
+            ensure!(BridgeTimelock::<T>::cancel(id), Error::<T>::InvalidParamChangeId);
+            Self::deposit_event(RawEvent::ParamChangeCancelled(id));
             Ok(())
         }
 
         /// TODO:: hash(tx_hash + account_id) as deposit identity
         /// TODO:: use offchain worker to do some verification on BTC
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn deposit(origin, account_id: T::AccountId, amount: T::Balance, tx_hash: TxHash) -> Result {
-            ensure!(!Self::is_tx_seen(&tx_hash), "repeated transaction");
+        pub fn deposit(origin, account_id: T::AccountId, amount: T::Balance, tx_hash: TxHash) -> DispatchResult {
+            ensure!(!Self::is_tx_seen(&tx_hash), Error::<T>::RepeatedTransaction);
+            Self::ensure_not_tripped(BridgeFunction::Deposit)?;
 
             let who = ensure_signed(origin)?;
-            ensure!(Self::has_auth(&who, Auth::Deposit), "no deposit auth");
+            ensure!(Self::has_auth(&who, Auth::Deposit), Error::<T>::NoDepositAuth);
             if amount >= Self::threshold() {
                 let bow = Self::check_list(&account_id);
                 if bow.is_none() || bow.unwrap() == BlackOrWhite::Black {
@@ -151,11 +259,11 @@ decl_module! {
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn refund(origin, who: T::AccountId, amount: T::Balance) -> Result {
+        pub fn refund(origin, who: T::AccountId, amount: T::Balance) -> DispatchResult {
             let author = ensure_signed(origin)?;
             let asset_id = Self::asset_id();
-            ensure!(Self::has_auth(&author, Auth::Refund), "no refund auth");
-            ensure!(Self::pending_withdraws(&who).contains(&amount), "pending withdraw not found");
+            ensure!(Self::has_auth(&author, Auth::Refund), Error::<T>::NoRefundAuth);
+            ensure!(Self::pending_withdraws(&who).contains(&amount), Error::<T>::PendingWithdrawNotFound);
             <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &Self::pending_withdraw_vault(), &who, amount)?;
             Self::remove_from_pending_withdraws(who.clone(), amount);
             Self::deposit_event(RawEvent::Refund(who, amount));
@@ -163,21 +271,23 @@ decl_module! {
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn withdraw_finish(origin, who: T::AccountId, amount: T::Balance) -> Result {
+        pub fn withdraw_finish(origin, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+            Self::ensure_not_tripped(BridgeFunction::Withdraw)?;
             let author = ensure_signed(origin)?;
             let asset_id = Self::asset_id();
-            ensure!(Self::has_auth(&author, Auth::Withdraw), "no withdraw auth");
-            ensure!(Self::pending_withdraws(&who).contains(&amount), "pending withdraw not found");
+            ensure!(Self::has_auth(&author, Auth::Withdraw), Error::<T>::NoWithdrawAuth);
+            ensure!(Self::pending_withdraws(&who).contains(&amount), Error::<T>::PendingWithdrawNotFound);
             Self::remove_from_pending_withdraws(who.clone(), amount);
             Self::deposit_event(RawEvent::Withdraw(who, amount));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn withdraw(origin, who: T::AccountId, amount: T::Balance) -> Result {
+        pub fn withdraw(origin, who: T::AccountId, amount: T::Balance) -> DispatchResult {
+            Self::ensure_not_tripped(BridgeFunction::Withdraw)?;
             let author = ensure_signed(origin)?;
             let asset_id = Self::asset_id();
-            ensure!(Self::has_auth(&author, Auth::Withdraw), "no withdraw auth");
+            ensure!(Self::has_auth(&author, Auth::Withdraw), Error::<T>::NoWithdrawAuth);
             <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &who, &Self::pending_withdraw_vault(), amount)?;
             if <PendingWithdraws<T>>::exists(&who) {
                 <PendingWithdraws<T>>::mutate(&who, |v| {
@@ -192,9 +302,9 @@ decl_module! {
 
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn mark_black(origin, account_id: T::AccountId) -> Result {
+        pub fn mark_black(origin, account_id: T::AccountId) -> DispatchResult {
             let who = ensure_signed(origin)?;
-            ensure!(Self::has_auth(&who, Auth::Mark), "no mark auth");
+            ensure!(Self::has_auth(&who, Auth::Mark), Error::<T>::NoMarkAuth);
             let pendings = <PendingDepositList<T>>::take(&account_id);
             if pendings.len() > 0 {
                 pendings.iter().for_each(|v| {
@@ -206,14 +316,16 @@ decl_module! {
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn mark_white(origin, account_id: T::AccountId) -> Result {
+        pub fn mark_white(origin, account_id: T::AccountId) -> DispatchResult {
             let who = ensure_signed(origin)?;
-            ensure!(Self::has_auth(&who, Auth::Mark), "no mark auth");
+            ensure!(Self::has_auth(&who, Auth::Mark), Error::<T>::NoMarkAuth);
+            // the pending deposits are minted on the way
+            Self::ensure_not_tripped(BridgeFunction::Deposit)?;
             let mut pendings = &<PendingDepositList<T>>::take(&account_id)[..];
             while pendings.len() > 0 {
                 pendings[0].tx_hash.unwrap();
                 Self::simple_deposit_with_event(&pendings[0].account_id,
-                                                pendings[0].amount, pendings[0].tx_hash.unwrap()).or_else(|err| -> Result {
+                                                pendings[0].amount, pendings[0].tx_hash.unwrap()).or_else(|err| -> DispatchResult {
                         <PendingDepositList<T>>::insert(&account_id, pendings);
                         Err(err)
                     })?;
@@ -231,6 +343,7 @@ decl_event!(
     where
         AccountId = <T as system::Trait>::AccountId,
         Balance = <T as rio_assets::Trait>::Balance,
+        BlockNumber = <T as system::Trait>::BlockNumber,
     {
         AccountMarked(AccountId, BlackOrWhite),
         Deposit(AccountId, Balance, TxHash),
@@ -238,10 +351,61 @@ decl_event!(
         PendingWithdraw(AccountId, Balance),
         Refund(AccountId, Balance),
         Withdraw(AccountId, Balance),
+        // (change id, the block it applies)
+        ParamChangeScheduled(ParamChangeId, BlockNumber),
+        ParamChangeCancelled(ParamChangeId),
+        ParamChangeApplied(ParamChangeId),
+        // (function, reason, the block it resumes by itself)
+        BreakerTripped(BridgeFunction, TripReason, Option<BlockNumber>),
+        BreakerReset(BridgeFunction),
     }
 );
 
 impl<T: Trait> Module<T> {
+    /// pending admin changes in the order they apply
+    pub fn pending_param_changes(
+    ) -> Vec<(ParamChangeId, ParamChange<BridgeParam<T::AccountId>, T::BlockNumber>)> {
+        BridgeTimelock::<T>::pending()
+    }
+
+    pub fn apply_param_change(param: BridgeParam<T::AccountId>) {
+        match param {
+            BridgeParam::UpdateAdmin(who, auth) => <Admins<T>>::insert(&who, auth),
+            BridgeParam::DeleteAdmin(who) => <Admins<T>>::remove(&who),
+        }
+    }
+
+    /// trip the breaker of `function`, it resumes by itself if BreakerTimeout is set
+    fn trip(function: BridgeFunction, reason: TripReason) {
+        let breaker = BridgeBreakers::<T>::trip(
+            function,
+            reason,
+            <system::Module<T>>::block_number(),
+            Self::breaker_timeout(),
+        );
+        Self::deposit_event(RawEvent::BreakerTripped(function, reason, breaker.resume_at));
+    }
+
+    /// Immutable
+    pub fn is_tripped(function: BridgeFunction) -> bool {
+        BridgeBreakers::<T>::is_tripped(function, <system::Module<T>>::block_number())
+    }
+
+    fn ensure_not_tripped(function: BridgeFunction) -> DispatchResult {
+        ensure!(!Self::is_tripped(function), Error::<T>::FunctionTripped);
+        Ok(())
+    }
+
+    /// Immutable
+    /// backs `BridgeApi::breakers`
+    pub fn breakers() -> Vec<(BridgeFunction, Breaker<T::BlockNumber>)> {
+        let now = <system::Module<T>>::block_number();
+        BridgeFunction::all()
+            .into_iter()
+            .filter_map(|f| BridgeBreakers::<T>::get(f, now).map(|b| (f, b)))
+            .collect()
+    }
+
     fn is_tx_seen(tx_hash: &TxHash) -> bool {
         <DepositHistory<T>>::exists(tx_hash)
     }
@@ -250,7 +414,7 @@ impl<T: Trait> Module<T> {
         account_id: &T::AccountId,
         amount: T::Balance,
         tx_hash: TxHash,
-    ) -> Result {
+    ) -> DispatchResult {
         let dep: Deposit<T::AccountId, T::Balance> = Deposit {
             tx_hash: None,
             amount,
@@ -271,7 +435,7 @@ impl<T: Trait> Module<T> {
         account_id: &T::AccountId,
         amount: T::Balance,
         tx_hash: TxHash,
-    ) -> Result {
+    ) -> DispatchResult {
         let mut dep: Deposit<T::AccountId, T::Balance> = Deposit {
             tx_hash: None,
             amount,
@@ -328,3 +492,12 @@ impl<T: Trait> Module<T> {
         }
     }
 }
+
+/// nothing on the bridge goes wrong by itself, all of its functions are tripped for any reason
+impl<T: Trait> TripBreakers for Module<T> {
+    fn trip_breakers(reason: TripReason) {
+        for function in BridgeFunction::all() {
+            Self::trip(function, reason);
+        }
+    }
+}
@@ -191,8 +191,12 @@ impl rio_assets::Trait for TestRuntime {
     type BeforeAssetTransfer = ();
     type BeforeAssetBurn = ();
 }
+parameter_types! {
+    pub const ParamChangeDelay: u64 = 10;
+}
 impl Trait for TestRuntime {
     type Event = ();
+    type ParamChangeDelay = ParamChangeDelay;
 }
 
 pub type RioBridgeTest = Module<TestRuntime>;
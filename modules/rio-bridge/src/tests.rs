@@ -40,6 +40,37 @@ fn resume_works() {
     });
 }
 
+#[test]
+fn admin_changes_are_timelocked() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioBridgeTest::schedule_param_change(
+            Origin::ROOT,
+            BridgeParam::UpdateAdmin(ALICE, Auth::Deposit)
+        ));
+        let delete_change = RioBridgeTest::next_param_change_id();
+        assert_ok!(RioBridgeTest::schedule_param_change(
+            Origin::ROOT,
+            BridgeParam::DeleteAdmin(ROOT)
+        ));
+        assert_ok!(RioBridgeTest::cancel_param_change(
+            Origin::ROOT,
+            delete_change
+        ));
+        assert_noop!(
+            RioBridgeTest::cancel_param_change(Origin::ROOT, delete_change),
+            Error::<TestRuntime>::InvalidParamChangeId
+        );
+        assert_eq!(RioBridgeTest::pending_param_changes()[0].1.eta, 10);
+
+        RioBridgeTest::on_initialize(9);
+        assert!(!<Admins<TestRuntime>>::exists(&ALICE));
+        RioBridgeTest::on_initialize(10);
+        assert_eq!(RioBridgeTest::admins(&ALICE), Auth::Deposit);
+        assert_eq!(RioBridgeTest::admins(&ROOT), Auth::All);
+        assert!(RioBridgeTest::pending_param_changes().is_empty());
+    });
+}
+
 #[test]
 fn deposit_auth_works() {
     ExtBuilder::default().build().execute_with(|| {
@@ -49,7 +80,7 @@ fn deposit_auth_works() {
         let orig = Origin::signed(ALICE);
         assert_noop!(
             RioBridgeTest::deposit(orig, who, amount, tx_hash),
-            "no deposit auth"
+            Error::<TestRuntime>::NoDepositAuth
         );
     });
 }
@@ -80,7 +111,7 @@ fn deposit_repeated_tx_hash_works() {
         let amount = 2_00000000;
         assert_noop!(
             RioBridgeTest::deposit(orig, who, amount, tx_hash),
-            "repeated transaction"
+            Error::<TestRuntime>::RepeatedTransaction
         );
     });
 }
@@ -303,3 +334,37 @@ fn two_withdraw_of_same_amount_refund_works() {
         );
     });
 }
+
+#[test]
+fn circuit_breaker_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let orig = Origin::signed(ROOT);
+        assert_ok!(RioBridgeTest::trip_breaker(
+            Origin::ROOT,
+            BridgeFunction::Deposit
+        ));
+        assert!(!RioBridgeTest::is_tripped(BridgeFunction::Withdraw));
+        assert_noop!(
+            RioBridgeTest::deposit(orig.clone(), DAVE, 1_00000000, TxHash::default()),
+            Error::<TestRuntime>::FunctionTripped
+        );
+        assert_ok!(RioBridgeTest::reset_breaker(
+            Origin::ROOT,
+            BridgeFunction::Deposit
+        ));
+        assert_ok!(RioBridgeTest::deposit(
+            orig,
+            DAVE,
+            1_00000000,
+            TxHash::default()
+        ));
+
+        // a reserve deficit stops both ways
+        <RioBridgeTest as TripBreakers>::trip_breakers(TripReason::ReserveDeficit);
+        assert_eq!(RioBridgeTest::breakers().len(), 2);
+        assert_eq!(
+            RioBridgeTest::breakers()[0].1.reason,
+            TripReason::ReserveDeficit
+        );
+    });
+}
@@ -0,0 +1,147 @@
+//! Runtime API definition for the rio-loan module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use rstd::prelude::*;
+use rstd::vec;
+use sp_runtime::RuntimeDebug;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub use rio_support::breaker::{Breaker, TripReason};
+
+/// keep in line with `rio_loan::LoanId`
+pub type LoanId = u64;
+/// keep in line with `rio_loan::LoanPackageId`
+pub type LoanPackageId = u64;
+/// keep in line with `rio_loan::LTV`
+pub type LTV = u64;
+/// keep in line with `rio_price::Price`
+pub type Price = u128;
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum LoanHealth {
+    Well,
+    Warning(LTV),
+    Liquidating(LTV),
+    Extended,
+    Expired,
+}
+impl Default for LoanHealth {
+    fn default() -> Self {
+        Self::Well
+    }
+}
+
+/// a loan of an account, evaluated at the current prices and time
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct LoanPosition<Balance, AssetId, Moment> {
+    pub loan_id: LoanId,
+    pub package_id: LoanPackageId,
+    /// the package's collateral asset goes first
+    pub collaterals: Vec<(AssetId, Balance)>,
+    /// loan balance including the interest not settled yet
+    pub debt: Balance,
+    pub ltv: LTV,
+    /// what `on_each_block` would find if it checked the loan now
+    pub health: LoanHealth,
+    /// price of the package's collateral asset at which the loan gets liquidating, None if it never does
+    pub liquidation_price: Option<Price>,
+    /// milliseconds till `due`, zero if already due
+    pub time_to_due: Moment,
+    /// milliseconds till `due_extend`, zero if already expired
+    pub time_to_due_extend: Moment,
+    /// how much more can be drawn before hitting the LTV limit
+    pub drawable: Balance,
+}
+
+/// what `apply` gives for an amount of collateral in a package
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct LoanQuote<Balance> {
+    pub collateral_amount: Balance,
+    /// the largest loan the collateral allows, as `get_collateral_loan` computes it
+    pub loan_amount: Balance,
+    /// charged upfront, zero under the interest model
+    pub interest: Balance,
+    /// `loan_amount - interest`, what the borrower receives
+    pub received: Balance,
+    pub liquidation_price: Option<Price>,
+}
+
+/// how well the loans are backed, in terms of the loan asset
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Solvency<Balance> {
+    pub total_loan: Balance,
+    /// collateral of the packages' asset in the pawn shop
+    pub total_collateral: Balance,
+    /// baskets of the loans and pledges of the credit lines at the prices of their assets
+    pub collateral_value: Balance,
+    /// shortfalls of liquidations not covered by the stability fund yet
+    pub bad_debt: Balance,
+    /// loan asset held by the stability fund
+    pub stability_fund: Balance,
+    /// no bad debt and the loans are fully backed by the collateral
+    pub solvent: bool,
+}
+
+/// the loan asset as a stablecoin backed by the collateral
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SystemDebt<Balance, AssetId> {
+    /// loan asset minted by the loans and the credit lines
+    pub total_debt: Balance,
+    /// collateral of the loans and pledges of the credit lines at the current prices
+    pub collateral_value: Balance,
+    /// `collateral_value / total_debt` in terms of LTV_PREC, None if there is no debt
+    pub collateralization_ratio: Option<LTV>,
+    /// loan asset held by the stability fund
+    pub surplus: Balance,
+    /// bad debt not covered by the stability fund yet
+    pub deficit: Balance,
+    /// (collateral asset, debt against it, debt ceiling)
+    pub debt_by_collateral: Vec<(AssetId, Balance, Option<Balance>)>,
+}
+
+/// functions of rio-loan which can be paused by their own circuit breaker
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum LoanFunction {
+    /// apply, draw, rollover and draw_credit, which issue the loan asset
+    Borrow,
+    /// mark_liquidated, bid, liquidate and liquidate_credit_line
+    Liquidation,
+}
+impl LoanFunction {
+    pub fn all() -> Vec<LoanFunction> {
+        vec![LoanFunction::Borrow, LoanFunction::Liquidation]
+    }
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait LoanApi<AccountId, Balance, AssetId, Moment, BlockNumber> where
+        AccountId: Codec,
+        Balance: Codec,
+        AssetId: Codec,
+        Moment: Codec,
+        BlockNumber: Codec,
+    {
+        fn solvency() -> Solvency<Balance>;
+        fn system_debt() -> SystemDebt<Balance, AssetId>;
+        /// loans of an account in `LoansByAccount`
+        fn loan_positions(who: AccountId) -> Vec<LoanPosition<Balance, AssetId, Moment>>;
+        /// None if the account can't apply for a loan with the collateral in the package
+        fn quote_apply(who: AccountId, collateral_amount: Balance, package_id: LoanPackageId) -> Option<LoanQuote<Balance>>;
+        /// breakers which are still tripped
+        fn breakers() -> Vec<(LoanFunction, Breaker<BlockNumber>)>;
+    }
+}
@@ -0,0 +1,159 @@
+//! RPC interface for the rio-loan module.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use rio_loan_rpc_runtime_api::{
+    Breaker, LoanApi as LoanRuntimeApi, LoanFunction, LoanHealth, LoanPackageId, LoanPosition,
+    LoanQuote, Solvency, SystemDebt,
+};
+
+#[rpc]
+pub trait LoanApi<BlockHash, AccountId, Balance, AssetId, Moment, BlockNumber> {
+    /// loans of an account with their LTV, health, liquidation price and drawable amount
+    #[rpc(name = "rioloan_positions")]
+    fn loan_positions(
+        &self,
+        who: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<LoanPosition<Balance, AssetId, Moment>>>;
+
+    /// the loan an amount of collateral gets in a package for an account
+    #[rpc(name = "rioloan_quoteApply")]
+    fn quote_apply(
+        &self,
+        who: AccountId,
+        collateral_amount: Balance,
+        package_id: LoanPackageId,
+        at: Option<BlockHash>,
+    ) -> Result<Option<LoanQuote<Balance>>>;
+
+    /// how well the loans are backed
+    #[rpc(name = "rioloan_solvency")]
+    fn solvency(&self, at: Option<BlockHash>) -> Result<Solvency<Balance>>;
+
+    /// total debt, collateralization ratio and the buffer of the loan asset
+    #[rpc(name = "rioloan_systemDebt")]
+    fn system_debt(&self, at: Option<BlockHash>) -> Result<SystemDebt<Balance, AssetId>>;
+
+    /// circuit breakers which are still tripped
+    #[rpc(name = "rioloan_breakers")]
+    fn breakers(
+        &self,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(LoanFunction, Breaker<BlockNumber>)>>;
+}
+
+/// A struct that implements the [`LoanApi`].
+pub struct Loan<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Loan<C, B> {
+    /// Create new `Loan` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Loan {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+    /// The call to runtime failed.
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+fn runtime_error<E: std::fmt::Debug>(message: &'static str) -> impl FnOnce(E) -> RpcError {
+    move |e| RpcError {
+        code: ErrorCode::ServerError(Error::RuntimeError.into()),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, AccountId, Balance, AssetId, Moment, BlockNumber>
+    LoanApi<<Block as BlockT>::Hash, AccountId, Balance, AssetId, Moment, BlockNumber>
+    for Loan<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+    C::Api: LoanRuntimeApi<Block, AccountId, Balance, AssetId, Moment, BlockNumber>,
+    AccountId: Codec,
+    Balance: Codec,
+    AssetId: Codec,
+    Moment: Codec,
+    BlockNumber: Codec,
+{
+    fn loan_positions(
+        &self,
+        who: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<LoanPosition<Balance, AssetId, Moment>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.loan_positions(&at, who)
+            .map_err(runtime_error("Unable to query loan positions."))
+    }
+
+    fn quote_apply(
+        &self,
+        who: AccountId,
+        collateral_amount: Balance,
+        package_id: LoanPackageId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Option<LoanQuote<Balance>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.quote_apply(&at, who, collateral_amount, package_id)
+            .map_err(runtime_error("Unable to quote the loan."))
+    }
+
+    fn solvency(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Solvency<Balance>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.solvency(&at)
+            .map_err(runtime_error("Unable to query loan solvency."))
+    }
+
+    fn system_debt(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<SystemDebt<Balance, AssetId>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.system_debt(&at)
+            .map_err(runtime_error("Unable to query the system debt."))
+    }
+
+    fn breakers(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(LoanFunction, Breaker<BlockNumber>)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.breakers(&at)
+            .map_err(runtime_error("Unable to query loan breakers."))
+    }
+}
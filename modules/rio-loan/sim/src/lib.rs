@@ -0,0 +1,267 @@
+//! Deterministic simulation of rio-loan over a BTC price path, on the mock runtime of rio-loan.
+//!
+//! Each price of the path is a block. As on chain, `on_initialize` runs the checks of
+//! `on_each_block` first, then the price comes in through `rio_price::OnChange::on_change`,
+//! so a price is acted on in the next block. The timestamp moves on by the block time of the
+//! scenario. After each block the bookings are checked against the loans, and the state of the
+//! system goes into a row of the trace.
+
+use std::io::{self, BufRead, Write};
+
+use rio_loan::mock::{constants::*, new_test_ext, Origin, RioLoanTest, SystemTest, TestRuntime};
+use rio_loan::{
+    GlobalLTVLimit, GlobalLiquidationThreshold, GlobalWarningThreshold, LoanHealth, Loans, LTV,
+    PRICE_PREC,
+};
+use rio_price::{OnChange, Price};
+use sp_runtime::traits::OnInitialize;
+use support::storage::{StorageLinkedMap, StorageValue};
+
+mod tests;
+
+pub type Balance = u128;
+pub type AccountId = u64;
+
+type RioAssets = rio_assets::Module<TestRuntime>;
+type Timestamp = timestamp::Module<TestRuntime>;
+
+/// borrowers of a scenario are this account and the ones after it
+pub const FIRST_BORROWER: AccountId = 100;
+
+/// a package and the loans made from it at the start price, the collateral is SBTC
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// days of the package
+    pub terms: u32,
+    /// in terms of INTEREST_RATE_PREC
+    pub interest_rate_hourly: u32,
+    /// (LTV limit, warning threshold, liquidation threshold), the ones of the mock genesis if None
+    pub thresholds: Option<(LTV, LTV, LTV)>,
+    /// (collateral, loan amount) of each borrower
+    pub loans: Vec<(Balance, Balance)>,
+    /// milliseconds between two blocks
+    pub block_time: u64,
+}
+
+/// the system at the end of a block
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceRow {
+    pub block: u64,
+    pub timestamp: u64,
+    pub price: Price,
+    pub total_loan: Balance,
+    pub total_collateral: Balance,
+    pub total_profit: Balance,
+    pub bad_debt: Balance,
+    pub well: u32,
+    pub warning: u32,
+    pub liquidating: u32,
+    /// extended or expired
+    pub overdue: u32,
+}
+
+pub struct Simulation {
+    ext: sp_io::TestExternalities,
+    block_time: u64,
+}
+
+impl Simulation {
+    /// the mock genesis at `start_price`, with the package and the loans of the scenario,
+    /// panics if any of the loans can't be made
+    pub fn new(scenario: &Scenario, start_price: Price) -> Self {
+        let mut ext = new_test_ext();
+        ext.execute_with(|| {
+            SystemTest::set_block_number(1);
+            Timestamp::set_timestamp(scenario.block_time);
+            <RioLoanTest as OnChange>::on_change(start_price);
+            if let Some((ltv_limit, warning, liquidation)) = scenario.thresholds {
+                GlobalLTVLimit::put(ltv_limit);
+                GlobalWarningThreshold::put(warning);
+                GlobalLiquidationThreshold::put(liquidation);
+            }
+
+            let package_id = RioLoanTest::next_loan_package_id();
+            RioLoanTest::create_package(
+                Origin::ROOT,
+                scenario.terms,
+                scenario.interest_rate_hourly,
+                1,
+            )
+            .expect("invalid package");
+            for (i, &(collateral, loan)) in scenario.loans.iter().enumerate() {
+                let who = FIRST_BORROWER + i as AccountId;
+                RioAssets::mint(Origin::ROOT, SBTC_ASSET_ID, who, collateral)
+                    .expect("mint collateral");
+                RioLoanTest::apply(Origin::signed(who), collateral, loan, package_id)
+                    .unwrap_or_else(|e| panic!("loan of borrower {}: {:?}", who, e));
+            }
+            check_invariants(1);
+        });
+        Self {
+            ext,
+            block_time: scenario.block_time,
+        }
+    }
+
+    /// a block for each price, panics at the first block that breaks an invariant
+    pub fn run(&mut self, prices: &[Price]) -> Vec<TraceRow> {
+        let block_time = self.block_time;
+        self.ext.execute_with(|| {
+            prices
+                .iter()
+                .map(|&price| {
+                    let block = SystemTest::block_number() + 1;
+                    SystemTest::set_block_number(block);
+                    Timestamp::set_timestamp(Timestamp::get() + block_time);
+                    RioLoanTest::on_initialize(block);
+                    <RioLoanTest as OnChange>::on_change(price);
+                    check_invariants(block);
+                    trace_row(block)
+                })
+                .collect()
+        })
+    }
+
+    /// anything else on the mock runtime, e.g. a repay in the middle of the path
+    pub fn execute_with<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.ext.execute_with(f)
+    }
+}
+
+/// panics with the block if the bookings don't add up to the loans
+pub fn check_invariants(block: u64) {
+    let mut collateral: Balance = 0;
+    let mut debt: Balance = 0;
+    for (loan_id, loan) in <Loans<TestRuntime>>::enumerate() {
+        collateral = collateral
+            .checked_add(loan.collateral_balance_available)
+            .unwrap_or_else(|| panic!("block {}: collateral overflows at loan {}", block, loan_id));
+        debt = debt
+            .checked_add(loan.loan_balance_total)
+            .unwrap_or_else(|| panic!("block {}: debt overflows at loan {}", block, loan_id));
+    }
+    let mut pledged = std::collections::BTreeMap::<u32, Balance>::new();
+    for line_id in 0..RioLoanTest::next_credit_line_id() {
+        if let Some(line) = RioLoanTest::credit_line(line_id) {
+            debt = debt
+                .checked_add(line.drawn)
+                .unwrap_or_else(|| panic!("block {}: debt overflows at line {}", block, line_id));
+            *pledged.entry(line.asset_id).or_default() += line.amount;
+        }
+    }
+    for (asset_id, amount) in pledged {
+        assert_eq!(
+            RioLoanTest::total_pledged(asset_id),
+            amount,
+            "block {}: TotalPledged of asset {} is not the pledge of the lines",
+            block,
+            asset_id
+        );
+    }
+
+    assert_eq!(
+        RioLoanTest::total_collateral(),
+        collateral,
+        "block {}: TotalCollateral is not the collateral of the loans",
+        block
+    );
+    assert_eq!(
+        RioLoanTest::total_loan(),
+        debt,
+        "block {}: TotalLoan is not the balance of the loans and credit lines",
+        block
+    );
+    // balances are unsigned, a booking that went below zero shows up as collateral
+    // the pawn shop doesn't hold
+    let held = RioAssets::free_balance(&SBTC_ASSET_ID, &PAWN_SHOP);
+    assert!(
+        held >= collateral,
+        "block {}: pawn shop holds {} of {} collateral",
+        block,
+        held,
+        collateral
+    );
+}
+
+fn trace_row(block: u64) -> TraceRow {
+    let mut row = TraceRow {
+        block,
+        timestamp: Timestamp::get(),
+        price: RioLoanTest::current_btc_price(),
+        total_loan: RioLoanTest::total_loan(),
+        total_collateral: RioLoanTest::total_collateral(),
+        total_profit: RioLoanTest::total_profit(),
+        bad_debt: RioLoanTest::bad_debt(),
+        ..Default::default()
+    };
+    for (_, loan) in <Loans<TestRuntime>>::enumerate() {
+        match loan.status {
+            LoanHealth::Well => row.well += 1,
+            LoanHealth::Warning(_) => row.warning += 1,
+            LoanHealth::Liquidating(_) => row.liquidating += 1,
+            LoanHealth::Extended | LoanHealth::Expired => row.overdue += 1,
+        }
+    }
+    row
+}
+
+/// prices in USD from the last column of a CSV with a header, e.g. `time,price`,
+/// at most 4 decimals as in PRICE_PREC
+pub fn read_prices<R: BufRead>(input: R) -> io::Result<Vec<Price>> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid price: {}", line),
+        )
+    };
+    let mut prices = Vec::new();
+    for line in input.lines().skip(1) {
+        let line = line?;
+        let field = match line.rsplit(',').next().map(str::trim) {
+            Some(field) if !field.is_empty() => field,
+            _ => continue,
+        };
+        let mut parts = field.splitn(2, '.');
+        let whole: Price = parts
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid(&line))?;
+        let decimals = parts.next().unwrap_or_default();
+        if decimals.len() > 4 {
+            return Err(invalid(&line));
+        }
+        let decimals: Price = format!("{:0<4}", decimals)
+            .parse()
+            .map_err(|_| invalid(&line))?;
+        prices.push(whole * Price::from(PRICE_PREC) + decimals);
+    }
+    Ok(prices)
+}
+
+/// the trace as CSV, amounts in the smallest unit and prices in terms of PRICE_PREC
+pub fn write_csv<W: Write>(rows: &[TraceRow], mut output: W) -> io::Result<()> {
+    writeln!(
+        output,
+        "block,timestamp,price,total_loan,total_collateral,total_profit,bad_debt,\
+         well,warning,liquidating,overdue"
+    )?;
+    for row in rows {
+        writeln!(
+            output,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            row.block,
+            row.timestamp,
+            row.price,
+            row.total_loan,
+            row.total_collateral,
+            row.total_profit,
+            row.bad_debt,
+            row.well,
+            row.warning,
+            row.liquidating,
+            row.overdue
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+#![cfg(test)]
+
+use super::*;
+
+const HOUR: u64 = 3_600_000;
+
+fn crash() -> Vec<Price> {
+    read_prices(include_str!("../fixtures/crash.csv").as_bytes()).unwrap()
+}
+
+#[test]
+fn read_prices_works() {
+    let csv = "time,price\n2020-03-12T00,7900.25\n\n2020-03-12T01, 5000\n";
+    assert_eq!(
+        read_prices(csv.as_bytes()).unwrap(),
+        vec![7900_2500, 5000_0000]
+    );
+    assert!(read_prices("price\n1.00001\n".as_bytes()).is_err());
+    assert!(read_prices("price\nabc\n".as_bytes()).is_err());
+}
+
+#[test]
+fn crash_keeps_the_bookings() {
+    // liquidating below 5556, warning below 5000, and well all the way down
+    let scenario = Scenario {
+        terms: 30,
+        interest_rate_hourly: 100,
+        thresholds: None,
+        loans: vec![
+            (1_00000000, 5000_00000000),
+            (1_00000000, 3000_00000000),
+            (1_00000000, 4000_00000000),
+        ],
+        block_time: HOUR,
+    };
+    let prices = crash();
+    let mut sim = Simulation::new(&scenario, prices[0]);
+    let rows = sim.run(&prices[1..]);
+
+    assert_eq!(rows.len(), prices.len() - 1);
+    assert_eq!(rows[0].timestamp, 2 * HOUR);
+    for row in &rows {
+        assert_eq!(row.total_loan, 12000_00000000);
+        assert_eq!(row.total_collateral, 3_00000000);
+        assert_eq!(row.bad_debt, 0);
+    }
+    assert!(rows
+        .iter()
+        .any(|row| row.well == 1 && row.warning == 1 && row.liquidating == 1));
+    // the warned loan is well again once the price recovers, the one under water stays in liquidation
+    let last = rows.last().unwrap();
+    assert_eq!((last.well, last.warning, last.liquidating), (2, 0, 1));
+    assert_eq!(last.price, 6500_0000);
+
+    let mut trace = Vec::new();
+    write_csv(&rows, &mut trace).unwrap();
+    let trace = String::from_utf8(trace).unwrap();
+    assert_eq!(trace.lines().count(), rows.len() + 1);
+    assert!(trace.starts_with("block,timestamp,price,"));
+}
+
+#[test]
+fn liquidations_keep_the_bookings() {
+    let scenario = Scenario {
+        terms: 30,
+        interest_rate_hourly: 100,
+        thresholds: None,
+        loans: vec![
+            (1_00000000, 5000_00000000),
+            (1_00000000, 4000_00000000),
+            (1_00000000, 3000_00000000),
+        ],
+        block_time: HOUR,
+    };
+    let mut sim = Simulation::new(&scenario, 8000_0000);
+    let loan_of = |i: AccountId| RioLoanTest::loans_by_account(&(FIRST_BORROWER + i))[0];
+    let (first, second, third) = sim.execute_with(|| (loan_of(0), loan_of(1), loan_of(2)));
+
+    // a keeper takes half of the first loan at 5500, 2625 worth of collateral for 2500
+    sim.run(&[7000_0000, 5500_0000]);
+    sim.execute_with(|| {
+        RioAssets::mint(Origin::ROOT, RIO_ASSET_ID, ALICE, 2500_00000000).unwrap();
+        RioLoanTest::liquidate(Origin::signed(ALICE), first, 2500_00000000).unwrap();
+        check_invariants(SystemTest::block_number());
+        assert_eq!(RioAssets::free_balance(&SBTC_ASSET_ID, &ALICE), 47727272);
+        assert_eq!(RioLoanTest::total_loan(), 9500_00000000);
+        assert_eq!(RioLoanTest::total_collateral(), 2_52272728);
+    });
+
+    // both of them are under water at 4000
+    let rows = sim.run(&[4000_0000, 4000_0000]);
+    let last = rows.last().unwrap();
+    assert_eq!((last.well, last.liquidating), (1, 2));
+    sim.execute_with(|| {
+        let mut liquidating = RioLoanTest::liquidating_loans();
+        liquidating.sort();
+        assert_eq!(liquidating, vec![first, second]);
+        RioAssets::mint(Origin::ROOT, RIO_ASSET_ID, LIQUIDATION_ACCOUNT, 5500_00000000).unwrap();
+        RioLoanTest::mark_liquidated(Origin::signed(LIQUIDATION_ACCOUNT), first, 2500_00000000)
+            .unwrap();
+        check_invariants(SystemTest::block_number());
+        // the sale falls short of the second one by 1000
+        RioLoanTest::mark_liquidated(Origin::signed(LIQUIDATION_ACCOUNT), second, 3000_00000000)
+            .unwrap();
+        check_invariants(SystemTest::block_number());
+        assert_eq!(RioLoanTest::total_loan(), 3000_00000000);
+        assert_eq!(RioLoanTest::total_collateral(), 1_00000000);
+        assert_eq!(RioLoanTest::bad_debt(), 1000_00000000);
+
+        RioLoanTest::set_auction_params(Origin::ROOT, 10, 12000, 8000).unwrap();
+    });
+
+    // the last one goes into an auction at 3000, and is sold at 120% right away
+    let rows = sim.run(&[3000_0000, 3000_0000]);
+    assert_eq!(rows.last().unwrap().liquidating, 1);
+    sim.execute_with(|| {
+        assert_eq!(RioLoanTest::auction_price(third), Some(3600_00000000));
+        RioAssets::mint(Origin::ROOT, RIO_ASSET_ID, BOB, 3600_00000000).unwrap();
+        RioLoanTest::bid(Origin::signed(BOB), third, 3600_00000000).unwrap();
+        check_invariants(SystemTest::block_number());
+        // 600 over the debt, 13% of it is the penalty
+        assert_eq!(
+            RioAssets::free_balance(&RIO_ASSET_ID, &(FIRST_BORROWER + 2)),
+            3000_00000000 - 2_16000000 + 522_00000000
+        );
+    });
+
+    let rows = sim.run(&[3000_0000]);
+    let last = rows.last().unwrap();
+    assert_eq!((last.total_loan, last.total_collateral), (0, 0));
+    assert_eq!((last.well, last.warning, last.liquidating), (0, 0, 0));
+    assert_eq!(last.bad_debt, 1000_00000000);
+}
@@ -13,14 +13,14 @@ use rstd::{
 };
 #[allow(unused_imports)]
 use support::{
-    decl_event, decl_module, decl_storage,
-    dispatch::{Parameter, Result as DispatchResult},
+    decl_error, decl_event, decl_module, decl_storage,
+    dispatch::{DispatchError, DispatchResult, Parameter},
     ensure,
     traits::{Contains, Get},
-    weights::SimpleDispatchInfo,
+    weights::{SimpleDispatchInfo, Weight},
 };
 #[allow(unused_imports)]
-use system::{ensure_root, ensure_signed, Error};
+use system::{ensure_root, ensure_signed};
 
 #[allow(unused_imports)]
 use sp_runtime::traits::{
@@ -34,41 +34,63 @@ use sp_runtime::traits::{
 // 	  },
 // }
 
-pub use rio_price::Price;
+pub use rio_loan_rpc_runtime_api::{
+    LoanFunction, LoanHealth, LoanPosition, LoanQuote, Solvency, SystemDebt,
+};
+pub use rio_bridge::{BlackOrWhite, KycStatus};
+pub use rio_price::{FeedId, Price};
+use rio_support::fixed::{
+    balance_mul_div, checked_add, checked_div, checked_mul, from_u128, mul_div, to_u128, MathError,
+    MathResult,
+};
+pub use rio_support::breaker::{Breaker, TripReason};
+use rio_support::breaker::{Breakers, TripBreakers};
+pub use rio_support::timelock::{ParamChange, ParamChangeId};
+use rio_support::timelock::Timelock;
 
-mod mock;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 mod tests;
 
 pub const INTEREST_RATE_PREC: u32 = 10000_0000;
 pub const LTV_PREC: u32 = 10000;
 pub const PRICE_PREC: u32 = rio_price::PRICE_PRECISION;
+/// precision of the borrow index
+pub const BORROW_INDEX_PREC: u128 = 1_000_000_000_000_000_000;
 
 /// should be 86400 seconds, a.k.a one day
 pub const TERMS_UNIT: u32 = 86400;
 
+/// a year in milliseconds, the stability fee of fixed rate loans accrues by time
+pub const YEAR_IN_MS: u64 = 365 * 86400_000;
+
 /// in terms of TERMS_UNIT, a.k.a 2 days
 pub const DUE_EXTEND: u32 = 2;
 
+/// loans are indexed by the hour they get due, in milliseconds
+pub const DUE_INDEX_UNIT: u64 = 3600_000;
+
+/// loans are indexed by the price they hit the next threshold, in buckets of 100
+pub const PRICE_INDEX_UNIT: Price = 100 * PRICE_PREC as Price;
+
+/// loans accruing interest are checked again in this many hours at most, a.k.a a day
+pub const RECHECK_HOURS: u64 = 24;
+
+/// loans in warning or flagged for keepers are checked again in this many hours,
+/// the price going up doesn't bring them into the check queue
+pub const WATCH_HOURS: u64 = 1;
+
+/// weight of checking a loan, reading the loan, its basket and the prices
+pub const LOAN_CHECK_WEIGHT: Weight = 100_000;
+
+/// extra weight of a check which writes, e.g. a new status, a top up or an expiration
+pub const LOAN_UPDATE_WEIGHT: Weight = 400_000;
+
 pub type LoanPackageId = u64;
 pub type LoanId = u64;
 pub type CreditLineId = u64;
 pub type LTV = u64;
 
-#[derive(Encode, Decode, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "std", derive(Debug))]
-pub enum LoanHealth {
-    Well,
-    Warning(LTV),
-    Liquidating(LTV),
-    Extended,
-    Expired,
-}
-impl Default for LoanHealth {
-    fn default() -> Self {
-        Self::Well
-    }
-}
-
 #[derive(Encode, Decode, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum LoanPackageStatus {
@@ -116,12 +138,9 @@ where
         + MaybeSerializeDeserialize,
     AssetId: Encode + Decode + Parameter + Member + SimpleArithmetic + Default + Copy,
 {
-    pub fn get_interest(&self, amount: Balance) -> Balance {
-        amount
-            * Balance::from(self.terms)
-            * Balance::from(24 as u32)
-            * Balance::from(self.interest_rate_hourly)
-            / Balance::from(INTEREST_RATE_PREC)
+    pub fn get_interest(&self, amount: Balance) -> MathResult<Balance> {
+        let rate = u128::from(self.terms) * 24 * u128::from(self.interest_rate_hourly);
+        balance_mul_div(amount, rate, u128::from(INTEREST_RATE_PREC))
     }
 
     pub fn get_dues<Moment>(&self, now: Moment) -> (Moment, Moment)
@@ -136,6 +155,63 @@ where
     }
 }
 
+/// risk parameters of an asset used as collateral
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CollateralParams<Balance> {
+    pub ltv_limit: LTV,
+    pub warning_threshold: LTV,
+    pub liquidation_threshold: LTV,
+    pub minimum_collateral: Balance,
+    /// price feed of rio-price, the BTC price is used if None
+    pub price_feed: Option<FeedId>,
+}
+
+/// value of a loan's collateral basket in terms of the loan asset,
+/// with the risk parameters weighted by the value of each asset
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BasketRisk<Balance> {
+    pub value: Balance,
+    pub ltv_limit: LTV,
+    pub warning_threshold: LTV,
+    pub liquidation_threshold: LTV,
+}
+
+/// descending price auction of a liquidating loan's collateral
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CollateralAuction<BlockNumber> {
+    pub loan_id: LoanId,
+    pub start: BlockNumber,
+}
+
+/// utilization based interest rate model, a.k.a the kink model
+/// rates are yearly in terms of INTEREST_RATE_PREC, utilization is TotalLoan / LoanCap in terms of LTV_PREC
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct InterestRateModel {
+    /// rate at zero utilization
+    pub base_rate: u32,
+    /// rate added per full utilization below the kink
+    pub slope_low: u32,
+    /// rate added per full utilization above the kink
+    pub slope_high: u32,
+    pub kink: LTV,
+    pub blocks_per_year: u32,
+}
+
+impl InterestRateModel {
+    pub fn yearly_rate(&self, utilization: LTV) -> u64 {
+        let utilization = utilization.min(LTV::from(LTV_PREC));
+        let below_kink = utilization.min(self.kink);
+        let above_kink = utilization.saturating_sub(self.kink);
+        u64::from(self.base_rate)
+            + u64::from(self.slope_low) * below_kink / LTV::from(LTV_PREC)
+            + u64::from(self.slope_high) * above_kink / LTV::from(LTV_PREC)
+    }
+}
+
 #[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub struct Loan<AccountId, Balance, Moment> {
@@ -162,36 +238,42 @@ where
     Moment: Parameter + Default + SimpleArithmetic + Copy,
     AccountId: Parameter + Member + MaybeSerializeDeserialize + MaybeDisplay + Ord + Default,
 {
-    pub fn get_ltv(collateral_amount: Balance, loan_amount: Balance, btc_price: Price) -> LTV {
-        let btc_price_in_balance = <Balance as TryFrom<u128>>::try_from(btc_price)
-            .ok()
-            .unwrap();
-        let ltv = (loan_amount * Balance::from(PRICE_PREC) * Balance::from(LTV_PREC))
-            / (collateral_amount * btc_price_in_balance);
-        TryInto::<LTV>::try_into(ltv).ok().unwrap()
+    pub fn get_ltv(
+        collateral_amount: Balance,
+        loan_amount: Balance,
+        btc_price: Price,
+    ) -> MathResult<LTV> {
+        let ltv = mul_div(
+            to_u128(loan_amount)?,
+            u128::from(PRICE_PREC) * u128::from(LTV_PREC),
+            checked_mul(to_u128(collateral_amount)?, btc_price)?,
+        )?;
+        TryInto::<LTV>::try_into(ltv).map_err(|_| MathError::Overflow)
     }
 
-    pub fn expiration_penalty(&self, penalty_rate: u32, btc_price: Price) -> Balance {
-        let btc_price_in_balance = <Balance as TryFrom<u128>>::try_from(btc_price)
-            .ok()
-            .unwrap();
-        self.loan_balance_total * Balance::from(PRICE_PREC) * Balance::from(penalty_rate)
-            / Balance::from(LTV_PREC)
-            / btc_price_in_balance
+    /// the penalty is charged in the collateral asset
+    pub fn expiration_penalty(&self, penalty_rate: u32, btc_price: Price) -> MathResult<Balance> {
+        let penalty = mul_div(
+            to_u128(self.loan_balance_total)?,
+            u128::from(PRICE_PREC) * u128::from(penalty_rate),
+            u128::from(LTV_PREC),
+        )?;
+        from_u128(checked_div(penalty, btc_price)?)
     }
 
     pub fn expiration_interest<AssetId>(
         &self,
         package: &LoanPackage<Balance, AssetId>,
         btc_price: Price,
-    ) -> Balance
+    ) -> MathResult<Balance>
     where
         AssetId: Encode + Decode + Parameter + Member + SimpleArithmetic + Default + Copy,
     {
-        package.get_interest(self.loan_balance_total) * Balance::from(PRICE_PREC)
-            / <Balance as TryFrom<u128>>::try_from(btc_price)
-                .ok()
-                .unwrap()
+        balance_mul_div(
+            package.get_interest(self.loan_balance_total)?,
+            u128::from(PRICE_PREC),
+            btc_price,
+        )
     }
 
     pub fn expire_then_update<AssetId>(
@@ -203,7 +285,7 @@ where
     ) where
         AssetId: Encode + Decode + Parameter + Member + SimpleArithmetic + Default + Copy,
     {
-        let expiration_fee = expiration_interest + expiration_penalty;
+        let expiration_fee = expiration_interest.saturating_add(expiration_penalty);
         match self
             .collateral_balance_available
             .checked_sub(&expiration_fee)
@@ -223,21 +305,83 @@ where
     }
 }
 
+/// a revolving credit line backed by saving share or IOU assets
 #[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct CreditLine<Balance, AssetId> {
+pub struct CreditLine<AccountId, Balance, AssetId> {
     pub id: CreditLineId,
+    pub who: AccountId,
+    /// LTV limit of the pledged asset when the line was refreshed
     pub ltv: LTV,
+    /// pledged amount, reserved in the owner's account so that saving shares keep their bonus
     pub amount: Balance,
+    /// price of the pledged asset when the line was refreshed
     pub price: Price,
+    /// borrow limit, the value of the pledge times the LTV limit
     pub credit: Balance,
     pub asset_id: AssetId,
+    /// loan asset drawn and not repaid yet, including the settled interest
+    pub drawn: Balance,
+}
+
+/// risk parameters of an asset pledged for credit lines
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CreditParams {
+    pub ltv: LTV,
+    /// price feed of rio-price, the BTC price is used if None
+    pub price_feed: Option<FeedId>,
+}
+
+/// limits of the borrowers by their KYC status
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BorrowerTier<Balance> {
+    /// loans can't be applied or drawn over this LTV, on top of the LTV limit of the collateral
+    pub max_ltv: LTV,
+    /// packages the borrowers can apply for, all of them if empty
+    pub packages: Vec<LoanPackageId>,
+    /// outstanding debt of all the loans of an account, no caps if None
+    pub cap: Option<Balance>,
+}
+
+impl<Balance> BorrowerTier<Balance> {
+    pub fn allows(&self, package_id: LoanPackageId) -> bool {
+        self.packages.is_empty() || self.packages.contains(&package_id)
+    }
+}
+
+/// collateral the chain pulls from the borrower's free balance into a loan over the warning threshold
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct TopUpPolicy<Balance> {
+    /// collateral asset of the package that can still be pulled, it goes down with each top-up
+    pub max_amount: Balance,
+    /// the loan is topped up to this LTV
+    pub target_ltv: LTV,
+}
+
+/// global risk parameters, which only change through the timelocked queue
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum RiskParam<Balance> {
+    GlobalLtvLimit(LTV),
+    GlobalWarningThreshold(LTV),
+    GlobalLiquidationThreshold(LTV),
+    /// in terms of LTV_PREC
+    PenaltyRate(u32),
+    /// no caps at all if None
+    LoanCap(Option<Balance>),
 }
 
 /// The module's configuration trait.
 pub trait Trait: rio_assets::Trait + timestamp::Trait {
     /// The overarching event type.
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// KYC status of the borrowers, usually rio-bridge
+    type Kyc: KycStatus<Self::AccountId>;
+    /// blocks a risk parameter change waits in the queue
+    type ParamChangeDelay: Get<Self::BlockNumber>;
 }
 
 // This module's storage items.
@@ -287,10 +431,103 @@ decl_storage! {
         pub LoanCap get(loan_cap) : Option<T::Balance>;
         /// module level switch
         Paused get(paused) : bool = false;
+        /// circuit breakers of each function, a tripped function is paused while the others keep running
+        FunctionBreakers get(function_breaker) : map LoanFunction => Option<Breaker<T::BlockNumber>>;
+        /// tripped breakers resume by themselves after this many blocks if set
+        BreakerTimeout get(breaker_timeout) : Option<T::BlockNumber>;
         /// for each loan, the amount of collateral asset must be greater than this
         pub MinimumCollateral get(minimum_collateral) config() : T::Balance;
         ///
         pub LiquidationPenalty get(liquidation_penalty) config() : u32;
+        /// risk parameters of each collateral asset, the global ones apply to assets not in here
+        pub CollateralParamsOf get(collateral_params) : linked_map T::AssetId => Option<CollateralParams<T::Balance>>;
+        /// prices of the collateral assets which have their own price feed
+        pub CollateralPrices get(collateral_price) : map T::AssetId => Price;
+        /// collateral of a loan in assets other than the collateral asset of its package
+        pub LoanBasket get(loan_basket) : map LoanId => Vec<(T::AssetId, T::Balance)>;
+        /// auctions of liquidating loans, collateral is sold off-chain through "mark_liquidated" if no auction
+        pub Auctions get(auction) : map LoanId => Option<CollateralAuction<T::BlockNumber>>;
+        /// blocks for the price to fall from the start to the floor, no auctions at all if zero
+        pub AuctionDuration get(auction_duration) : T::BlockNumber;
+        /// auction price at the start, in terms of LTV_PREC of the collateral value
+        pub AuctionStartRate get(auction_start_rate) : u32 = 11000;
+        /// auction price at the end, in terms of LTV_PREC of the collateral value
+        pub AuctionFloorRate get(auction_floor_rate) : u32 = 8000;
+        /// keepers get the collateral at this discount, in terms of LTV_PREC
+        pub LiquidationDiscount get(liquidation_discount) : u32 = 500;
+        /// the most part of a loan's debt a keeper can repay at a time, in terms of LTV_PREC
+        pub CloseFactor get(close_factor) : u32 = 5000;
+        /// interest of new loans accrues by this model if set, otherwise it is charged upfront by the package
+        pub InterestModel get(interest_model) : Option<InterestRateModel>;
+        /// grows with the interest accrued by the interest model
+        pub BorrowIndex get(borrow_index) : u128 = BORROW_INDEX_PREC;
+        /// the block that interest has been accrued to
+        LastAccrual get(last_accrual) : T::BlockNumber;
+        /// borrow index when the interest of a loan was settled last time, only loans under the interest model are here
+        pub LoanBorrowIndex get(loan_borrow_index) : map LoanId => Option<u128>;
+        /// the account of the stability fund, which covers the shortfalls of liquidations
+        pub StabilityFund get(stability_fund) : Option<T::AccountId>;
+        /// the share of the interest going into the stability fund, in terms of LTV_PREC
+        pub StabilityFundRate get(stability_fund_rate) : u32;
+        /// shortfalls of liquidations not covered by the stability fund yet
+        pub BadDebt get(bad_debt) : T::Balance;
+        /// the stability fund is fed up to this much loan asset, no limit if None
+        pub SurplusBufferCap get(surplus_buffer_cap) : Option<T::Balance>;
+        /// yearly rate on top of the interest model, in terms of INTEREST_RATE_PREC
+        pub StabilityFee get(stability_fee) : u32;
+        /// grows with the stability fee only, for the loans of fixed rate packages
+        pub FeeIndex get(fee_index) : u128 = BORROW_INDEX_PREC;
+        /// grows with the stability fee only while there is no interest model, for the credit lines,
+        /// the borrow index carries the fee once there is a model
+        pub CreditFeeIndex get(credit_fee_index) : u128 = BORROW_INDEX_PREC;
+        /// the moment that the stability fee has been accrued to
+        LastFeeAccrual get(last_fee_accrual) : T::Moment;
+        /// fee index when the stability fee of a fixed rate loan was settled last time
+        pub LoanFeeIndex get(loan_fee_index) : map LoanId => Option<u128>;
+        /// migrations done by on_runtime_upgrade so far
+        StorageVersion get(storage_version) : u32;
+        /// loan asset minted against each collateral asset, by the collateral asset of the package or the credit line
+        pub CollateralDebt get(collateral_debt) : linked_map T::AssetId => T::Balance;
+        /// the most loan asset that can be minted against a collateral asset, no limit if None
+        pub DebtCeilings get(debt_ceiling) : linked_map T::AssetId => Option<T::Balance>;
+        /// loans to be checked, the ones which don't fit in a block are carried over to the next
+        pub CheckQueue get(check_queue) : Vec<LoanId>;
+        /// weight the loan checks can take in a block, 100 checks which write by default
+        pub CheckWeightLimit get(check_weight_limit) : Weight = 50_000_000;
+        /// loans by the hour they get due or overdue
+        pub DueIndex get(due_index) : map u64 => Vec<LoanId>;
+        /// hours before this are checked already
+        pub NextDueHour get(next_due_hour) : u64;
+        /// loans by the price source (None for BTC) and the bucket of the price they hit the next threshold
+        pub PriceIndex get(price_index) : map (Option<FeedId>, Price) => Vec<LoanId>;
+        /// non-empty buckets of PriceIndex of each price source, in ascending order
+        pub PriceBuckets get(price_buckets) : map Option<FeedId> => Vec<Price>;
+        /// where a loan is in DueIndex and PriceIndex
+        pub LoanIndexKeys get(loan_index_keys) : map LoanId => (Option<u64>, Option<(Option<FeedId>, Price)>);
+        /// loans holding an asset in their baskets
+        pub BasketLoans get(basket_loans) : map T::AssetId => Vec<LoanId>;
+        /// assets that can be pledged for credit lines, e.g. the saving share asset and IOU assets
+        pub CreditAssets get(credit_asset) : linked_map T::AssetId => Option<CreditParams>;
+        pub CreditLines get(credit_line) : map CreditLineId => Option<CreditLine<T::AccountId, T::Balance, T::AssetId>>;
+        pub CreditLinesByAccount get(credit_lines_by_account) : map T::AccountId => Vec<CreditLineId>;
+        /// increase monotonically
+        NextCreditLineId get(next_credit_line_id) : CreditLineId;
+        /// borrow index when the interest of a credit line was settled last time
+        pub CreditLineBorrowIndex get(credit_line_borrow_index) : map CreditLineId => u128;
+        /// credit fee index when the interest of a credit line was settled last time
+        pub CreditLineFeeIndex get(credit_line_fee_index) : map CreditLineId => u128;
+        /// pledged balance of all the credit lines by asset, reserved in the owners' accounts
+        pub TotalPledged get(total_pledged) : linked_map T::AssetId => T::Balance;
+        /// by the KYC status (None for accounts not in the KYC list), no limits for a status without a tier
+        pub BorrowerTiers get(borrower_tier) : map Option<BlackOrWhite> => Option<BorrowerTier<T::Balance>>;
+        /// caps of the outstanding debt of some accounts, in place of the cap of their tier
+        pub AccountLoanCaps get(account_loan_cap) : map T::AccountId => Option<T::Balance>;
+        /// auto top-up policies registered by the borrowers
+        pub TopUpPolicies get(top_up_policy) : map LoanId => Option<TopUpPolicy<T::Balance>>;
+        /// risk parameter changes waiting for the delay
+        pub PendingParamChanges get(pending_param_change) : linked_map ParamChangeId => Option<ParamChange<RiskParam<T::Balance>, T::BlockNumber>>;
+        /// increase monotonically
+        NextParamChangeId get(next_param_change_id) : ParamChangeId;
     }
 }
 
@@ -301,11 +538,16 @@ decl_module! {
         const LTV_PRECISION: u32 = LTV_PREC;
         const BTC_PRICE_PRECISION: u32 = PRICE_PREC;
         const INTEREST_RATE_PRECISION: u32 = INTEREST_RATE_PREC;
+        const ParamChangeDelay: T::BlockNumber = T::ParamChangeDelay::get();
+
+        type Error = Error<T>;
 
         fn deposit_event() = default;
 
         fn on_initialize(height: T::BlockNumber) {
+            Self::apply_due_param_changes(height);
             if !Self::paused() {
+                Self::accrue_interest();
                 Self::on_each_block(height);
             }
         }
@@ -313,6 +555,10 @@ decl_module! {
         fn on_finalize(_height: T::BlockNumber) {
         }
 
+        fn on_runtime_upgrade() {
+            Self::migrate();
+        }
+
         #[weight = SimpleDispatchInfo::MaxNormal]
         pub fn pause(origin) -> DispatchResult {
             ensure_root(origin)?;
@@ -327,17 +573,35 @@ decl_module! {
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn trip_breaker(origin, function: LoanFunction) -> DispatchResult {
+            ensure_root(origin)?;
+            Self::trip(function, TripReason::Manual);
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn reset_breaker(origin, function: LoanFunction) -> DispatchResult {
+            ensure_root(origin)?;
+            LoanBreakers::<T>::reset(function);
+            Self::deposit_event(RawEvent::BreakerReset(function));
+            Ok(())
+        }
+
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_collateral_asset_id(origin, asset_id: T::AssetId) -> RioLoanResult {
+        pub fn set_breaker_timeout(origin, timeout: Option<T::BlockNumber>) -> DispatchResult {
             ensure_root(origin)?;
-            <CollateralAssetId<T>>::put(asset_id);
+            match timeout {
+                Some(t) => <BreakerTimeout<T>>::put(t),
+                None => <BreakerTimeout<T>>::kill(),
+            }
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_global_ltv_limit(origin, limit: LTV) -> RioLoanResult {
+        pub fn set_collateral_asset_id(origin, asset_id: T::AssetId) -> RioLoanResult {
             ensure_root(origin)?;
-            GlobalLTVLimit::put(limit);
+            <CollateralAssetId<T>>::put(asset_id);
             Ok(())
         }
 
@@ -348,59 +612,92 @@ decl_module! {
             Ok(())
         }
 
+        /// the change applies after ParamChangeDelay blocks, the thresholds must stay in order
+        /// with the pending changes before it
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_global_liquidation_threshold(origin, threshold: LTV) -> RioLoanResult {
+        pub fn schedule_param_change(origin, param: RiskParam<T::Balance>) -> RioLoanResult {
             ensure_root(origin)?;
-            GlobalWarningThreshold::put(threshold);
+            let mut risk = Self::pending_global_risk();
+            Self::check_param_change(&mut risk, &param)?;
+            let (id, eta) = RiskTimelock::<T>::schedule(
+                param,
+                <system::Module<T>>::block_number(),
+                T::ParamChangeDelay::get(),
+            );
+            Self::deposit_event(RawEvent::ParamChangeScheduled(id, eta));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_global_warning_threshold(origin, threshold: LTV) -> RioLoanResult {
+        pub fn cancel_param_change(origin, id: ParamChangeId) -> RioLoanResult {
             ensure_root(origin)?;
-            GlobalLiquidationThreshold::put(threshold);
+            ensure!(RiskTimelock::<T>::cancel(id), Error::<T>::InvalidParamChangeId);
+            Self::deposit_event(RawEvent::ParamChangeCancelled(id));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_loan_cap(origin, balance: T::Balance) -> RioLoanResult {
+        pub fn set_liquidation_account(origin, account_id: T::AccountId) -> RioLoanResult {
             ensure_root(origin)?;
-            if balance.is_zero() {
-                <LoanCap<T>>::kill();
-            } else {
-                <LoanCap<T>>::put(balance);
-            }
+            <LiquidationAccount<T>>::put(account_id);
             Ok(())
         }
 
+        /// None removes the asset from collateral assets, it then falls back to the global parameters
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_liquidation_account(origin, account_id: T::AccountId) -> RioLoanResult {
+        pub fn set_collateral_params(origin, asset_id: T::AssetId, params: Option<CollateralParams<T::Balance>>) -> RioLoanResult {
             ensure_root(origin)?;
-            <LiquidationAccount<T>>::put(account_id);
+            match params {
+                Some(params) => {
+                    ensure!(
+                        params.ltv_limit < params.warning_threshold
+                            && params.warning_threshold < params.liquidation_threshold
+                            && params.liquidation_threshold <= LTV::from(LTV_PREC),
+                        Error::<T>::InvalidThresholds
+                    );
+                    if let Some(feed) = params.price_feed {
+                        Self::seed_price(&asset_id, feed)?;
+                    }
+                    <CollateralParamsOf<T>>::insert(asset_id, params);
+                }
+                None => <CollateralParamsOf<T>>::remove(asset_id),
+            }
+            Self::recheck_loans(Some(asset_id));
             Ok(())
         }
 
+        /// new loans are charged upfront by their package again if None, the existing ones keep the accrued interest
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_penalty_rate(origin, rate: u32) -> RioLoanResult {
+        pub fn set_interest_model(origin, model: Option<InterestRateModel>) -> RioLoanResult {
             ensure_root(origin)?;
-            PenaltyRate::put(rate);
+            if let Some(ref m) = model {
+                ensure!(m.blocks_per_year > 0, Error::<T>::ZeroNotAllowed);
+                ensure!(m.kink <= LTV::from(LTV_PREC), Error::<T>::InvalidKink);
+            }
+            // interest before the change is accrued by the old model
+            Self::accrue_interest();
+            <LastAccrual<T>>::put(<system::Module<T>>::block_number());
+            match model {
+                Some(model) => InterestModel::put(model),
+                None => InterestModel::kill(),
+            }
             Ok(())
         }
 
         /// create a loan package that defines how many days, interest by hour, and minimum RIO about a loan of this package
         #[weight = SimpleDispatchInfo::FreeOperational]
         pub fn create_package(origin, terms: u32, interest_rate_hourly: u32, min_rio: T::Balance) -> RioLoanResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
             ensure_root(origin)?;
-            ensure!(terms > 0 && interest_rate_hourly > 0 && min_rio > T::Balance::zero(), "zero is not allowed");
-            ensure!(interest_rate_hourly < INTEREST_RATE_PREC, "invalid interest rate");
+            ensure!(terms > 0 && interest_rate_hourly > 0 && min_rio > T::Balance::zero(), Error::<T>::ZeroNotAllowed);
+            ensure!(interest_rate_hourly < INTEREST_RATE_PREC, Error::<T>::InvalidInterestRate);
             Self::create_loan_package(terms, interest_rate_hourly, min_rio)
         }
 
         /// no more loans can be made from this package, and a disable can't be reversed
         #[weight = SimpleDispatchInfo::FreeOperational]
         pub fn disable_package(origin, package_id: LoanPackageId) -> RioLoanResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
             ensure_root(origin)?;
             Self::disable_loan_package(package_id)
         }
@@ -409,21 +706,58 @@ decl_module! {
         #[weight = SimpleDispatchInfo::FreeOperational]
         pub fn set_price(origin, price: Price) -> RioLoanResult {
             ensure_root(origin)?;
+            Self::on_price_drop(None, Self::current_btc_price(), price);
             CurrentBTCPrice::put(price);
             Ok(())
         }
 
+        /// None stops feeding the fund, what's in the fund stays there
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_stability_fund(origin, fund: Option<T::AccountId>, rate: u32) -> RioLoanResult {
+            ensure_root(origin)?;
+            ensure!(rate <= LTV_PREC, Error::<T>::InvalidRate);
+            match fund {
+                Some(fund) => <StabilityFund<T>>::put(fund),
+                None => <StabilityFund<T>>::kill(),
+            }
+            StabilityFundRate::put(rate);
+            Self::cover_bad_debt(Self::loan_asset_id())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_check_weight_limit(origin, limit: Weight) -> RioLoanResult {
+            ensure_root(origin)?;
+            // at least one loan is checked in a block
+            ensure!(
+                limit >= LOAN_CHECK_WEIGHT + LOAN_UPDATE_WEIGHT,
+                Error::<T>::CheckWeightTooLow
+            );
+            CheckWeightLimit::put(limit);
+            Ok(())
+        }
+
+        /// put loans into the indexes again, e.g. the ones made before the indexes
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn reindex_loans(origin, loan_ids: Vec<LoanId>) -> RioLoanResult {
+            ensure_root(origin)?;
+            for loan_id in loan_ids {
+                Self::index_loan(loan_id);
+            }
+            Ok(())
+        }
+
         /// a user repay a loan he has made before, by providing the loan id and he should make sure there is enough related assets in his account
         #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
         pub fn repay(origin, loan_id: LoanId) -> RioLoanResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
             Self::repay_loan(ensure_signed(origin)?, loan_id)
         }
 
         /// a user can apply for a loan choosing one active loan package, providing the collateral and loan amount he wants,
         #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
         pub fn apply(origin, collateral_amount: T::Balance, loan_amount: T::Balance, package_id: LoanPackageId) -> RioLoanResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Borrow)?;
             Self::apply_for_loan(ensure_signed(origin)?, package_id, collateral_amount, loan_amount)
         }
 
@@ -432,33 +766,398 @@ decl_module! {
         /// auction_balance will be first used to make up the loan, then what so ever left will be returned to the loan's owner account
         #[weight = SimpleDispatchInfo::FreeOperational]
         pub fn mark_liquidated(origin, loan_id: LoanId, auction_balance: T::Balance) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Liquidation)?;
             let liquidation_account = ensure_signed(origin)?;
-            ensure!(liquidation_account == Self::liquidation_account(), "liquidation account only");
-            ensure!(<Loans<T>>::exists(loan_id), "loan doesn't exists");
+            ensure!(liquidation_account == Self::liquidation_account(), Error::<T>::LiquidationAccountOnly);
+            ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
 
             Self::mark_loan_liquidated(&Self::get_loan_by_id(loan_id), liquidation_account, auction_balance)
         }
 
+        /// a zero duration turns auctions off, liquidation goes back to "mark_liquidated"
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_auction_params(origin, duration: T::BlockNumber, start_rate: u32, floor_rate: u32) -> RioLoanResult {
+            ensure_root(origin)?;
+            ensure!(floor_rate > 0 && floor_rate <= start_rate, Error::<T>::InvalidAuctionRates);
+            <AuctionDuration<T>>::put(duration);
+            AuctionStartRate::put(start_rate);
+            AuctionFloorRate::put(floor_rate);
+            Ok(())
+        }
+
+        /// buy all the collateral of a liquidating loan at the current auction price, in terms of the loan asset
+        /// max_price protects the bidder from price changes before the bid is included
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn bid(origin, loan_id: LoanId, max_price: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Liquidation)?;
+            let who = ensure_signed(origin)?;
+            Self::take_auction(who, loan_id, max_price)
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_keeper_params(origin, discount: u32, close_factor: u32) -> RioLoanResult {
+            ensure_root(origin)?;
+            ensure!(discount < LTV_PREC, Error::<T>::InvalidDiscount);
+            ensure!(close_factor > 0 && close_factor <= LTV_PREC, Error::<T>::InvalidCloseFactor);
+            LiquidationDiscount::put(discount);
+            CloseFactor::put(close_factor);
+            Ok(())
+        }
+
+        /// anyone can repay part of the debt of a loan over the liquidation threshold,
+        /// and gets the collateral of the same value plus the discount
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn liquidate(origin, loan_id: LoanId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Liquidation)?;
+            let keeper = ensure_signed(origin)?;
+            Self::liquidate_by_keeper(keeper, loan_id, amount)
+        }
+
+        /// an auction which reached the floor without any bids starts over from the current collateral value
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn restart_auction(origin, loan_id: LoanId) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure_signed(origin)?;
+            let auction = Self::auction(loan_id).ok_or(Error::<T>::NoAuction)?;
+            let now = <system::Module<T>>::block_number();
+            ensure!(now >= auction.start + Self::auction_duration(), Error::<T>::AuctionNotOver);
+            <Auctions<T>>::insert(loan_id, CollateralAuction { loan_id, start: now });
+            Self::deposit_event(RawEvent::AuctionStarted(loan_id));
+            Ok(())
+        }
+
         /// when user got a warning of high-risk LTV, user can lower the LTV by add more collateral
         #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
         pub fn add_collateral(origin, loan_id: LoanId, amount: T::Balance) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
             let who = ensure_signed(origin)?;
-            ensure!(<Loans<T>>::exists(loan_id), "loan doesn't exists");
+            ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
             let loan = Self::get_loan_by_id(loan_id);
-            ensure!(who == loan.who, "adding collateral to other's loan is not allowed");
+            ensure!(who == loan.who, Error::<T>::NotLoanOwner);
 
             Self::add_loan_collateral(&loan, loan.who.clone(), amount)
         }
 
+        /// let the chain add collateral to the loan when it crosses the warning threshold, None removes the policy
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn set_top_up_policy(origin, loan_id: LoanId, policy: Option<TopUpPolicy<T::Balance>>) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            let who = ensure_signed(origin)?;
+            ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+            let loan = Self::get_loan_by_id(loan_id);
+            ensure!(who == loan.who, Error::<T>::NotLoanOwner);
+            match policy {
+                Some(policy) => {
+                    // a top up has to bring the loan out of the warning
+                    let risk = Self::basket_risk(&Self::collaterals_of(&loan))
+                        .map_err(|_| Error::<T>::Overflow)?;
+                    ensure!(
+                        policy.target_ltv > 0 && policy.target_ltv < risk.warning_threshold,
+                        Error::<T>::InvalidLtv
+                    );
+                    <TopUpPolicies<T>>::insert(loan_id, policy);
+                }
+                None => <TopUpPolicies<T>>::remove(loan_id),
+            }
+            Ok(())
+        }
+
         /// as long as the LTV of this loan is below the "GlobalLTVLimit", user can keep drawing RIO from this loan
         #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
         pub fn draw(origin, loan_id: LoanId, amount: T::Balance) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Borrow)?;
             let who = ensure_signed(origin)?;
             Self::draw_from_loan(who, loan_id, amount)
         }
+
+        /// extend a due loan into a new package instead of letting it expire
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn rollover(origin, loan_id: LoanId, new_package_id: LoanPackageId) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Borrow)?;
+            let who = ensure_signed(origin)?;
+            Self::rollover_loan(who, loan_id, new_package_id)
+        }
+
+        /// repay part of a loan to lower its LTV, the repaid loan asset is burnt
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn repay_partial(origin, loan_id: LoanId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::repay_loan_partially(ensure_signed(origin)?, loan_id, amount)
+        }
+
+        /// take some collateral back, as long as the LTV of this loan stays below the LTV limit of its basket
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn withdraw_collateral(origin, loan_id: LoanId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+            let asset_id = Self::loan_package(Self::get_loan_by_id(loan_id).package_id).collateral_asset_id;
+            Self::withdraw_loan_collateral(ensure_signed(origin)?, loan_id, asset_id, amount)
+        }
+
+        /// add collateral in another asset which has its risk parameters set, into the basket of the loan
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn add_collateral_asset(origin, loan_id: LoanId, asset_id: T::AssetId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            let who = ensure_signed(origin)?;
+            ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+            let loan = Self::get_loan_by_id(loan_id);
+            ensure!(who == loan.who, Error::<T>::NotLoanOwner);
+
+            if asset_id == Self::loan_package(loan.package_id).collateral_asset_id {
+                Self::add_loan_collateral(&loan, who, amount)
+            } else {
+                Self::add_basket_collateral(&loan, who, asset_id, amount)
+            }
+        }
+
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn withdraw_collateral_asset(origin, loan_id: LoanId, asset_id: T::AssetId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::withdraw_loan_collateral(ensure_signed(origin)?, loan_id, asset_id, amount)
+        }
+
+        /// an asset can't be pledged for new credit lines once it's removed, existing lines have no credit left
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_credit_asset(origin, asset_id: T::AssetId, params: Option<CreditParams>) -> RioLoanResult {
+            ensure_root(origin)?;
+            match params {
+                Some(params) => {
+                    ensure!(params.ltv > 0 && params.ltv < LTV::from(LTV_PREC), Error::<T>::InvalidLtv);
+                    if let Some(feed) = params.price_feed {
+                        Self::seed_price(&asset_id, feed)?;
+                    }
+                    <CreditAssets<T>>::insert(asset_id, params);
+                }
+                None => <CreditAssets<T>>::remove(asset_id),
+            }
+            Ok(())
+        }
+
+        /// pledge saving share or IOU assets for a credit line, they stay in the account as reserved balance
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn open_credit_line(origin, asset_id: T::AssetId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::open_line(ensure_signed(origin)?, asset_id, amount)
+        }
+
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn pledge(origin, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::pledge_to_line(ensure_signed(origin)?, line_id, amount)
+        }
+
+        /// take some pledge back, as long as the drawn balance stays within the credit
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn unpledge(origin, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::unpledge_from_line(ensure_signed(origin)?, line_id, amount)
+        }
+
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn draw_credit(origin, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Borrow)?;
+            Self::draw_from_line(ensure_signed(origin)?, line_id, amount)
+        }
+
+        /// the repaid loan asset is burnt, anything over the drawn balance is left untouched
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn repay_credit(origin, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::repay_to_line(ensure_signed(origin)?, line_id, amount)
+        }
+
+        /// a fully repaid line is closed, and all of its pledge is released
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn close_credit_line(origin, line_id: CreditLineId) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::close_line(ensure_signed(origin)?, line_id)
+        }
+
+        /// repay an underwater line up to the close factor, for the pledge worth the repaid balance plus the liquidation discount
+        #[weight = SimpleDispatchInfo::FixedNormal(1000_000)]
+        pub fn liquidate_credit_line(origin, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            Self::ensure_not_tripped(LoanFunction::Liquidation)?;
+            Self::liquidate_line(ensure_signed(origin)?, line_id, amount)
+        }
+
+        /// None removes the tier, accounts of the KYC status are not limited any more
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_borrower_tier(origin, kyc: Option<BlackOrWhite>, tier: Option<BorrowerTier<T::Balance>>) -> RioLoanResult {
+            ensure_root(origin)?;
+            match tier {
+                Some(tier) => {
+                    ensure!(tier.max_ltv > 0 && tier.max_ltv < LTV::from(LTV_PREC), Error::<T>::InvalidLtv);
+                    <BorrowerTiers<T>>::insert(kyc, tier);
+                }
+                None => <BorrowerTiers<T>>::remove(kyc),
+            }
+            Ok(())
+        }
+
+        /// None falls back to the cap of the account's tier
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_account_loan_cap(origin, who: T::AccountId, cap: Option<T::Balance>) -> RioLoanResult {
+            ensure_root(origin)?;
+            match cap {
+                Some(cap) => <AccountLoanCaps<T>>::insert(who, cap),
+                None => <AccountLoanCaps<T>>::remove(who),
+            }
+            Ok(())
+        }
+
+        /// debt already over a lowered ceiling stays, only new loan asset is refused
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_debt_ceiling(origin, asset_id: T::AssetId, ceiling: Option<T::Balance>) -> RioLoanResult {
+            ensure_root(origin)?;
+            match ceiling {
+                Some(ceiling) => <DebtCeilings<T>>::insert(asset_id, ceiling),
+                None => <DebtCeilings<T>>::remove(asset_id),
+            }
+            Ok(())
+        }
+
+        /// loans under the interest model and credit lines pay the fee on top of the model,
+        /// loans of fixed rate packages, and credit lines while there is no model, pay it alone
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_stability_fee(origin, fee: u32) -> RioLoanResult {
+            ensure_root(origin)?;
+            ensure!(fee < INTEREST_RATE_PREC, Error::<T>::InvalidInterestRate);
+            // interest before the change is accrued by the old fee
+            Self::accrue_interest();
+            StabilityFee::put(fee);
+            Ok(())
+        }
+
+        /// None feeds the stability fund without a limit
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_surplus_buffer_cap(origin, cap: Option<T::Balance>) -> RioLoanResult {
+            ensure_root(origin)?;
+            match cap {
+                Some(cap) => <SurplusBufferCap<T>>::put(cap),
+                None => <SurplusBufferCap<T>>::kill(),
+            }
+            Ok(())
+        }
+    }
+}
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// module is paused
+        Paused,
+        /// loan id doesn't exist
+        InvalidLoanId,
+        /// zero is not allowed
+        ZeroNotAllowed,
+        /// loan is in liquidation
+        LoanInLiquidation,
+        /// LTV would exceed the limit
+        OverLTVLimit,
+        /// collateral is below the minimum
+        BelowMinCollateral,
+        /// not owner of the loan
+        NotLoanOwner,
+        /// not enough loan asset to repay
+        NotEnoughToRepay,
+        /// package id doesn't exist or is inactive
+        InvalidPackageId,
+        /// not enough collateral in the loan
+        NotEnoughCollateralInLoan,
+        /// not enough collateral asset in the pawn shop
+        NotEnoughCollateralInShop,
+        /// not enough free collateral asset
+        NotEnoughFreeCollateral,
+        /// no auction for the loan
+        NoAuction,
+        /// partial repayment covers the whole loan, use repay
+        UseRepayToPayOff,
+        /// total collateral underflows
+        TotalCollateralUnderflow,
+        /// not enough credit left to draw
+        ShortOfCredit,
+        /// total loan reached the loan cap
+        ReachLoanCap,
+        /// auction price is over the max price
+        OverMaxPrice,
+        /// packages use different assets
+        PackageAssetsMismatch,
+        /// repay amount is over the close factor
+        OverCloseFactor,
+        /// loan amount is below the minimum
+        BelowMinLoan,
+        /// not enough loan asset to liquidate
+        NotEnoughToLiquidate,
+        /// not enough loan asset to bid
+        NotEnoughToBid,
+        /// asset is not a collateral asset
+        NotCollateralAsset,
+        /// loan is not liquidatable
+        NotLiquidatable,
+        /// loan is not due
+        LoanNotDue,
+        /// loan is not in liquidating
+        NotInLiquidating,
+        /// only the liquidation account is allowed
+        LiquidationAccountOnly,
+        /// thresholds are out of order
+        InvalidThresholds,
+        /// not enough for checking a loan
+        CheckWeightTooLow,
+        /// rate is over 100%
+        InvalidRate,
+        /// package of the loan doesn't exist
+        InvalidLoanPackage,
+        /// kink is over 100%
+        InvalidKink,
+        /// interest rate is not below 100%
+        InvalidInterestRate,
+        /// discount is over 100%
+        InvalidDiscount,
+        /// invalid combination of collateral and loan amount
+        InvalidCollateralLoan,
+        /// close factor is zero or over 100%
+        InvalidCloseFactor,
+        /// auction rates are out of order
+        InvalidAuctionRates,
+        /// interest is not less than the loan amount
+        InterestTooHigh,
+        /// both amounts are zero
+        BothAmountsZero,
+        /// auction is not over
+        AuctionNotOver,
+        /// arithmetic overflow or division by zero
+        Overflow,
+        /// LTV is zero or not below 100%
+        InvalidLtv,
+        /// asset can't be pledged for credit lines
+        NotCreditAsset,
+        /// credit line id doesn't exist
+        InvalidCreditLineId,
+        /// not owner of the credit line
+        NotCreditLineOwner,
+        /// not enough pledge in the credit line
+        NotEnoughPledge,
+        /// credit line has balance drawn
+        CreditLineInDebt,
+        /// package is not open to the borrower's tier
+        PackageNotAllowed,
+        /// outstanding debt of the account would go over its cap
+        OverAccountCap,
+        /// no pending parameter change of the id
+        InvalidParamChangeId,
+        /// debt against the collateral asset would go over its ceiling
+        OverDebtCeiling,
+        /// the price feed has no price yet
+        NoPrice,
+        /// the circuit breaker of the function is tripped
+        FunctionTripped,
     }
 }
 
@@ -467,12 +1166,14 @@ decl_event!(
     pub enum Event<T>
     where
         AccountId = <T as system::Trait>::AccountId,
+        AssetId = <T as rio_assets::Trait>::AssetId,
         Balance = <T as rio_assets::Trait>::Balance,
         Loan = Loan<<T as system::Trait>::AccountId, <T as rio_assets::Trait>::Balance, <T as timestamp::Trait>::Moment>,
         CollateralBalanceOriginal = <T as rio_assets::Trait>::Balance,
         CollateralBalanceAvailable = <T as rio_assets::Trait>::Balance,
         AuctionBalance = <T as rio_assets::Trait>::Balance,
         TotalLoanBalance = <T as rio_assets::Trait>::Balance,
+        BlockNumber = <T as system::Trait>::BlockNumber,
     {
         PackageCreated(LoanPackageId),
         PackageDisabled(LoanPackageId),
@@ -494,10 +1195,44 @@ decl_event!(
         ),
 
         AddCollateral(LoanId, Balance),
+        AddCollateralAsset(LoanId, AssetId, Balance),
+        InterestSettled(LoanId, Balance),
+        LoanPartiallyRepaid(LoanId, Balance, LTV), // (loanid, repaid balance, new LTV)
+        LoanRolledOver(LoanId, LoanPackageId, Balance), // (loanid, new package id, interest)
+        CollateralWithdrawn(LoanId, AssetId, Balance, LTV), // (loanid, asset id, withdrawn balance, new LTV)
+        AuctionStarted(LoanId),
+        AuctionSettled(LoanId, AccountId, Balance, Balance, Balance, Balance), // (loanid, bidder, price, debt, penalty, refund)
+        AuctionShortfall(LoanId, Balance), // (loanid, debt not covered by the auction)
+        BadDebtIncurred(LoanId, Balance), // (loanid, shortfall)
+        BadDebtCovered(Balance, Balance), // (covered by the stability fund, bad debt left)
+        LiquidatedByKeeper(LoanId, AccountId, Balance, Vec<(AssetId, Balance)>), // (loanid, keeper, repaid, seized collateral)
+        CreditLineOpened(CreditLineId, AccountId, AssetId, Balance), // (line id, accountid, pledged asset, pledged balance)
+        CreditPledged(CreditLineId, Balance),
+        CreditUnpledged(CreditLineId, Balance),
+        CreditDrawn(CreditLineId, Balance),
+        CreditRepaid(CreditLineId, Balance),
+        CreditLineClosed(CreditLineId),
+        CreditLineLiquidated(CreditLineId, AccountId, Balance, Balance), // (line id, keeper, repaid, pledge seized)
+        ToppedUp(LoanId, Balance, LTV), // (loanid, collateral pulled by the policy, new LTV)
+        ParamChangeScheduled(ParamChangeId, BlockNumber), // (change id, the block it applies)
+        ParamChangeCancelled(ParamChangeId),
+        ParamChangeApplied(ParamChangeId),
+        ParamChangeRejected(ParamChangeId), // out of order with the parameters when it was due
+        BreakerTripped(LoanFunction, TripReason, Option<BlockNumber>), // (function, reason, the block it resumes by itself)
+        BreakerReset(LoanFunction),
     }
 );
 
-pub type RioLoanResult<T = ()> = result::Result<T, &'static str>;
+pub type RioLoanResult<T = ()> = result::Result<T, DispatchError>;
+
+type LoanBreakers<T> =
+    Breakers<FunctionBreakers<T>, LoanFunction, <T as system::Trait>::BlockNumber>;
+type RiskTimelock<T> = Timelock<
+    PendingParamChanges<T>,
+    NextParamChangeId,
+    RiskParam<<T as rio_assets::Trait>::Balance>,
+    <T as system::Trait>::BlockNumber,
+>;
 
 impl<T: Trait> Module<T> {
     pub fn create_loan_package(
@@ -526,7 +1261,7 @@ impl<T: Trait> Module<T> {
 
     /// disable a loan package won't delete it, just make it impossible to create new loans by this package
     pub fn disable_loan_package(package_id: LoanPackageId) -> DispatchResult {
-        ensure!(<LoanPackages<T>>::exists(package_id), "invalid package id");
+        ensure!(<LoanPackages<T>>::exists(package_id), Error::<T>::InvalidPackageId);
         <LoanPackages<T>>::mutate(package_id, |v| {
             v.status = LoanPackageStatus::Inactive;
         });
@@ -538,27 +1273,28 @@ impl<T: Trait> Module<T> {
 
     pub fn repay_loan(who: T::AccountId, loan_id: LoanId) -> DispatchResult {
         let pawn_shop = Self::pawn_shop();
-        ensure!(<Loans<T>>::exists(loan_id), "invalid loan id");
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(<Loans<T>>::get(loan_id).who == who, Error::<T>::NotLoanOwner);
+        Self::settle_interest(loan_id)?;
         let loan = <Loans<T>>::get(loan_id);
-        ensure!(loan.who == who, "not owner of the loan");
         ensure!(
             <LoanPackages<T>>::exists(loan.package_id),
-            "invalid package id in loan"
+            Error::<T>::InvalidLoanPackage
         );
         let package = Self::loan_package(loan.package_id);
         ensure!(
             <rio_assets::Module<T>>::free_balance(&package.loan_asset_id, &who)
                 >= loan.loan_balance_total,
-            "not enough asset to repay"
+            Error::<T>::NotEnoughToRepay
         );
         ensure!(
             <rio_assets::Module<T>>::free_balance(&package.collateral_asset_id, &pawn_shop)
                 >= loan.collateral_balance_available,
-            "not enough collateral asset in shop"
+            Error::<T>::NotEnoughCollateralInShop
         );
         ensure!(
             !Self::check_loan_in_liquidation(&loan_id),
-            "loan is in liquidation"
+            Error::<T>::LoanInLiquidation
         );
 
         // remove this loan from the system
@@ -572,7 +1308,7 @@ impl<T: Trait> Module<T> {
                 .collect::<Vec<LoanId>>();
         });
         // decrease total loan amount and collateral amount
-        <TotalLoan<T>>::mutate(|v| *v -= loan.loan_balance_total);
+        Self::decrease_debt(&package.collateral_asset_id, loan.loan_balance_total);
         <TotalCollateral<T>>::mutate(|v| *v -= loan.collateral_balance_available);
 
         // callback to be called when unexpected happens
@@ -581,7 +1317,7 @@ impl<T: Trait> Module<T> {
             <LoansByAccount<T>>::mutate(&who, |v| {
                 v.push(loan.id);
             });
-            <TotalLoan<T>>::mutate(|v| *v += loan.loan_balance_total);
+            Self::increase_debt(&package.collateral_asset_id, loan.loan_balance_total);
             <TotalCollateral<T>>::mutate(|v| *v += loan.collateral_balance_available);
         };
 
@@ -620,6 +1356,21 @@ impl<T: Trait> Module<T> {
             pawn_shop.clone(),
             loan.loan_balance_total,
         )?;
+        // return the collateral in other assets
+        for (asset_id, balance) in Self::loan_basket(loan_id) {
+            <rio_assets::Module<T>>::make_transfer_with_event(
+                &asset_id,
+                &pawn_shop,
+                &who,
+                balance,
+            )?;
+        }
+
+        LoanBorrowIndex::remove(loan_id);
+        LoanFeeIndex::remove(loan_id);
+        <TopUpPolicies<T>>::remove(loan_id);
+        Self::put_basket(loan_id, vec![]);
+        Self::unindex_loan(loan_id);
 
         Self::deposit_event(RawEvent::LoanRepaid(
             loan_id,
@@ -629,43 +1380,166 @@ impl<T: Trait> Module<T> {
         Ok(())
     }
 
-    /// keep borrowing from a loan which has not reach the LTV limit
-    pub fn draw_from_loan(
+    pub fn repay_loan_partially(
         who: T::AccountId,
         loan_id: LoanId,
         amount: T::Balance,
     ) -> DispatchResult {
-        ensure!(<Loans<T>>::exists(loan_id), "invalid loan id");
-        let loan = Self::get_loan_by_id(loan_id);
-        ensure!(loan.who == who, "can't draw from others loan");
-        let btc_price = Self::current_btc_price();
-        let global_ltv = Self::global_ltv_limit();
-        let available_credit = loan.collateral_balance_available
-            * <T::Balance as TryFrom<u128>>::try_from(btc_price)
-                .ok()
-                .unwrap()
-            * <T::Balance as TryFrom<u64>>::try_from(global_ltv)
-                .ok()
-                .unwrap()
-            / T::Balance::from(LTV_PREC)
-            / T::Balance::from(PRICE_PREC)
-            - loan.loan_balance_total;
-        ensure!(amount <= available_credit, "short of available credit");
-
-        let profit_pool = Self::profit_pool();
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(<Loans<T>>::get(loan_id).who == who, Error::<T>::NotLoanOwner);
+        ensure!(
+            !Self::check_loan_in_liquidation(&loan_id),
+            Error::<T>::LoanInLiquidation
+        );
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        Self::settle_interest(loan_id)?;
+        let loan = <Loans<T>>::get(loan_id);
+        ensure!(
+            amount < loan.loan_balance_total,
+            Error::<T>::UseRepayToPayOff
+        );
         let package = Self::loan_package(loan.package_id);
-        let interest = package.get_interest(amount);
-        // mint the interest, and send it into the profit pool
-        <rio_assets::Module<T>>::mint(
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&package.loan_asset_id, &who) >= amount,
+            Error::<T>::NotEnoughToRepay
+        );
+        let loan_balance_total = loan.loan_balance_total - amount;
+        let risk = Self::basket_risk(&Self::collaterals_of(&loan))
+            .map_err(|_| Error::<T>::Overflow)?;
+        let ltv = Self::basket_ltv(loan_balance_total, &risk);
+
+        // RIO requirements, the repaid asset should be burnt
+        <rio_assets::Module<T>>::burn(
             system::RawOrigin::Root.into(),
-            package.loan_asset_id.clone(),
-            profit_pool.clone(),
-            interest,
+            package.loan_asset_id,
+            who,
+            amount,
         )?;
-        // apart from the interest, the rest are send to borrower's account
-        <rio_assets::Module<T>>::mint(
-            system::RawOrigin::Root.into(),
-            package.loan_asset_id.clone(),
+
+        <Loans<T>>::mutate(loan_id, |v| {
+            v.loan_balance_total = loan_balance_total;
+            // out of warning
+            if let LoanHealth::Warning(_) = v.status {
+                if ltv < risk.warning_threshold {
+                    v.status = LoanHealth::Well;
+                }
+            }
+        });
+        Self::decrease_debt(&package.collateral_asset_id, amount);
+
+        Self::index_loan(loan_id);
+        Self::deposit_event(RawEvent::LoanPartiallyRepaid(loan_id, amount, ltv));
+        Ok(())
+    }
+
+    /// withdraw from either the collateral of the package's asset or the basket
+    pub fn withdraw_loan_collateral(
+        who: T::AccountId,
+        loan_id: LoanId,
+        asset_id: T::AssetId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(<Loans<T>>::get(loan_id).who == who, Error::<T>::NotLoanOwner);
+        ensure!(
+            !Self::check_loan_in_liquidation(&loan_id),
+            Error::<T>::LoanInLiquidation
+        );
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        Self::settle_interest(loan_id)?;
+        let loan = <Loans<T>>::get(loan_id);
+        let is_basket = asset_id != Self::loan_package(loan.package_id).collateral_asset_id;
+
+        let mut collaterals = Self::collaterals_of(&loan);
+        let collateral = collaterals
+            .iter_mut()
+            .find(|(a, _)| *a == asset_id)
+            .ok_or(Error::<T>::NotEnoughCollateralInLoan)?;
+        let collateral_left = collateral
+            .1
+            .checked_sub(&amount)
+            .ok_or(Error::<T>::NotEnoughCollateralInLoan)?;
+        // only basket assets can be withdrawn entirely
+        ensure!(
+            (is_basket && collateral_left.is_zero())
+                || (!collateral_left.is_zero()
+                    && collateral_left >= Self::risk_params(&asset_id).minimum_collateral),
+            Error::<T>::BelowMinCollateral
+        );
+        collateral.1 = collateral_left;
+        let risk = Self::basket_risk(&collaterals).map_err(|_| Error::<T>::Overflow)?;
+        let ltv = Self::basket_ltv(loan.loan_balance_total, &risk);
+        ensure!(ltv < risk.ltv_limit, Error::<T>::OverLTVLimit);
+
+        let pawnshop = Self::pawn_shop();
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&asset_id, &pawnshop) >= amount,
+            Error::<T>::NotEnoughCollateralInShop
+        );
+        <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &pawnshop, &who, amount)?;
+
+        if is_basket {
+            Self::put_basket(loan_id, collaterals.split_off(1));
+        } else {
+            <Loans<T>>::mutate(loan_id, |v| {
+                v.collateral_balance_original =
+                    v.collateral_balance_original.saturating_sub(amount);
+                v.collateral_balance_available = collateral_left;
+            });
+            <TotalCollateral<T>>::mutate(|v| *v = v.saturating_sub(amount));
+        }
+
+        Self::index_loan(loan_id);
+        Self::deposit_event(RawEvent::CollateralWithdrawn(loan_id, asset_id, amount, ltv));
+        Ok(())
+    }
+
+    /// keep borrowing from a loan which has not reach the LTV limit
+    pub fn draw_from_loan(
+        who: T::AccountId,
+        loan_id: LoanId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(Self::get_loan_by_id(loan_id).who == who, Error::<T>::NotLoanOwner);
+        Self::settle_interest(loan_id)?;
+        let loan = Self::get_loan_by_id(loan_id);
+        let tier = Self::tier_of(&who);
+        if let Some(tier) = &tier {
+            ensure!(tier.allows(loan.package_id), Error::<T>::PackageNotAllowed);
+        }
+        let risk = Self::basket_risk(&Self::collaterals_of(&loan))
+            .map_err(|_| Error::<T>::Overflow)?;
+        let available_credit = Self::tier_available_credit(&tier, loan.loan_balance_total, &risk)
+            .map_err(|_| Error::<T>::Overflow)?;
+        ensure!(amount <= available_credit, Error::<T>::ShortOfCredit);
+        if let Some(room) = Self::borrowing_room(&who, &tier) {
+            ensure!(amount <= room, Error::<T>::OverAccountCap);
+        }
+
+        let profit_pool = Self::profit_pool();
+        let package = Self::loan_package(loan.package_id);
+        Self::check_debt_ceiling(&package.collateral_asset_id, amount)?;
+        // interest of loans under the interest model accrues afterwards
+        let interest = if LoanBorrowIndex::exists(loan_id) {
+            T::Balance::zero()
+        } else {
+            package.get_interest(amount)
+                .map_err(|_| Error::<T>::Overflow)?
+        };
+        ensure!(interest < amount, Error::<T>::InterestTooHigh);
+        // mint the interest, and send it into the profit pool
+        <rio_assets::Module<T>>::mint(
+            system::RawOrigin::Root.into(),
+            package.loan_asset_id.clone(),
+            profit_pool.clone(),
+            interest,
+        )?;
+        // apart from the interest, the rest are send to borrower's account
+        <rio_assets::Module<T>>::mint(
+            system::RawOrigin::Root.into(),
+            package.loan_asset_id.clone(),
             who.clone(),
             amount - interest,
         )
@@ -682,55 +1556,171 @@ impl<T: Trait> Module<T> {
         <Loans<T>>::mutate(loan_id, |v| {
             v.loan_balance_total += amount;
         });
-        <TotalLoan<T>>::mutate(|v| *v += amount);
+        Self::increase_debt(&package.collateral_asset_id, amount);
         <TotalProfit<T>>::mutate(|v| *v += interest);
+        Self::feed_stability_fund(package.loan_asset_id, interest);
 
+        Self::index_loan(loan_id);
         Self::deposit_event(RawEvent::LoanDrawn(loan_id, amount));
 
         Ok(())
     }
 
-    pub fn apply_for_loan(
+    /// The interest of the new package is charged on the whole loan balance, added to the balance like a draw,
+    /// no penalty at all. Loans under the interest model keep accruing and are charged nothing upfront.
+    pub fn rollover_loan(
         who: T::AccountId,
+        loan_id: LoanId,
+        new_package_id: LoanPackageId,
+    ) -> DispatchResult {
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(Self::get_loan_by_id(loan_id).who == who, Error::<T>::NotLoanOwner);
+        ensure!(
+            !Self::check_loan_in_liquidation(&loan_id),
+            Error::<T>::LoanInLiquidation
+        );
+        ensure!(
+            <ActiveLoanPackages<T>>::exists(new_package_id),
+            Error::<T>::InvalidPackageId
+        );
+        let now = <timestamp::Module<T>>::get();
+        ensure!(Self::get_loan_by_id(loan_id).due <= now, Error::<T>::LoanNotDue);
+        let package = Self::loan_package(Self::get_loan_by_id(loan_id).package_id);
+        let new_package = <ActiveLoanPackages<T>>::get(new_package_id);
+        ensure!(
+            package.collateral_asset_id == new_package.collateral_asset_id
+                && package.loan_asset_id == new_package.loan_asset_id,
+            Error::<T>::PackageAssetsMismatch
+        );
+
+        Self::settle_interest(loan_id)?;
+        let loan = Self::get_loan_by_id(loan_id);
+        let interest = if LoanBorrowIndex::exists(loan_id) {
+            T::Balance::zero()
+        } else {
+            new_package.get_interest(loan.loan_balance_total)
+                .map_err(|_| Error::<T>::Overflow)?
+        };
+        let loan_balance_total = loan.loan_balance_total + interest;
+        let risk = Self::basket_risk(&Self::collaterals_of(&loan))
+            .map_err(|_| Error::<T>::Overflow)?;
+        let ltv = Self::basket_ltv(loan_balance_total, &risk);
+        ensure!(ltv <= risk.ltv_limit, Error::<T>::OverLTVLimit);
+
+        // mint the interest, and send it into the profit pool
+        <rio_assets::Module<T>>::mint(
+            system::RawOrigin::Root.into(),
+            new_package.loan_asset_id,
+            Self::profit_pool(),
+            interest,
+        )?;
+        let (due, due_extend) = new_package.get_dues(now);
+        <Loans<T>>::mutate(loan_id, |v| {
+            v.package_id = new_package_id;
+            v.loan_balance_total = loan_balance_total;
+            v.due = due;
+            v.due_extend = due_extend;
+            v.status = if ltv >= risk.warning_threshold {
+                LoanHealth::Warning(ltv)
+            } else {
+                LoanHealth::Well
+            };
+        });
+        Self::increase_debt(&new_package.collateral_asset_id, interest);
+        <TotalProfit<T>>::mutate(|v| *v += interest);
+        Self::feed_stability_fund(new_package.loan_asset_id, interest);
+
+        Self::index_loan(loan_id);
+        Self::deposit_event(RawEvent::LoanRolledOver(loan_id, new_package_id, interest));
+        Ok(())
+    }
+
+    /// Immutable
+    /// the checks of an application, shared by `apply_for_loan` and `quote_apply`,
+    /// gives the actual amounts and the interest charged upfront
+    fn check_application(
+        who: &T::AccountId,
         package_id: LoanPackageId,
         collateral_amount: T::Balance,
         loan_amount: T::Balance,
-    ) -> DispatchResult {
+    ) -> Result<(CollateralLoan<T::Balance>, T::Balance), DispatchError> {
         ensure!(
             !(collateral_amount.is_zero() && loan_amount.is_zero()),
-            "invalid combination of collateral & loan amount"
+            Error::<T>::InvalidCollateralLoan
         );
         ensure!(
             <ActiveLoanPackages<T>>::exists(package_id),
-            "invalid package id"
+            Error::<T>::InvalidPackageId
         );
         let package = <ActiveLoanPackages<T>>::get(package_id);
-        let shop = <PawnShop<T>>::get();
-        let profit_pool = Self::profit_pool();
         let loan_cap = <LoanCap<T>>::get();
         let total_loan = <TotalLoan<T>>::get();
 
         if loan_cap.is_some() && total_loan >= loan_cap.unwrap() {
-            return Err("reach loan limit");
+            return Err(Error::<T>::ReachLoanCap.into());
+        }
+        let tier = Self::tier_of(who);
+        if let Some(tier) = &tier {
+            ensure!(tier.allows(package_id), Error::<T>::PackageNotAllowed);
+        }
+
+        let actual = Self::get_collateral_loan(
+            &package.collateral_asset_id,
+            collateral_amount,
+            loan_amount,
+        )?;
+        ensure!(
+            package.min <= actual.loan_amount,
+            Error::<T>::BelowMinLoan
+        );
+        ensure!(
+            collateral_amount >= Self::risk_params(&package.collateral_asset_id).minimum_collateral,
+            Error::<T>::BelowMinCollateral
+        );
+        if let Some(tier) = &tier {
+            let risk = Self::basket_risk(&[(package.collateral_asset_id, actual.collateral_amount)])
+                .map_err(|_| Error::<T>::Overflow)?;
+            ensure!(
+                Self::basket_ltv(actual.loan_amount, &risk) <= tier.max_ltv,
+                Error::<T>::OverLTVLimit
+            );
         }
+        if let Some(room) = Self::borrowing_room(who, &tier) {
+            ensure!(actual.loan_amount <= room, Error::<T>::OverAccountCap);
+        }
+        Self::check_debt_ceiling(&package.collateral_asset_id, actual.loan_amount)?;
+
+        let interest = if Self::interest_model().is_some() {
+            T::Balance::zero()
+        } else {
+            package
+                .get_interest(actual.loan_amount)
+                .map_err(|_| Error::<T>::Overflow)?
+        };
+        ensure!(interest < actual.loan_amount, Error::<T>::InterestTooHigh);
+        Ok((actual, interest))
+    }
+
+    pub fn apply_for_loan(
+        who: T::AccountId,
+        package_id: LoanPackageId,
+        collateral_amount: T::Balance,
+        loan_amount: T::Balance,
+    ) -> DispatchResult {
+        let package = <ActiveLoanPackages<T>>::get(package_id);
+        let shop = <PawnShop<T>>::get();
+        let profit_pool = Self::profit_pool();
 
-        match Self::get_collateral_loan(collateral_amount, loan_amount) {
+        match Self::check_application(&who, package_id, collateral_amount, loan_amount) {
             Err(err) => Err(err),
-            Ok(CollateralLoan {
-                collateral_amount: actual_collateral_amount,
-                loan_amount: actual_loan_amount,
-            }) => {
-                ensure!(
-                    package.min <= actual_loan_amount,
-                    "not reach min loan amount"
-                );
-                ensure!(
-                    collateral_amount >= Self::minimum_collateral(),
-                    "not reach min collateral amount"
-                );
-
-                let interest = package.get_interest(actual_loan_amount);
-                ensure!(interest < actual_loan_amount, "interest is too high");
+            Ok((
+                CollateralLoan {
+                    collateral_amount: actual_collateral_amount,
+                    loan_amount: actual_loan_amount,
+                },
+                interest,
+            )) => {
+                let interest_model = Self::interest_model();
                 // move enough collateral from user account into rio loan system
                 <rio_assets::Module<T>>::make_transfer_with_event(
                     &package.collateral_asset_id,
@@ -799,11 +1789,19 @@ impl<T: Trait> Module<T> {
                 <LoansByAccount<T>>::mutate(&who, |v| {
                     v.push(loan_id);
                 });
+                Self::accrue_interest();
+                if interest_model.is_some() {
+                    LoanBorrowIndex::insert(loan_id, Self::borrow_index());
+                } else {
+                    LoanFeeIndex::insert(loan_id, Self::fee_index());
+                }
                 // some system bookings
-                <TotalLoan<T>>::mutate(|v| *v += actual_loan_amount);
+                Self::increase_debt(&package.collateral_asset_id, actual_loan_amount);
                 <TotalCollateral<T>>::mutate(|v| *v += actual_collateral_amount);
                 <TotalProfit<T>>::mutate(|v| *v += interest);
+                Self::feed_stability_fund(package.loan_asset_id, interest);
 
+                Self::index_loan(loan_id);
                 Self::deposit_event(RawEvent::LoanCreated(loan));
                 Ok(())
             }
@@ -817,30 +1815,47 @@ impl<T: Trait> Module<T> {
     ) -> DispatchResult {
         ensure!(
             Self::check_loan_in_liquidation(&loan.id),
-            "loan id not in liquidating"
+            Error::<T>::NotInLiquidating
         );
         let pawnshop = Self::pawn_shop();
         let package = Self::loan_package(loan.package_id);
         ensure!(
             <rio_assets::Module<T>>::free_balance(&package.loan_asset_id, &liquidation_account)
                 >= auction_balance,
-            "not enough asset to liquidate"
+            Error::<T>::NotEnoughToLiquidate
         );
+        // the sale only covers the package's asset, the rest of the basket goes back to the owner,
+        // or to the stability fund which takes the bad debt if the sale falls short
+        let repaid = auction_balance.min(loan.loan_balance_total);
+        let basket = Self::loan_basket(loan.id);
+        for (asset_id, balance) in basket.iter() {
+            ensure!(
+                <rio_assets::Module<T>>::free_balance(asset_id, &pawnshop) >= *balance,
+                Error::<T>::NotEnoughCollateralInShop
+            );
+        }
+        let basket_to = match Self::stability_fund() {
+            Some(fund) if repaid < loan.loan_balance_total => fund,
+            _ => loan.who.clone(),
+        };
         // first move the amount of asset previously got by OTC into "pawn shop"
         // pawn shop is explained in the storage field
         <rio_assets::Module<T>>::make_transfer_with_event(
             &package.loan_asset_id,
             &liquidation_account,
             &pawnshop,
-            loan.loan_balance_total,
+            repaid,
         )?;
         // "loan_balance_total" is what the borrower owes the system,
         // after even that debt, the rest from the auction will be used to calculate the liquidation penalty
         let mut leftover = auction_balance.checked_sub(&loan.loan_balance_total);
         if leftover.is_some() && leftover.unwrap() > T::Balance::zero() {
             let penalty_rate = Self::liquidation_penalty();
+            // the penalty never exceeds the leftover
             let penalty =
-                leftover.unwrap() * T::Balance::from(penalty_rate) / T::Balance::from(LTV_PREC);
+                balance_mul_div(leftover.unwrap(), u128::from(penalty_rate), u128::from(LTV_PREC))
+                    .unwrap_or_else(|_| leftover.unwrap())
+                    .min(leftover.unwrap());
             // transfer the penalty into profit pool
             <rio_assets::Module<T>>::make_transfer_with_event(
                 &package.loan_asset_id,
@@ -883,21 +1898,23 @@ impl<T: Trait> Module<T> {
             // the final non-zero leftover is for the event detail
             leftover = Some(leftover.unwrap() - penalty)
         }
-        // after liquidation, remove the loan from the system
-        <Loans<T>>::remove(&loan.id);
-        <LoansByAccount<T>>::mutate(&loan.who, |v| {
-            *v = v
-                .clone()
-                .into_iter()
-                .filter(|ele| ele != &loan.id)
-                .collect::<Vec<LoanId>>();
-        });
-        LiquidatingLoans::mutate(|v| {
-            *v = v
-                .clone()
-                .into_iter()
-                .filter(|ele| ele != &loan.id)
-                .collect::<Vec<LoanId>>();
+        // RIO requirements, the repaid loan asset should be burnt
+        <rio_assets::Module<T>>::burn(
+            system::RawOrigin::Root.into(),
+            package.loan_asset_id,
+            pawnshop.clone(),
+            repaid,
+        )?;
+        // checked above, nothing is left in the pawn shop for the basket
+        for (asset_id, balance) in basket {
+            <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &pawnshop, &basket_to, balance)?;
+        }
+        // after liquidation, remove the loan from the system,
+        // the whole debt is off the books, what the sale didn't cover goes to the bad debt
+        Self::close_loan(loan);
+        Self::decrease_debt(&package.collateral_asset_id, loan.loan_balance_total);
+        <TotalCollateral<T>>::mutate(|v| {
+            *v = v.saturating_sub(loan.collateral_balance_available)
         });
         Self::deposit_event(RawEvent::Liquidated(
             loan.id,
@@ -905,8 +1922,12 @@ impl<T: Trait> Module<T> {
             loan.collateral_balance_available,
             auction_balance,
             loan.loan_balance_total,
-            leftover.unwrap(),
+            leftover.unwrap_or_default(),
         ));
+        // what the auction can't cover is bad debt
+        if repaid < loan.loan_balance_total {
+            Self::incur_bad_debt(loan.id, package.loan_asset_id, loan.loan_balance_total - repaid)?;
+        }
 
         Ok(())
     }
@@ -921,9 +1942,10 @@ impl<T: Trait> Module<T> {
         let pawnshop = Self::pawn_shop();
         let package = Self::loan_package(loan.package_id);
 
+        Self::settle_interest(loan.id)?;
         ensure!(
             <rio_assets::Module<T>>::free_balance(&package.collateral_asset_id, &from) >= amount,
-            "not enough collateral asset in free balance"
+            Error::<T>::NotEnoughFreeCollateral
         );
         // transfer the amount of collateral from borrower's account to the platform
         <rio_assets::Module<T>>::make_transfer_with_event(
@@ -942,33 +1964,251 @@ impl<T: Trait> Module<T> {
             *c += amount;
         });
 
+        Self::index_loan(loan.id);
         Self::deposit_event(RawEvent::AddCollateral(loan.id, amount));
 
         Ok(())
     }
 
+    /// pull collateral by the top-up policy of the loan, as much as it takes to get to the target LTV
+    /// within the policy and the free balance of the borrower. false if nothing is pulled.
+    fn top_up_loan(loan: &Loan<T::AccountId, T::Balance, T::Moment>) -> bool {
+        let mut policy = match Self::top_up_policy(loan.id) {
+            Some(policy) => policy,
+            None => return false,
+        };
+        let asset_id = Self::loan_package(loan.package_id).collateral_asset_id;
+        let price = Self::price_of(&asset_id, &Self::risk_params(&asset_id));
+        let risk = match Self::basket_risk(&Self::collaterals_of(loan)) {
+            Ok(risk) => risk,
+            Err(_) => return false,
+        };
+        let needed = match Self::top_up_amount(Self::loan_debt(loan), &risk, policy.target_ltv, price) {
+            Ok(needed) => needed,
+            Err(_) => return false,
+        };
+        let amount = needed
+            .min(policy.max_amount)
+            .min(<rio_assets::Module<T>>::free_balance(&asset_id, &loan.who));
+        if amount.is_zero() || Self::add_loan_collateral(loan, loan.who.clone(), amount).is_err() {
+            return false;
+        }
+
+        policy.max_amount -= amount;
+        <TopUpPolicies<T>>::insert(loan.id, policy);
+        let loan = Self::get_loan_by_id(loan.id);
+        let ltv = Self::basket_risk(&Self::collaterals_of(&loan))
+            .map(|risk| Self::basket_ltv(Self::loan_debt(&loan), &risk))
+            .unwrap_or_else(|_| LTV::max_value());
+        Self::deposit_event(RawEvent::ToppedUp(loan.id, amount, ltv));
+        true
+    }
+
+    /// Immutable
+    /// collateral at the price that brings the basket to the target LTV, rounding up
+    pub fn top_up_amount(
+        debt: T::Balance,
+        risk: &BasketRisk<T::Balance>,
+        target_ltv: LTV,
+        price: Price,
+    ) -> MathResult<T::Balance> {
+        let value = mul_div(to_u128(debt)?, u128::from(LTV_PREC), u128::from(target_ltv))?;
+        let value = value.saturating_sub(to_u128(risk.value)?);
+        if value == 0 {
+            return Ok(T::Balance::zero());
+        }
+        from_u128(mul_div(value, u128::from(PRICE_PREC), price)?.saturating_add(1))
+    }
+
+    /// collateral in assets other than the package's goes into the basket of the loan
+    pub fn add_basket_collateral(
+        loan: &Loan<T::AccountId, T::Balance, T::Moment>,
+        from: T::AccountId,
+        asset_id: T::AssetId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        let params = Self::collateral_params(&asset_id).ok_or(Error::<T>::NotCollateralAsset)?;
+        let mut basket = Self::loan_basket(loan.id);
+        let held = basket
+            .iter()
+            .find(|(a, _)| *a == asset_id)
+            .map(|(_, b)| *b)
+            .unwrap_or_default();
+        ensure!(
+            held + amount >= params.minimum_collateral,
+            Error::<T>::BelowMinCollateral
+        );
+
+        Self::settle_interest(loan.id)?;
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&asset_id, &from) >= amount,
+            Error::<T>::NotEnoughFreeCollateral
+        );
+        <rio_assets::Module<T>>::make_transfer_with_event(
+            &asset_id,
+            &from,
+            &Self::pawn_shop(),
+            amount,
+        )?;
+
+        match basket.iter_mut().find(|(a, _)| *a == asset_id) {
+            Some((_, b)) => *b += amount,
+            None => basket.push((asset_id, amount)),
+        }
+        Self::put_basket(loan.id, basket);
+
+        Self::index_loan(loan.id);
+        Self::deposit_event(RawEvent::AddCollateralAsset(loan.id, asset_id, amount));
+        Ok(())
+    }
+
+    fn put_basket(loan_id: LoanId, mut basket: Vec<(T::AssetId, T::Balance)>) {
+        basket.retain(|(_, b)| !b.is_zero());
+        let old = Self::loan_basket(loan_id);
+        for (asset_id, _) in old.iter() {
+            if !basket.iter().any(|(a, _)| a == asset_id) {
+                <BasketLoans<T>>::mutate(asset_id, |v| v.retain(|id| *id != loan_id));
+            }
+        }
+        for (asset_id, _) in basket.iter() {
+            if !old.iter().any(|(a, _)| a == asset_id) {
+                <BasketLoans<T>>::mutate(asset_id, |v| v.push(loan_id));
+            }
+        }
+        if basket.is_empty() {
+            <LoanBasket<T>>::remove(loan_id);
+        } else {
+            <LoanBasket<T>>::insert(loan_id, basket);
+        }
+    }
+
+    /// risk parameters of a collateral asset, the global ones if not set
+    pub fn risk_params(asset_id: &T::AssetId) -> CollateralParams<T::Balance> {
+        Self::collateral_params(asset_id).unwrap_or_else(|| CollateralParams {
+            ltv_limit: Self::global_ltv_limit(),
+            warning_threshold: Self::global_warning_threshold(),
+            liquidation_threshold: Self::global_liquidation_threshold(),
+            minimum_collateral: Self::minimum_collateral(),
+            price_feed: None,
+        })
+    }
+
+    pub fn price_of(asset_id: &T::AssetId, params: &CollateralParams<T::Balance>) -> Price {
+        match params.price_feed {
+            Some(_) => Self::collateral_price(asset_id),
+            None => Self::current_btc_price(),
+        }
+    }
+
+    /// the feed may have changed before the asset is added, so its price is taken from rio-price
+    fn seed_price(asset_id: &T::AssetId, feed: FeedId) -> DispatchResult {
+        let price = rio_price::FeedPrice::get(feed);
+        ensure!(!price.is_zero(), Error::<T>::NoPrice);
+        <CollateralPrices<T>>::insert(asset_id, price);
+        Ok(())
+    }
+
+    /// Immutable
+    /// all collateral of a loan, the package's asset goes first
+    pub fn collaterals_of(
+        loan: &Loan<T::AccountId, T::Balance, T::Moment>,
+    ) -> Vec<(T::AssetId, T::Balance)> {
+        let mut collaterals = vec![(
+            Self::loan_package(loan.package_id).collateral_asset_id,
+            loan.collateral_balance_available,
+        )];
+        collaterals.extend(Self::loan_basket(loan.id));
+        collaterals
+    }
+
+    /// Immutable
+    /// an empty basket, with the global thresholds
+    pub fn global_risk() -> BasketRisk<T::Balance> {
+        BasketRisk {
+            value: T::Balance::zero(),
+            ltv_limit: Self::global_ltv_limit(),
+            warning_threshold: Self::global_warning_threshold(),
+            liquidation_threshold: Self::global_liquidation_threshold(),
+        }
+    }
+
+    /// Immutable
+    pub fn basket_risk(
+        collaterals: &[(T::AssetId, T::Balance)],
+    ) -> MathResult<BasketRisk<T::Balance>> {
+        let weigh = |sum: u128, value: u128, ltv: LTV| {
+            checked_add(sum, checked_mul(value, u128::from(ltv))?)
+        };
+
+        let mut value = 0u128;
+        let mut ltv_limit = 0u128;
+        let mut warning = 0u128;
+        let mut liquidation = 0u128;
+        for (asset_id, amount) in collaterals {
+            let params = Self::risk_params(asset_id);
+            let price = Self::price_of(asset_id, &params);
+            let v = mul_div(to_u128(*amount)?, price, u128::from(PRICE_PREC))?;
+            value = checked_add(value, v)?;
+            ltv_limit = weigh(ltv_limit, v, params.ltv_limit)?;
+            warning = weigh(warning, v, params.warning_threshold)?;
+            liquidation = weigh(liquidation, v, params.liquidation_threshold)?;
+        }
+
+        if value.is_zero() {
+            return Ok(Self::global_risk());
+        }
+        Ok(BasketRisk {
+            value: from_u128(value)?,
+            ltv_limit: from_u128(checked_div(ltv_limit, value)?)?,
+            warning_threshold: from_u128(checked_div(warning, value)?)?,
+            liquidation_threshold: from_u128(checked_div(liquidation, value)?)?,
+        })
+    }
+
+    /// Immutable
+    /// debt beyond the precision counts as the highest LTV
+    pub fn basket_ltv(debt: T::Balance, risk: &BasketRisk<T::Balance>) -> LTV {
+        to_u128(debt)
+            .and_then(|debt| mul_div(debt, u128::from(LTV_PREC), to_u128(risk.value)?))
+            .and_then(from_u128)
+            .unwrap_or_else(|_| LTV::max_value())
+    }
+
+    /// Immutable
+    /// how much more can be borrowed against the basket before hitting its LTV limit
+    pub fn available_credit(
+        debt: T::Balance,
+        risk: &BasketRisk<T::Balance>,
+    ) -> MathResult<T::Balance> {
+        let credit =
+            balance_mul_div(risk.value, u128::from(risk.ltv_limit), u128::from(LTV_PREC))?;
+        Ok(credit.saturating_sub(debt))
+    }
+
     /// Immutable.
-    /// Respect the LTV limit.
+    /// Respect the LTV limit of the collateral asset.
     pub fn get_collateral_loan(
+        asset_id: &T::AssetId,
         collateral_amount: T::Balance,
         loan_amount: T::Balance,
-    ) -> Result<CollateralLoan<T::Balance>, &'static str> {
+    ) -> Result<CollateralLoan<T::Balance>, DispatchError> {
         if collateral_amount.is_zero() && loan_amount.is_zero() {
-            return Err("both amount are zero");
+            return Err(Error::<T>::BothAmountsZero.into());
         }
 
-        let btc_price = CurrentBTCPrice::get();
-        let ltv = GlobalLTVLimit::get();
-        let btc_price_in_balance = <T::Balance as TryFrom<u128>>::try_from(btc_price)
-            .ok()
-            .unwrap();
-        let price_prec_in_balance = T::Balance::from(PRICE_PREC);
-        let ltv_prec_in_balance = T::Balance::from(LTV_PREC);
-        let ltv_in_balance = <T::Balance as TryFrom<u64>>::try_from(ltv).ok().unwrap();
+        let params = Self::risk_params(asset_id);
+        let btc_price = Self::price_of(asset_id, &params);
+        let ltv = u128::from(params.ltv_limit);
+        let prec = u128::from(LTV_PREC) * u128::from(PRICE_PREC);
+        let overflow = |_| Error::<T>::Overflow;
 
         if collateral_amount.is_zero() {
-            let must_collateral_amount = loan_amount * ltv_prec_in_balance * price_prec_in_balance
-                / (btc_price_in_balance * ltv_in_balance);
+            let must_collateral_amount = balance_mul_div(
+                loan_amount,
+                prec,
+                checked_mul(btc_price, ltv).map_err(overflow)?,
+            )
+            .map_err(overflow)?;
             return Ok(CollateralLoan {
                 collateral_amount: must_collateral_amount,
                 loan_amount,
@@ -976,20 +2216,30 @@ impl<T: Trait> Module<T> {
         }
 
         if loan_amount.is_zero() {
-            let can_loan_amount = (collateral_amount * btc_price_in_balance * ltv_in_balance)
-                / (ltv_prec_in_balance * price_prec_in_balance);
+            let can_loan_amount = balance_mul_div(
+                collateral_amount,
+                checked_mul(btc_price, ltv).map_err(overflow)?,
+                prec,
+            )
+            .map_err(overflow)?;
             return Ok(CollateralLoan {
                 collateral_amount,
                 loan_amount: can_loan_amount,
             });
         }
 
-        if (loan_amount * ltv_prec_in_balance) * price_prec_in_balance
-            / collateral_amount
-            / btc_price_in_balance
-            > ltv_in_balance
-        {
-            Err("over LTV limit")
+        let loan_ltv = checked_div(
+            mul_div(
+                to_u128(loan_amount).map_err(overflow)?,
+                prec,
+                to_u128(collateral_amount).map_err(overflow)?,
+            )
+            .map_err(overflow)?,
+            btc_price,
+        )
+        .map_err(overflow)?;
+        if loan_ltv > ltv {
+            Err(Error::<T>::OverLTVLimit.into())
         } else {
             Ok(CollateralLoan {
                 collateral_amount,
@@ -998,98 +2248,391 @@ impl<T: Trait> Module<T> {
         }
     }
 
-    fn get_next_loan_package_id() -> LoanPackageId {
-        NextLoanPackageId::mutate(|v| {
-            let org = *v;
-            *v += 1;
-            org
-        })
+    /// Immutable
+    /// TotalLoan / LoanCap in terms of LTV_PREC, zero if there is no cap
+    pub fn utilization() -> LTV {
+        match Self::loan_cap() {
+            Some(cap) if !cap.is_zero() => {
+                to_u128(Self::total_loan())
+                    .and_then(|debt| mul_div(debt, u128::from(LTV_PREC), to_u128(cap)?))
+                    .and_then(from_u128)
+                    .unwrap_or_else(|_| LTV::from(LTV_PREC))
+                    .min(LTV::from(LTV_PREC))
+            }
+            _ => 0,
+        }
     }
 
-    fn get_next_loan_id() -> LoanId {
-        NextLoanId::mutate(|v| {
-            let org = *v;
-            *v += 1;
-            org
-        })
+    /// grow the borrow index by the interest model and the fee index by the stability fee
+    /// from the last accrual till now
+    pub fn accrue_interest() {
+        Self::accrue_fee();
+        let now = <system::Module<T>>::block_number();
+        let last = Self::last_accrual();
+        if now <= last {
+            return;
+        }
+        <LastAccrual<T>>::put(now);
+
+        let model = match Self::interest_model() {
+            Some(model) => model,
+            None => return,
+        };
+        let blocks = TryInto::<u128>::try_into(now - last)
+            .ok()
+            .unwrap_or(u128::max_value());
+        let rate = u128::from(model.yearly_rate(Self::utilization()))
+            + u128::from(Self::stability_fee());
+        let index = Self::borrow_index();
+        let growth = mul_div(
+            index,
+            rate.saturating_mul(blocks),
+            u128::from(INTEREST_RATE_PREC) * u128::from(model.blocks_per_year),
+        )
+        .unwrap_or(u128::max_value());
+        BorrowIndex::put(index.saturating_add(growth));
     }
 
-    /// Don't expect too much from Blockchain, we can't break any physical rules.
-    /// Don't be cocky to assume any optimization.
-    /// All the status automation are executed here.
-    /// I don't know if the iterator of "StorageLinkedMap" is consistent.
-    /// So a simple loop is the practical solution.
-    /// A due loan has a 2-day-long buffer to get either repaid or extended.
-    fn on_each_block(_height: T::BlockNumber) {
+    /// the fee index grows by time, as the fixed rate packages count their terms by time,
+    /// so does the credit fee index while there is no interest model
+    fn accrue_fee() {
         let now = <timestamp::Module<T>>::get();
-        let btc_price = Self::current_btc_price();
-        let liquidation_thd = Self::global_liquidation_threshold();
-        let warning_thd = Self::global_warning_threshold();
-        let mut packages = btree_map::BTreeMap::new();
-        let mut total_penalty = T::Balance::zero();
-        let mut total_interest = T::Balance::zero();
-        let collateral_asset_id = Self::collateral_asset_id();
-        let pawnshop = Self::pawn_shop();
-        let profit_pool = Self::profit_pool();
+        let last = Self::last_fee_accrual();
+        if now <= last {
+            return;
+        }
+        <LastFeeAccrual<T>>::put(now);
+        // nothing to accrue before the first accrual
+        if last.is_zero() {
+            return;
+        }
 
-        for (loan_id, loan) in <Loans<T>>::enumerate() {
-            if Self::check_loan_in_liquidation(&loan_id) {
-                continue;
+        let elapsed = to_u128(now - last).unwrap_or(u128::max_value());
+        let grow = |index: u128| {
+            let growth = mul_div(
+                index,
+                u128::from(Self::stability_fee()).saturating_mul(elapsed),
+                u128::from(INTEREST_RATE_PREC) * u128::from(YEAR_IN_MS),
+            )
+            .unwrap_or(u128::max_value());
+            index.saturating_add(growth)
+        };
+        FeeIndex::put(grow(Self::fee_index()));
+        if Self::interest_model().is_none() {
+            CreditFeeIndex::put(grow(Self::credit_fee_index()));
+        }
+    }
+
+    /// Immutable
+    /// interest accrued by the interest model, or the stability fee of fixed rate loans,
+    /// since the last settlement
+    pub fn accrued_interest(loan: &Loan<T::AccountId, T::Balance, T::Moment>) -> T::Balance {
+        match (Self::loan_borrow_index(loan.id), Self::loan_fee_index(loan.id)) {
+            (Some(settled_index), _) => Self::index_interest(loan.loan_balance_total, settled_index),
+            (None, Some(settled_index)) => {
+                Self::index_growth(loan.loan_balance_total, settled_index, Self::fee_index())
             }
+            _ => T::Balance::zero(),
+        }
+    }
 
-            match Self::check_loan_health(&loan, now, btc_price, liquidation_thd, warning_thd) {
-                LoanHealth::Well => {}
-                LoanHealth::Warning(ltv) => {
-                    if loan.status != LoanHealth::Warning(ltv) {
-                        <Loans<T>>::mutate(&loan.id, |v| v.status = LoanHealth::Warning(ltv));
-                        Self::deposit_event(RawEvent::Warning(loan_id, ltv));
-                    }
-                }
-                LoanHealth::Extended => {
-                    if loan.status != LoanHealth::Extended {
-                        <Loans<T>>::mutate(&loan.id, |v| v.status = LoanHealth::Extended);
-                        Self::deposit_event(RawEvent::Extended(loan_id, loan.who));
+    /// Immutable
+    /// interest on `balance` since the borrow index was `settled_index`
+    fn index_interest(balance: T::Balance, settled_index: u128) -> T::Balance {
+        Self::index_growth(balance, settled_index, Self::borrow_index())
+    }
+
+    /// Immutable
+    /// interest of a credit line since its last settlement, by the interest model
+    /// and by the stability fee while there was no model
+    fn line_interest(line: &CreditLine<T::AccountId, T::Balance, T::AssetId>) -> T::Balance {
+        Self::index_interest(line.drawn, Self::credit_line_borrow_index(line.id)).saturating_add(
+            Self::index_growth(line.drawn, Self::credit_line_fee_index(line.id), Self::credit_fee_index()),
+        )
+    }
+
+    /// Immutable
+    /// growth of `balance` while an index went from `settled_index` to `index`
+    fn index_growth(balance: T::Balance, settled_index: u128, index: u128) -> T::Balance {
+        if settled_index.is_zero() {
+            return T::Balance::zero();
+        }
+        balance_mul_div(balance, index.saturating_sub(settled_index), settled_index)
+            .unwrap_or_else(|_| T::Balance::max_value())
+    }
+
+    /// Immutable
+    /// what the borrower owes right now, including the interest not settled yet
+    pub fn loan_debt(loan: &Loan<T::AccountId, T::Balance, T::Moment>) -> T::Balance {
+        loan.loan_balance_total + Self::accrued_interest(loan)
+    }
+
+    /// Immutable
+    pub fn tier_of(who: &T::AccountId) -> Option<BorrowerTier<T::Balance>> {
+        Self::borrower_tier(T::Kyc::kyc_status(who))
+    }
+
+    /// Immutable
+    /// debt of all the running loans and credit lines of an account, including the interest not settled yet
+    pub fn outstanding_debt(who: &T::AccountId) -> T::Balance {
+        let loans = Self::loans_by_account(who)
+            .iter()
+            .filter(|loan_id| <Loans<T>>::exists(loan_id))
+            .fold(T::Balance::zero(), |acc, loan_id| {
+                acc.saturating_add(Self::loan_debt(&Self::get_loan_by_id(loan_id)))
+            });
+        Self::credit_lines_by_account(who)
+            .iter()
+            .filter_map(|line_id| Self::credit_line(line_id))
+            .fold(loans, |acc, line| {
+                acc.saturating_add(line.drawn).saturating_add(Self::line_interest(&line))
+            })
+    }
+
+    /// Immutable
+    /// how much more an account can borrow before hitting its cap, the account's own cap goes before its tier's
+    pub fn borrowing_room(
+        who: &T::AccountId,
+        tier: &Option<BorrowerTier<T::Balance>>,
+    ) -> Option<T::Balance> {
+        Self::account_loan_cap(who)
+            .or_else(|| tier.as_ref().and_then(|tier| tier.cap))
+            .map(|cap| cap.saturating_sub(Self::outstanding_debt(who)))
+    }
+
+    /// Immutable
+    /// `available_credit` with the LTV limit lowered to the max LTV of the tier
+    pub fn tier_available_credit(
+        tier: &Option<BorrowerTier<T::Balance>>,
+        debt: T::Balance,
+        risk: &BasketRisk<T::Balance>,
+    ) -> MathResult<T::Balance> {
+        match tier {
+            Some(tier) if tier.max_ltv < risk.ltv_limit => {
+                let risk = BasketRisk {
+                    ltv_limit: tier.max_ltv,
+                    ..risk.clone()
+                };
+                Self::available_credit(debt, &risk)
+            }
+            _ => Self::available_credit(debt, risk),
+        }
+    }
+
+    /// the accrued interest, or the stability fee of fixed rate loans, is minted into the profit pool
+    /// and added to the loan balance, just like the interest charged upfront
+    pub fn settle_interest(loan_id: LoanId) -> DispatchResult {
+        let under_model = LoanBorrowIndex::exists(loan_id);
+        if !under_model && !LoanFeeIndex::exists(loan_id) {
+            return Ok(());
+        }
+        Self::accrue_interest();
+        let loan = Self::get_loan_by_id(loan_id);
+        let interest = Self::accrued_interest(&loan);
+        if !interest.is_zero() {
+            let package = Self::loan_package(loan.package_id);
+            <rio_assets::Module<T>>::mint(
+                system::RawOrigin::Root.into(),
+                package.loan_asset_id,
+                Self::profit_pool(),
+                interest,
+            )?;
+            <Loans<T>>::mutate(loan_id, |v| v.loan_balance_total += interest);
+            Self::increase_debt(&package.collateral_asset_id, interest);
+            <TotalProfit<T>>::mutate(|v| *v += interest);
+            Self::feed_stability_fund(package.loan_asset_id, interest);
+            Self::deposit_event(RawEvent::InterestSettled(loan_id, interest));
+        }
+        if under_model {
+            LoanBorrowIndex::insert(loan_id, Self::borrow_index());
+        } else {
+            LoanFeeIndex::insert(loan_id, Self::fee_index());
+        }
+        Ok(())
+    }
+
+    /// bookings which didn't exist when the running loans were created, each step runs once
+    fn migrate() {
+        if Self::storage_version() < 1 {
+            // the debt by collateral is booked from scratch by the loans and credit lines running now
+            let assets = <CollateralDebt<T>>::enumerate()
+                .map(|(asset_id, _)| asset_id)
+                .collect::<Vec<_>>();
+            for asset_id in assets {
+                <CollateralDebt<T>>::remove(asset_id);
+            }
+            Self::accrue_interest();
+            for (loan_id, loan) in <Loans<T>>::enumerate() {
+                let package = Self::loan_package(loan.package_id);
+                <CollateralDebt<T>>::mutate(&package.collateral_asset_id, |v| {
+                    *v = v.saturating_add(loan.loan_balance_total)
+                });
+                // fixed rate loans pay the stability fee from now on
+                if !LoanBorrowIndex::exists(loan_id) && !LoanFeeIndex::exists(loan_id) {
+                    LoanFeeIndex::insert(loan_id, Self::fee_index());
+                }
+            }
+            for line_id in 0..Self::next_credit_line_id() {
+                if let Some(line) = Self::credit_line(line_id) {
+                    <CollateralDebt<T>>::mutate(&line.asset_id, |v| *v = v.saturating_add(line.drawn));
+                }
+            }
+            StorageVersion::put(1);
+        }
+        if Self::storage_version() < 2 {
+            // loans made before the indexes were never checked by the price or the due hour
+            let loan_ids = <Loans<T>>::enumerate()
+                .map(|(loan_id, _)| loan_id)
+                .collect::<Vec<_>>();
+            for loan_id in loan_ids {
+                Self::index_loan(loan_id);
+            }
+            StorageVersion::put(2);
+        }
+    }
+
+    fn get_next_loan_package_id() -> LoanPackageId {
+        NextLoanPackageId::mutate(|v| {
+            let org = *v;
+            *v += 1;
+            org
+        })
+    }
+
+    fn get_next_loan_id() -> LoanId {
+        NextLoanId::mutate(|v| {
+            let org = *v;
+            *v += 1;
+            org
+        })
+    }
+
+    /// All the status automation are executed here.
+    /// Only the loans in the check queue are checked, as many as the "CheckWeightLimit" allows,
+    /// loans get into the queue when the hour they get due has passed or the price falls over their next threshold.
+    /// Loans accruing interest get due for a check every "RECHECK_HOURS" at least.
+    /// A due loan has a 2-day-long buffer to get either repaid or extended.
+    fn on_each_block(_height: T::BlockNumber) {
+        let now = <timestamp::Module<T>>::get();
+        let mut packages = btree_map::BTreeMap::new();
+        let mut total_penalty = T::Balance::zero();
+        let mut total_interest = T::Balance::zero();
+        // penalty and interest are cut from the collateral of the package's asset
+        let mut fees = btree_map::BTreeMap::<T::AssetId, T::Balance>::new();
+        let pawnshop = Self::pawn_shop();
+        let profit_pool = Self::profit_pool();
+
+        Self::enqueue_due_loans(now);
+        let limit = Self::check_weight_limit();
+        let mut used: Weight = 0;
+        let mut queue = CheckQueue::take().into_iter();
+
+        while let Some(loan_id) = queue.next() {
+            // the check has to fit in the limit even if it writes, the rest are carried over
+            if used.saturating_add(LOAN_CHECK_WEIGHT + LOAN_UPDATE_WEIGHT) > limit {
+                let carried = rstd::iter::once(loan_id).chain(queue).collect::<Vec<_>>();
+                CheckQueue::mutate(|v| *v = carried.into_iter().chain(v.drain(..)).collect());
+                break;
+            }
+            used = used.saturating_add(LOAN_CHECK_WEIGHT);
+            if !<Loans<T>>::exists(loan_id) || Self::check_loan_in_liquidation(&loan_id) {
+                continue;
+            }
+            let mut loan = Self::get_loan_by_id(loan_id);
+            let mut health = Self::check_loan_health(&loan, now);
+            if let LoanHealth::Warning(_) | LoanHealth::Liquidating(_) = health {
+                if Self::top_up_loan(&loan) {
+                    used = used.saturating_add(LOAN_UPDATE_WEIGHT);
+                    loan = Self::get_loan_by_id(loan_id);
+                    health = Self::check_loan_health(&loan, now);
+                }
+            }
+            if health != loan.status {
+                used = used.saturating_add(LOAN_UPDATE_WEIGHT);
+            }
+
+            match health {
+                LoanHealth::Well => {
+                    if loan.status != LoanHealth::Well {
+                        <Loans<T>>::mutate(&loan.id, |v| v.status = LoanHealth::Well);
+                    }
+                }
+                LoanHealth::Warning(ltv) => {
+                    if loan.status != LoanHealth::Warning(ltv) {
+                        <Loans<T>>::mutate(&loan.id, |v| v.status = LoanHealth::Warning(ltv));
+                        Self::deposit_event(RawEvent::Warning(loan_id, ltv));
+                    }
+                }
+                LoanHealth::Extended => {
+                    if loan.status != LoanHealth::Extended {
+                        <Loans<T>>::mutate(&loan.id, |v| v.status = LoanHealth::Extended);
+                        Self::deposit_event(RawEvent::Extended(loan_id, loan.who));
                     }
                 }
                 LoanHealth::Liquidating(l) => {
-                    Self::liquidate_loan(loan_id, l);
-                    Self::deposit_event(RawEvent::Liquidating(
-                        loan_id,
-                        loan.who.clone(),
-                        loan.collateral_balance_available,
-                        loan.loan_balance_total,
-                    ));
+                    if loan.status != LoanHealth::Liquidating(l) {
+                        Self::start_liquidation(loan_id, l);
+                        Self::deposit_event(RawEvent::Liquidating(
+                            loan_id,
+                            loan.who.clone(),
+                            loan.collateral_balance_available,
+                            loan.loan_balance_total,
+                        ));
+                    }
                 }
                 LoanHealth::Expired => {
                     if !packages.contains_key(&loan.package_id) {
                         packages.insert(loan.package_id, <LoanPackages<T>>::get(loan.package_id));
                     }
                     let package = packages.get(&loan.package_id).unwrap();
-                    let penalty = loan.expiration_penalty(Self::penalty_rate(), btc_price);
-                    let interest = loan.expiration_interest(package, btc_price);
-
-                    total_penalty += penalty;
-                    total_interest += interest;
+                    let collateral_price = Self::price_of(
+                        &package.collateral_asset_id,
+                        &Self::risk_params(&package.collateral_asset_id),
+                    );
+                    let penalty = loan.expiration_penalty(Self::penalty_rate(), collateral_price);
+                    // loans under the interest model keep accruing, no extra interest for the extension
+                    let interest = if LoanBorrowIndex::exists(loan_id) {
+                        Ok(T::Balance::zero())
+                    } else {
+                        loan.expiration_interest(package, collateral_price)
+                    };
+                    let (penalty, interest) = match (penalty, interest) {
+                        (Ok(penalty), Ok(interest)) => (penalty, interest),
+                        // left as it is, overdue loans are checked again in the next hour
+                        _ => {
+                            Self::index_loan(loan_id);
+                            continue;
+                        }
+                    };
 
                     let who = loan.who.clone();
                     let mut new_loan = loan.clone();
                     new_loan.expire_then_update(package, now, penalty, interest);
-                    let new_ltv = <Loan<T::AccountId, T::Balance, T::Moment>>::get_ltv(
-                        new_loan.collateral_balance_available,
-                        new_loan.loan_balance_total,
-                        btc_price,
-                    );
+                    let new_risk = match Self::basket_risk(&Self::collaterals_of(&new_loan)) {
+                        Ok(risk) => risk,
+                        _ => {
+                            Self::index_loan(loan_id);
+                            continue;
+                        }
+                    };
+
+                    total_penalty = total_penalty.saturating_add(penalty);
+                    total_interest = total_interest.saturating_add(interest);
+                    let fee = fees.entry(package.collateral_asset_id).or_default();
+                    *fee = fee.saturating_add(penalty.saturating_add(interest));
 
-                    if new_ltv >= liquidation_thd {
+                    let new_ltv = Self::basket_ltv(Self::loan_debt(&new_loan), &new_risk);
+
+                    if new_ltv >= new_risk.liquidation_threshold {
                         <Loans<T>>::insert(loan_id, new_loan);
-                        Self::liquidate_loan(loan_id, new_ltv);
+                        Self::start_liquidation(loan_id, new_ltv);
                         Self::deposit_event(RawEvent::Liquidating(
                             loan_id,
                             loan.who.clone(),
                             loan.collateral_balance_available,
                             loan.loan_balance_total,
                         ));
-                    } else if new_ltv >= warning_thd {
+                    } else if new_ltv >= new_risk.warning_threshold {
                         new_loan.status = LoanHealth::Warning(new_ltv);
                         <Loans<T>>::insert(loan_id, new_loan);
                         Self::deposit_event(RawEvent::Warning(loan_id, new_ltv));
@@ -1104,23 +2647,27 @@ impl<T: Trait> Module<T> {
                     }
                 }
             }
+            Self::index_loan(loan_id);
         }
 
-        if !(total_penalty + total_interest).is_zero() {
+        for (collateral_asset_id, fee) in fees {
+            if fee.is_zero() {
+                continue;
+            }
             <rio_assets::Module<T>>::make_transfer_with_event(
                 &collateral_asset_id,
                 &pawnshop,
                 &profit_pool,
-                total_penalty + total_interest,
+                fee,
             )
             .and_then(|_| {
                 <TotalCollateral<T>>::mutate(|v| {
-                    match v.checked_sub(&(total_interest + total_penalty)) {
+                    match v.checked_sub(&fee) {
                         Some(total) => {
                             *v = total;
                             Ok(())
                         }
-                        None => Err("total collateral underflows"),
+                        None => Err(Error::<T>::TotalCollateralUnderflow.into()),
                     }
                 })
             })
@@ -1128,41 +2675,1140 @@ impl<T: Trait> Module<T> {
         }
     }
 
+    /// loans over the liquidation threshold are flagged for the keepers, the close factor brings them back step by step.
+    /// Only the loans under water, which keepers can't bring back, go to the auction for the whole basket
+    fn start_liquidation(loan_id: LoanId, liquidating_ltv: LTV) {
+        if liquidating_ltv < LTV_PREC {
+            <Loans<T>>::mutate(loan_id, |v| {
+                v.status = LoanHealth::Liquidating(liquidating_ltv)
+            });
+        } else {
+            Self::liquidate_loan(loan_id, liquidating_ltv);
+        }
+    }
+
     fn liquidate_loan(loan_id: LoanId, liquidating_ltv: LTV) {
         <Loans<T>>::mutate(loan_id, |v| {
             v.status = LoanHealth::Liquidating(liquidating_ltv)
         });
+        Self::unindex_loan(loan_id);
         if LiquidatingLoans::exists() {
             LiquidatingLoans::mutate(|v| v.push(loan_id));
         } else {
             let ll: Vec<LoanId> = vec![loan_id];
             LiquidatingLoans::put(ll);
         }
+
+        if !Self::auction_duration().is_zero() {
+            let start = <system::Module<T>>::block_number();
+            <Auctions<T>>::insert(loan_id, CollateralAuction { loan_id, start });
+            Self::deposit_event(RawEvent::AuctionStarted(loan_id));
+        }
+    }
+
+    /// remove a loan from the system, the bookings are left to the caller
+    fn close_loan(loan: &Loan<T::AccountId, T::Balance, T::Moment>) {
+        let loan_id = loan.id;
+        <Loans<T>>::remove(loan_id);
+        <LoansByAccount<T>>::mutate(&loan.who, |v| v.retain(|id| *id != loan_id));
+        LiquidatingLoans::mutate(|v| v.retain(|id| *id != loan_id));
+        LoanBorrowIndex::remove(loan_id);
+        LoanFeeIndex::remove(loan_id);
+        <TopUpPolicies<T>>::remove(loan_id);
+        Self::put_basket(loan_id, vec![]);
+        <Auctions<T>>::remove(loan_id);
+        Self::unindex_loan(loan_id);
+    }
+
+    /// a share of the interest in the profit pool goes to the stability fund.
+    /// It never fails, so it can go after the bookings of the interest,
+    /// the share stays in the profit pool if it can't be moved
+    fn feed_stability_fund(asset_id: T::AssetId, interest: T::Balance) {
+        let fund = match Self::stability_fund() {
+            Some(fund) => fund,
+            None => return,
+        };
+        // the rate is at most LTV_PREC, the share never exceeds the interest
+        let mut share = balance_mul_div(
+            interest,
+            u128::from(Self::stability_fund_rate()),
+            u128::from(LTV_PREC),
+        )
+        .unwrap_or(interest)
+        .min(interest);
+        // the surplus over the cap stays in the profit pool
+        if let Some(cap) = Self::surplus_buffer_cap() {
+            let held = <rio_assets::Module<T>>::free_balance(&asset_id, &fund);
+            share = share.min(cap.saturating_sub(held));
+        }
+        let profit_pool = Self::profit_pool();
+        share = share.min(<rio_assets::Module<T>>::free_balance(&asset_id, &profit_pool));
+        if share.is_zero() {
+            return;
+        }
+        if <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &profit_pool, &fund, share)
+            .is_ok()
+        {
+            // only burns what the fund holds
+            let _ = Self::cover_bad_debt(asset_id);
+        }
+    }
+
+    fn incur_bad_debt(loan_id: LoanId, asset_id: T::AssetId, shortfall: T::Balance) -> DispatchResult {
+        <BadDebt<T>>::mutate(|v| *v += shortfall);
+        Self::deposit_event(RawEvent::BadDebtIncurred(loan_id, shortfall));
+        Self::cover_bad_debt(asset_id)
+    }
+
+    /// the loan asset minted for the bad debt never comes back, the stability fund burns the same amount instead
+    fn cover_bad_debt(asset_id: T::AssetId) -> DispatchResult {
+        let bad_debt = Self::bad_debt();
+        let fund = match Self::stability_fund() {
+            Some(fund) if !bad_debt.is_zero() => fund,
+            _ => return Ok(()),
+        };
+        let covered = bad_debt.min(<rio_assets::Module<T>>::free_balance(&asset_id, &fund));
+        if covered.is_zero() {
+            return Ok(());
+        }
+        <rio_assets::Module<T>>::burn(system::RawOrigin::Root.into(), asset_id, fund, covered)?;
+        <BadDebt<T>>::put(bad_debt - covered);
+        Self::deposit_event(RawEvent::BadDebtCovered(covered, bad_debt - covered));
+        Ok(())
+    }
+
+    /// Immutable
+    pub fn solvency() -> Solvency<T::Balance> {
+        let total_loan = Self::total_loan();
+        let total_collateral = Self::total_collateral();
+        let collateral_value = Self::collateral_value();
+        let bad_debt = Self::bad_debt();
+        let stability_fund = Self::stability_fund()
+            .map(|fund| <rio_assets::Module<T>>::free_balance(&Self::loan_asset_id(), &fund))
+            .unwrap_or_default();
+        Solvency {
+            total_loan,
+            total_collateral,
+            collateral_value,
+            bad_debt,
+            stability_fund,
+            solvent: bad_debt.is_zero() && collateral_value >= total_loan,
+        }
+    }
+
+    /// Immutable
+    /// value of the baskets of all the loans at the prices of their assets,
+    /// and of the pledge of all the credit lines, goes through all the loans
+    pub fn collateral_value() -> T::Balance {
+        let mut collateral_value = T::Balance::zero();
+        for (_, loan) in <Loans<T>>::enumerate() {
+            let value = Self::basket_risk(&Self::collaterals_of(&loan))
+                .map(|risk| risk.value)
+                .unwrap_or_else(|_| T::Balance::max_value());
+            collateral_value = collateral_value.saturating_add(value);
+        }
+        collateral_value.saturating_add(Self::pledged_value())
+    }
+
+    /// Immutable
+    /// value of the pledge of all the credit lines at the current prices
+    pub fn pledged_value() -> T::Balance {
+        <TotalPledged<T>>::enumerate().fold(T::Balance::zero(), |value, (asset_id, pledged)| {
+            let pledged_value =
+                balance_mul_div(pledged, Self::pledge_price(&asset_id), u128::from(PRICE_PREC))
+                    .unwrap_or_else(|_| T::Balance::max_value());
+            value.saturating_add(pledged_value)
+        })
+    }
+
+    /// Immutable
+    /// the price of its feed, or the BTC price for an asset without a feed
+    fn pledge_price(asset_id: &T::AssetId) -> Price {
+        match Self::credit_asset(asset_id).and_then(|params| params.price_feed) {
+            Some(_) => Self::collateral_price(asset_id),
+            None => Self::current_btc_price(),
+        }
+    }
+
+    fn increase_debt(asset_id: &T::AssetId, amount: T::Balance) {
+        <TotalLoan<T>>::mutate(|v| *v += amount);
+        <CollateralDebt<T>>::mutate(asset_id, |v| *v += amount);
+    }
+
+    /// debt from before the per collateral bookings is only taken off `TotalLoan`,
+    /// while `TotalLoan` itself covers every debt, borrowing stops if it doesn't
+    fn decrease_debt(asset_id: &T::AssetId, amount: T::Balance) {
+        if amount > Self::total_loan() {
+            Self::trip(LoanFunction::Borrow, TripReason::InvariantBroken);
+        }
+        <TotalLoan<T>>::mutate(|v| *v = v.saturating_sub(amount));
+        <CollateralDebt<T>>::mutate(asset_id, |v| *v = v.saturating_sub(amount));
+    }
+
+    /// trip the breaker of `function`, it resumes by itself if BreakerTimeout is set
+    fn trip(function: LoanFunction, reason: TripReason) {
+        let breaker = LoanBreakers::<T>::trip(
+            function,
+            reason,
+            <system::Module<T>>::block_number(),
+            Self::breaker_timeout(),
+        );
+        Self::deposit_event(RawEvent::BreakerTripped(function, reason, breaker.resume_at));
+    }
+
+    /// Immutable
+    pub fn is_tripped(function: LoanFunction) -> bool {
+        LoanBreakers::<T>::is_tripped(function, <system::Module<T>>::block_number())
+    }
+
+    fn ensure_not_tripped(function: LoanFunction) -> DispatchResult {
+        ensure!(!Self::is_tripped(function), Error::<T>::FunctionTripped);
+        Ok(())
+    }
+
+    /// Immutable
+    /// backs `LoanApi::breakers`
+    pub fn breakers() -> Vec<(LoanFunction, Breaker<T::BlockNumber>)> {
+        let now = <system::Module<T>>::block_number();
+        LoanFunction::all()
+            .into_iter()
+            .filter_map(|f| LoanBreakers::<T>::get(f, now).map(|b| (f, b)))
+            .collect()
+    }
+
+    /// Immutable
+    fn check_debt_ceiling(asset_id: &T::AssetId, amount: T::Balance) -> DispatchResult {
+        if let Some(ceiling) = Self::debt_ceiling(asset_id) {
+            let debt = Self::collateral_debt(asset_id)
+                .checked_add(&amount)
+                .ok_or(Error::<T>::Overflow)?;
+            ensure!(debt <= ceiling, Error::<T>::OverDebtCeiling);
+        }
+        Ok(())
+    }
+
+    /// Immutable
+    /// backs `LoanApi::system_debt`, goes through all the loans
+    pub fn system_debt() -> SystemDebt<T::Balance, T::AssetId> {
+        let total_debt = Self::total_loan();
+        let collateral_value = Self::collateral_value();
+        // None if there is no debt at all
+        let collateralization_ratio = to_u128(collateral_value)
+            .and_then(|value| mul_div(value, u128::from(LTV_PREC), to_u128(total_debt)?))
+            .ok()
+            .and_then(|ratio| TryInto::<LTV>::try_into(ratio).ok());
+        let mut debt_by_collateral: Vec<_> = <CollateralDebt<T>>::enumerate()
+            .map(|(asset_id, debt)| (asset_id, debt, Self::debt_ceiling(asset_id)))
+            .collect();
+        for (asset_id, ceiling) in <DebtCeilings<T>>::enumerate() {
+            if !<CollateralDebt<T>>::exists(asset_id) {
+                debt_by_collateral.push((asset_id, T::Balance::zero(), ceiling));
+            }
+        }
+        SystemDebt {
+            total_debt,
+            collateral_value,
+            collateralization_ratio,
+            surplus: Self::stability_fund()
+                .map(|fund| <rio_assets::Module<T>>::free_balance(&Self::loan_asset_id(), &fund))
+                .unwrap_or_default(),
+            deficit: Self::bad_debt(),
+            debt_by_collateral,
+        }
+    }
+
+    /// Immutable
+    /// backs `LoanApi::loan_positions`
+    pub fn loan_positions(who: T::AccountId) -> Vec<LoanPosition<T::Balance, T::AssetId, T::Moment>> {
+        let now = <timestamp::Module<T>>::get();
+        let tier = Self::tier_of(&who);
+        let room = Self::borrowing_room(&who, &tier);
+        Self::loans_by_account(&who)
+            .into_iter()
+            .filter(|loan_id| <Loans<T>>::exists(loan_id))
+            .filter_map(|loan_id| {
+                let loan = Self::get_loan_by_id(loan_id);
+                let collaterals = Self::collaterals_of(&loan);
+                let risk = Self::basket_risk(&collaterals).ok()?;
+                let debt = Self::loan_debt(&loan);
+                let liquidation_price =
+                    Self::threshold_price(debt, &collaterals, &risk, risk.liquidation_threshold)
+                        .map(|(_, price)| price);
+                let drawable = match &tier {
+                    Some(tier) if !tier.allows(loan.package_id) => T::Balance::zero(),
+                    _ => {
+                        let credit = Self::tier_available_credit(&tier, debt, &risk).ok()?;
+                        room.map_or(credit, |room| credit.min(room))
+                    }
+                };
+                Some(LoanPosition {
+                    loan_id,
+                    package_id: loan.package_id,
+                    debt,
+                    ltv: Self::basket_ltv(debt, &risk),
+                    health: Self::check_loan_health(&loan, now),
+                    liquidation_price,
+                    time_to_due: loan.due.saturating_sub(now),
+                    time_to_due_extend: loan.due_extend.saturating_sub(now),
+                    drawable,
+                    collaterals,
+                })
+            })
+            .collect()
+    }
+
+    /// Immutable
+    /// backs `LoanApi::quote_apply`, goes through the same checks as `apply_for_loan`
+    pub fn quote_apply(
+        who: T::AccountId,
+        collateral_amount: T::Balance,
+        package_id: LoanPackageId,
+    ) -> Option<LoanQuote<T::Balance>> {
+        let (
+            CollateralLoan {
+                collateral_amount,
+                loan_amount,
+            },
+            interest,
+        ) = Self::check_application(&who, package_id, collateral_amount, T::Balance::zero())
+            .ok()?;
+        let package = <ActiveLoanPackages<T>>::get(package_id);
+
+        let collaterals = vec![(package.collateral_asset_id, collateral_amount)];
+        let risk = Self::basket_risk(&collaterals).ok()?;
+        let liquidation_price =
+            Self::threshold_price(loan_amount, &collaterals, &risk, risk.liquidation_threshold)
+                .map(|(_, price)| price);
+        Some(LoanQuote {
+            collateral_amount,
+            loan_amount,
+            interest,
+            received: loan_amount - interest,
+            liquidation_price,
+        })
+    }
+
+    fn moment_hour(moment: T::Moment) -> u64 {
+        TryInto::<u64>::try_into(moment)
+            .ok()
+            .unwrap_or_else(u64::max_value)
+            / DUE_INDEX_UNIT
+    }
+
+    /// the first hour which hasn't been checked
+    fn due_cursor(now: T::Moment) -> u64 {
+        match Self::next_due_hour() {
+            0 => Self::moment_hour(now),
+            hour => hour,
+        }
+    }
+
+    /// loans in the hours which have passed go into the check queue
+    fn enqueue_due_loans(now: T::Moment) {
+        let now_hour = Self::moment_hour(now);
+        let mut hour = Self::due_cursor(now);
+        let mut loans = vec![];
+        while hour < now_hour {
+            loans.extend(DueIndex::take(hour));
+            hour += 1;
+        }
+        NextDueHour::put(hour);
+        if !loans.is_empty() {
+            CheckQueue::mutate(|v| v.extend(loans));
+        }
+    }
+
+    /// loans whose next threshold is crossed by the fall of the price go into the check queue,
+    /// as well as the loans holding assets of this price source in their baskets
+    fn on_price_drop(feed: Option<FeedId>, old: Price, new: Price) {
+        if new >= old {
+            return;
+        }
+        let (from, to) = (new / PRICE_INDEX_UNIT, old / PRICE_INDEX_UNIT);
+        let mut loans = vec![];
+        let mut buckets = Self::price_buckets(feed);
+        buckets.retain(|bucket| {
+            if *bucket < from || *bucket > to {
+                return true;
+            }
+            loans.extend(PriceIndex::take((feed, *bucket)));
+            false
+        });
+        if buckets.is_empty() {
+            PriceBuckets::remove(feed);
+        } else {
+            PriceBuckets::insert(feed, buckets);
+        }
+        for (asset_id, params) in <CollateralParamsOf<T>>::enumerate() {
+            if params.price_feed == feed {
+                loans.extend(Self::basket_loans(asset_id));
+            }
+        }
+        if !loans.is_empty() {
+            CheckQueue::mutate(|v| v.extend(loans));
+        }
+    }
+
+    /// the price of the package's collateral asset at which the loan hits its next threshold,
+    /// the thresholds are weighted at the current prices.
+    /// Debt accruing under the interest model moves it up slowly, it is updated whenever the loan is touched or checked.
+    pub fn trigger_price(
+        loan: &Loan<T::AccountId, T::Balance, T::Moment>,
+    ) -> Option<(Option<FeedId>, Price)> {
+        let collaterals = Self::collaterals_of(loan);
+        let risk = Self::basket_risk(&collaterals).ok()?;
+        let threshold = match loan.status {
+            // flagged for keepers, checked again once it goes under water
+            LoanHealth::Liquidating(_) => LTV_PREC,
+            LoanHealth::Warning(_) => risk.liquidation_threshold,
+            _ => risk.warning_threshold,
+        };
+        // the interest till the next check can't move the threshold past the indexed price
+        let debt = Self::loan_debt(loan);
+        let interest = balance_mul_div(
+            debt,
+            u128::from(Self::yearly_rate_of(loan.id))
+                .saturating_mul(u128::from((RECHECK_HOURS + 1) * DUE_INDEX_UNIT)),
+            u128::from(INTEREST_RATE_PREC) * u128::from(YEAR_IN_MS),
+        )
+        .unwrap_or_else(|_| T::Balance::max_value());
+        Self::threshold_price(debt.saturating_add(interest), &collaterals, &risk, threshold)
+    }
+
+    /// Immutable
+    /// the yearly rate a loan accrues at right now in terms of INTEREST_RATE_PREC,
+    /// zero for the ones charged upfront only
+    pub fn yearly_rate_of(loan_id: LoanId) -> u64 {
+        let fee = u64::from(Self::stability_fee());
+        if LoanBorrowIndex::exists(loan_id) {
+            Self::interest_model()
+                .map(|model| model.yearly_rate(Self::utilization()))
+                .unwrap_or_default()
+                .saturating_add(fee)
+        } else if LoanFeeIndex::exists(loan_id) {
+            fee
+        } else {
+            0
+        }
+    }
+
+    /// Immutable
+    /// the price of the first collateral asset at which the debt reaches the threshold of the basket
+    pub fn threshold_price(
+        debt: T::Balance,
+        collaterals: &[(T::AssetId, T::Balance)],
+        risk: &BasketRisk<T::Balance>,
+        threshold: LTV,
+    ) -> Option<(Option<FeedId>, Price)> {
+        let (asset_id, amount) = &collaterals[0];
+        if amount.is_zero() || threshold.is_zero() {
+            return None;
+        }
+        let params = Self::risk_params(asset_id);
+        let price = Self::price_of(asset_id, &params);
+        let amount = to_u128(*amount).ok()?;
+
+        // the collateral value at the threshold, the part in other assets stays
+        let value =
+            mul_div(to_u128(debt).ok()?, u128::from(LTV_PREC), u128::from(threshold)).ok()?;
+        let others = to_u128(risk.value)
+            .ok()?
+            .saturating_sub(mul_div(amount, price, u128::from(PRICE_PREC)).ok()?);
+        if value <= others {
+            return None;
+        }
+        let trigger = mul_div(value - others, u128::from(PRICE_PREC), amount).ok()?;
+        Some((params.price_feed, trigger))
+    }
+
+    /// put a loan into the indexes by the time it gets due and the price it hits the next threshold
+    pub fn index_loan(loan_id: LoanId) {
+        Self::unindex_loan(loan_id);
+        if !<Loans<T>>::exists(loan_id) || Self::check_loan_in_liquidation(&loan_id) {
+            return;
+        }
+        let loan = Self::get_loan_by_id(loan_id);
+        let now = <timestamp::Module<T>>::get();
+        let due = if loan.due > now {
+            loan.due
+        } else {
+            loan.due_extend
+        };
+        // overdue ones are checked in the next hour again
+        let mut due_hour = Self::moment_hour(due).max(Self::due_cursor(now));
+        let recheck = match loan.status {
+            // back to well once the price recovers
+            LoanHealth::Warning(_) | LoanHealth::Liquidating(_) => Some(WATCH_HOURS),
+            // the interest brings the loan closer to the thresholds without any price change
+            _ if Self::yearly_rate_of(loan_id) > 0 => Some(RECHECK_HOURS),
+            _ => None,
+        };
+        if let Some(hours) = recheck {
+            due_hour = due_hour.min(Self::due_cursor(now) + hours);
+        }
+        DueIndex::mutate(due_hour, |v| v.push(loan_id));
+
+        let price_key = Self::trigger_price(&loan)
+            .map(|(feed, price)| (feed, price / PRICE_INDEX_UNIT));
+        if let Some((feed, bucket)) = price_key {
+            PriceIndex::mutate((feed, bucket), |v| v.push(loan_id));
+            PriceBuckets::mutate(feed, |v| {
+                if let Err(i) = v.binary_search(&bucket) {
+                    v.insert(i, bucket);
+                }
+            });
+        }
+        LoanIndexKeys::insert(loan_id, (Some(due_hour), price_key));
+    }
+
+    fn unindex_loan(loan_id: LoanId) {
+        let (due_hour, price_key) = LoanIndexKeys::take(loan_id);
+        if let Some(hour) = due_hour {
+            let mut loans = DueIndex::take(hour);
+            loans.retain(|id| *id != loan_id);
+            if !loans.is_empty() {
+                DueIndex::insert(hour, loans);
+            }
+        }
+        if let Some((feed, bucket)) = price_key {
+            let mut loans = PriceIndex::take((feed, bucket));
+            loans.retain(|id| *id != loan_id);
+            if loans.is_empty() {
+                PriceBuckets::mutate(feed, |v| v.retain(|b| *b != bucket));
+            } else {
+                PriceIndex::insert((feed, bucket), loans);
+            }
+        }
+    }
+
+    /// the repaid loan asset is burnt, collateral is seized from the package's asset first then the basket
+    pub fn liquidate_by_keeper(
+        keeper: T::AccountId,
+        loan_id: LoanId,
+        amount: T::Balance,
+    ) -> DispatchResult {
+        ensure!(<Loans<T>>::exists(loan_id), Error::<T>::InvalidLoanId);
+        ensure!(
+            !Self::check_loan_in_liquidation(&loan_id),
+            Error::<T>::LoanInLiquidation
+        );
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        Self::settle_interest(loan_id)?;
+        let loan = Self::get_loan_by_id(loan_id);
+        let overflow = |_| Error::<T>::Overflow;
+        let mut collaterals = Self::collaterals_of(&loan);
+        let risk = Self::basket_risk(&collaterals).map_err(overflow)?;
+        ensure!(
+            Self::basket_ltv(loan.loan_balance_total, &risk) >= risk.liquidation_threshold,
+            Error::<T>::NotLiquidatable
+        );
+        let closable = balance_mul_div(
+            loan.loan_balance_total,
+            u128::from(Self::close_factor()),
+            u128::from(LTV_PREC),
+        )
+        .map_err(overflow)?;
+        ensure!(amount <= closable, Error::<T>::OverCloseFactor);
+        let package = Self::loan_package(loan.package_id);
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&package.loan_asset_id, &keeper) >= amount,
+            Error::<T>::NotEnoughToRepay
+        );
+
+        // what to seize is worked out before anything moves
+        let mut to_seize = balance_mul_div(
+            amount,
+            u128::from(LTV_PREC) + u128::from(Self::liquidation_discount()),
+            u128::from(LTV_PREC),
+        )
+        .map_err(overflow)?;
+        let mut seized = vec![];
+        for (asset_id, held) in collaterals.iter_mut() {
+            if to_seize.is_zero() {
+                break;
+            }
+            let price = Self::price_of(asset_id, &Self::risk_params(asset_id));
+            if price.is_zero() || held.is_zero() {
+                continue;
+            }
+            let value = balance_mul_div(*held, price, u128::from(PRICE_PREC)).map_err(overflow)?;
+            let (take, take_value) = if to_seize >= value {
+                (*held, value)
+            } else {
+                let take =
+                    balance_mul_div(to_seize, u128::from(PRICE_PREC), price).map_err(overflow)?;
+                (take.min(*held), to_seize)
+            };
+            *held -= take;
+            to_seize = to_seize.saturating_sub(take_value);
+            seized.push((asset_id.clone(), take));
+        }
+
+        let primary_seized = loan.collateral_balance_available.saturating_sub(collaterals[0].1);
+        let loan_balance_total = loan.loan_balance_total - amount;
+        let new_risk = Self::basket_risk(&collaterals).map_err(overflow)?;
+        let new_ltv = Self::basket_ltv(loan_balance_total, &new_risk);
+        let pawnshop = Self::pawn_shop();
+        for (asset_id, take) in seized.iter() {
+            ensure!(
+                <rio_assets::Module<T>>::free_balance(asset_id, &pawnshop) >= *take,
+                Error::<T>::NotEnoughCollateralInShop
+            );
+        }
+
+        // RIO requirements, the repaid asset should be burnt
+        <rio_assets::Module<T>>::burn(
+            system::RawOrigin::Root.into(),
+            package.loan_asset_id,
+            keeper.clone(),
+            amount,
+        )?;
+        for (asset_id, take) in seized.iter() {
+            <rio_assets::Module<T>>::make_transfer_with_event(asset_id, &pawnshop, &keeper, *take)?;
+        }
+        <Loans<T>>::mutate(loan_id, |v| {
+            v.loan_balance_total = loan_balance_total;
+            v.collateral_balance_original =
+                v.collateral_balance_original.saturating_sub(primary_seized);
+            v.collateral_balance_available = collaterals[0].1;
+            v.status = if new_ltv >= new_risk.liquidation_threshold {
+                LoanHealth::Liquidating(new_ltv)
+            } else if new_ltv >= new_risk.warning_threshold {
+                LoanHealth::Warning(new_ltv)
+            } else {
+                LoanHealth::Well
+            };
+        });
+        Self::put_basket(loan_id, collaterals.split_off(1));
+        Self::decrease_debt(&package.collateral_asset_id, amount);
+        <TotalCollateral<T>>::mutate(|v| *v = v.saturating_sub(primary_seized));
+
+        // paid off, what's left goes back to the owner
+        if loan_balance_total.is_zero() {
+            let loan = Self::get_loan_by_id(loan_id);
+            for (asset_id, balance) in Self::collaterals_of(&loan) {
+                if !balance.is_zero() {
+                    <rio_assets::Module<T>>::make_transfer_with_event(
+                        &asset_id, &pawnshop, &loan.who, balance,
+                    )?;
+                }
+            }
+            <TotalCollateral<T>>::mutate(|v| {
+                *v = v.saturating_sub(loan.collateral_balance_available)
+            });
+            Self::close_loan(&loan);
+        } else if new_ltv >= LTV_PREC {
+            // what's left is under water, the auction takes it
+            Self::liquidate_loan(loan_id, new_ltv);
+        } else {
+            Self::index_loan(loan_id);
+        }
+
+        Self::deposit_event(RawEvent::LiquidatedByKeeper(loan_id, keeper, amount, seized));
+        Ok(())
+    }
+
+    /// Immutable
+    /// collateral value falls linearly from the start rate to the floor rate, then stays at the floor
+    pub fn auction_price(loan_id: LoanId) -> Option<T::Balance> {
+        let auction = Self::auction(loan_id)?;
+        let loan = Self::get_loan_by_id(loan_id);
+        let value = Self::basket_risk(&Self::collaterals_of(&loan)).ok()?.value;
+
+        let duration = Self::auction_duration();
+        let elapsed = <system::Module<T>>::block_number().saturating_sub(auction.start);
+        let (start_rate, floor_rate) = (Self::auction_start_rate(), Self::auction_floor_rate());
+        let rate = if duration.is_zero() || elapsed >= duration {
+            floor_rate
+        } else {
+            let elapsed = TryInto::<u32>::try_into(elapsed).ok().unwrap_or(u32::max_value());
+            let duration = TryInto::<u32>::try_into(duration).ok().unwrap_or(u32::max_value());
+            let fallen =
+                u64::from(start_rate - floor_rate) * u64::from(elapsed) / u64::from(duration);
+            start_rate - fallen as u32
+        };
+        balance_mul_div(value, u128::from(rate), u128::from(LTV_PREC)).ok()
+    }
+
+    /// the bidder pays the auction price and gets all the collateral,
+    /// the payment pays off the debt, and what's left is cut by the liquidation penalty then refunded to the owner
+    pub fn take_auction(
+        who: T::AccountId,
+        loan_id: LoanId,
+        max_price: T::Balance,
+    ) -> DispatchResult {
+        ensure!(Self::auction(loan_id).is_some(), Error::<T>::NoAuction);
+        let price = Self::auction_price(loan_id).ok_or(Error::<T>::Overflow)?;
+        ensure!(price <= max_price, Error::<T>::OverMaxPrice);
+        let loan = Self::get_loan_by_id(loan_id);
+        let package = Self::loan_package(loan.package_id);
+        let pawnshop = Self::pawn_shop();
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&package.loan_asset_id, &who) >= price,
+            Error::<T>::NotEnoughToBid
+        );
+        let collaterals = Self::collaterals_of(&loan)
+            .into_iter()
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect::<Vec<_>>();
+        for (asset_id, amount) in collaterals.iter() {
+            ensure!(
+                <rio_assets::Module<T>>::free_balance(asset_id, &pawnshop) >= *amount,
+                Error::<T>::NotEnoughCollateralInShop
+            );
+        }
+
+        // the interest only changes the debt, the price and the collateral stay the same
+        Self::settle_interest(loan_id)?;
+        let loan = Self::get_loan_by_id(loan_id);
+        let debt = loan.loan_balance_total;
+        let repaid = price.min(debt);
+        let leftover = price - repaid;
+        let penalty = balance_mul_div(
+            leftover,
+            u128::from(Self::liquidation_penalty()),
+            u128::from(LTV_PREC),
+        )
+        .map_err(|_| Error::<T>::Overflow)?;
+        let refund = leftover.checked_sub(&penalty).ok_or(Error::<T>::Overflow)?;
+
+        <rio_assets::Module<T>>::make_transfer_with_event(
+            &package.loan_asset_id,
+            &who,
+            &pawnshop,
+            price,
+        )?;
+        let mut moved = Vec::with_capacity(collaterals.len());
+        for (asset_id, amount) in collaterals {
+            <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &pawnshop, &who, amount)
+                .or_else(|err| -> DispatchResult {
+                    // revert the collateral moved so far and the payment
+                    for (asset_id, amount) in moved.iter() {
+                        <rio_assets::Module<T>>::make_transfer_with_event(
+                            asset_id, &who, &pawnshop, *amount,
+                        )?;
+                    }
+                    <rio_assets::Module<T>>::make_transfer_with_event(
+                        &package.loan_asset_id,
+                        &pawnshop,
+                        &who,
+                        price,
+                    )?;
+                    Err(err)
+                })?;
+            moved.push((asset_id, amount));
+        }
+
+        // the payment in the pawn shop covers the burn, the penalty and the refund
+        // RIO requirements, the repaid loan asset should be burnt
+        <rio_assets::Module<T>>::burn(
+            system::RawOrigin::Root.into(),
+            package.loan_asset_id,
+            pawnshop.clone(),
+            repaid,
+        )?;
+        if !penalty.is_zero() {
+            <rio_assets::Module<T>>::make_transfer_with_event(
+                &package.loan_asset_id,
+                &pawnshop,
+                &Self::profit_pool(),
+                penalty,
+            )?;
+        }
+        if !refund.is_zero() {
+            <rio_assets::Module<T>>::make_transfer_with_event(
+                &package.loan_asset_id,
+                &pawnshop,
+                &loan.who,
+                refund,
+            )?;
+        }
+
+        Self::close_loan(&loan);
+        Self::decrease_debt(&package.collateral_asset_id, debt);
+        <TotalCollateral<T>>::mutate(|v| {
+            *v = v.saturating_sub(loan.collateral_balance_available)
+        });
+
+        if debt > repaid {
+            Self::deposit_event(RawEvent::AuctionShortfall(loan_id, debt - repaid));
+            Self::incur_bad_debt(loan_id, package.loan_asset_id, debt - repaid)?;
+        }
+        Self::deposit_event(RawEvent::AuctionSettled(loan_id, who, price, debt, penalty, refund));
+        Ok(())
+    }
+
+    pub fn open_line(who: T::AccountId, asset_id: T::AssetId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        ensure!(<CreditAssets<T>>::exists(&asset_id), Error::<T>::NotCreditAsset);
+        let mut line = CreditLine {
+            who: who.clone(),
+            amount,
+            asset_id,
+            ..Default::default()
+        };
+        Self::refresh_credit(&mut line)?;
+        // reserved balance is left out of the share tracking of rio-saving, so the bonus keeps going
+        <rio_assets::Module<T>>::reserve(&asset_id, &who, amount)?;
+
+        Self::accrue_interest();
+        let line_id = NextCreditLineId::mutate(|v| {
+            let id = *v;
+            *v += 1;
+            id
+        });
+        line.id = line_id;
+        <CreditLines<T>>::insert(line_id, line);
+        <CreditLinesByAccount<T>>::mutate(&who, |v| v.push(line_id));
+        CreditLineBorrowIndex::insert(line_id, Self::borrow_index());
+        CreditLineFeeIndex::insert(line_id, Self::credit_fee_index());
+        <TotalPledged<T>>::mutate(&asset_id, |v| *v = v.saturating_add(amount));
+
+        Self::deposit_event(RawEvent::CreditLineOpened(line_id, who, asset_id, amount));
+        Ok(())
+    }
+
+    pub fn pledge_to_line(who: T::AccountId, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        let mut line = Self::owned_credit_line(&who, line_id)?;
+        ensure!(<CreditAssets<T>>::exists(&line.asset_id), Error::<T>::NotCreditAsset);
+        line.amount = line.amount.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+        Self::refresh_credit(&mut line)?;
+
+        <rio_assets::Module<T>>::reserve(&line.asset_id, &who, amount)?;
+        <TotalPledged<T>>::mutate(&line.asset_id, |v| *v = v.saturating_add(amount));
+        <CreditLines<T>>::insert(line_id, line);
+        Self::deposit_event(RawEvent::CreditPledged(line_id, amount));
+        Ok(())
+    }
+
+    pub fn unpledge_from_line(who: T::AccountId, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        let mut line = Self::owned_credit_line(&who, line_id)?;
+        ensure!(amount <= line.amount, Error::<T>::NotEnoughPledge);
+        Self::settle_credit_interest(&mut line)?;
+
+        line.amount -= amount;
+        Self::refresh_credit(&mut line)?;
+        ensure!(line.drawn <= line.credit, Error::<T>::OverLTVLimit);
+        <rio_assets::Module<T>>::unreserve(&line.asset_id, &who, amount);
+        <TotalPledged<T>>::mutate(&line.asset_id, |v| *v = v.saturating_sub(amount));
+        <CreditLines<T>>::insert(line_id, line);
+        Self::deposit_event(RawEvent::CreditUnpledged(line_id, amount));
+        Ok(())
+    }
+
+    pub fn draw_from_line(who: T::AccountId, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        let mut line = Self::owned_credit_line(&who, line_id)?;
+        if let Some(cap) = Self::loan_cap() {
+            ensure!(Self::total_loan() < cap, Error::<T>::ReachLoanCap);
+        }
+        Self::settle_credit_interest(&mut line)?;
+        Self::refresh_credit(&mut line)?;
+        let drawn = line.drawn.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+        ensure!(drawn <= line.credit, Error::<T>::ShortOfCredit);
+        // the same limits of the borrower as applying for a loan
+        let tier = Self::tier_of(&who);
+        if let Some(tier) = &tier {
+            if tier.max_ltv < line.ltv {
+                let tier_credit =
+                    balance_mul_div(line.credit, u128::from(tier.max_ltv), u128::from(line.ltv))
+                        .map_err(|_| Error::<T>::Overflow)?;
+                ensure!(drawn <= tier_credit, Error::<T>::OverLTVLimit);
+            }
+        }
+        if let Some(room) = Self::borrowing_room(&who, &tier) {
+            ensure!(amount <= room, Error::<T>::OverAccountCap);
+        }
+        Self::check_debt_ceiling(&line.asset_id, amount)?;
+
+        <rio_assets::Module<T>>::mint(
+            system::RawOrigin::Root.into(),
+            Self::loan_asset_id(),
+            who,
+            amount,
+        )?;
+        line.drawn = drawn;
+        Self::increase_debt(&line.asset_id, amount);
+        <CreditLines<T>>::insert(line_id, line);
+        Self::deposit_event(RawEvent::CreditDrawn(line_id, amount));
+        Ok(())
+    }
+
+    pub fn repay_to_line(who: T::AccountId, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        let mut line = Self::owned_credit_line(&who, line_id)?;
+        Self::settle_credit_interest(&mut line)?;
+        let amount = amount.min(line.drawn);
+        let loan_asset_id = Self::loan_asset_id();
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&loan_asset_id, &who) >= amount,
+            Error::<T>::NotEnoughToRepay
+        );
+
+        <rio_assets::Module<T>>::burn(
+            system::RawOrigin::Root.into(),
+            loan_asset_id,
+            who,
+            amount,
+        )?;
+        line.drawn -= amount;
+        Self::decrease_debt(&line.asset_id, amount);
+        <CreditLines<T>>::insert(line_id, line);
+        Self::deposit_event(RawEvent::CreditRepaid(line_id, amount));
+        Ok(())
+    }
+
+    pub fn close_line(who: T::AccountId, line_id: CreditLineId) -> DispatchResult {
+        let mut line = Self::owned_credit_line(&who, line_id)?;
+        Self::settle_credit_interest(&mut line)?;
+        ensure!(line.drawn.is_zero(), Error::<T>::CreditLineInDebt);
+
+        <rio_assets::Module<T>>::unreserve(&line.asset_id, &who, line.amount);
+        <TotalPledged<T>>::mutate(&line.asset_id, |v| *v = v.saturating_sub(line.amount));
+        <CreditLines<T>>::remove(line_id);
+        CreditLineBorrowIndex::remove(line_id);
+        CreditLineFeeIndex::remove(line_id);
+        <CreditLinesByAccount<T>>::mutate(&who, |v| v.retain(|id| *id != line_id));
+        Self::deposit_event(RawEvent::CreditLineClosed(line_id));
+        Ok(())
+    }
+
+    pub fn liquidate_line(keeper: T::AccountId, line_id: CreditLineId, amount: T::Balance) -> DispatchResult {
+        ensure!(!amount.is_zero(), Error::<T>::ZeroNotAllowed);
+        let mut line = Self::credit_line(line_id).ok_or(Error::<T>::InvalidCreditLineId)?;
+        let (debt, credit) = Self::line_debt_and_credit(&line).ok_or(Error::<T>::Overflow)?;
+        ensure!(debt > credit, Error::<T>::NotLiquidatable);
+        let overflow = |_| Error::<T>::Overflow;
+        ensure!(
+            amount <= balance_mul_div(debt, u128::from(Self::close_factor()), u128::from(LTV_PREC)).map_err(overflow)?,
+            Error::<T>::OverCloseFactor
+        );
+        let loan_asset_id = Self::loan_asset_id();
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&loan_asset_id, &keeper) >= amount,
+            Error::<T>::NotEnoughToRepay
+        );
+        Self::refresh_credit(&mut line)?;
+        // all of the pledge once it's worth less than the repaid balance plus the discount
+        let seized = if line.price.is_zero() {
+            line.amount
+        } else {
+            balance_mul_div(
+                amount,
+                checked_mul(u128::from(LTV_PREC + Self::liquidation_discount()), u128::from(PRICE_PREC))
+                    .map_err(overflow)?,
+                checked_mul(u128::from(LTV_PREC), line.price).map_err(overflow)?,
+            )
+            .map_err(overflow)?
+            .min(line.amount)
+        };
+        ensure!(
+            <rio_assets::Module<T>>::reserved_balance(&line.asset_id, &line.who) >= seized,
+            Error::<T>::NotEnoughPledge
+        );
+
+        Self::settle_credit_interest(&mut line)?;
+        // a transfer of the owner, so that rio-saving moves the shares along with the pledge
+        <rio_assets::Module<T>>::unreserve(&line.asset_id, &line.who, seized);
+        <rio_assets::Module<T>>::transfer(
+            system::RawOrigin::Signed(line.who.clone()).into(),
+            line.asset_id,
+            keeper.clone(),
+            seized,
+        )
+        .or_else(|err| -> DispatchResult {
+            <rio_assets::Module<T>>::reserve(&line.asset_id, &line.who, seized)?;
+            Err(err)
+        })?;
+        // RIO requirements, the repaid asset should be burnt
+        <rio_assets::Module<T>>::burn(
+            system::RawOrigin::Root.into(),
+            loan_asset_id,
+            keeper.clone(),
+            amount,
+        )?;
+        line.drawn = line.drawn.saturating_sub(amount);
+        line.amount -= seized;
+        Self::refresh_credit(&mut line)?;
+        Self::decrease_debt(&line.asset_id, amount);
+        <TotalPledged<T>>::mutate(&line.asset_id, |v| *v = v.saturating_sub(seized));
+        <CreditLines<T>>::insert(line_id, line);
+        Self::deposit_event(RawEvent::CreditLineLiquidated(line_id, keeper, amount, seized));
+        Ok(())
+    }
+
+    /// Immutable
+    /// the debt of the line including the interest not settled yet, and its credit at the current price,
+    /// the line can be liquidated once the debt is over the credit, keepers look it up themselves
+    pub fn line_debt_and_credit(
+        line: &CreditLine<T::AccountId, T::Balance, T::AssetId>,
+    ) -> Option<(T::Balance, T::Balance)> {
+        let mut line = line.clone();
+        Self::refresh_credit(&mut line).ok()?;
+        Some((line.drawn.saturating_add(Self::line_interest(&line)), line.credit))
+    }
+
+    fn owned_credit_line(
+        who: &T::AccountId,
+        line_id: CreditLineId,
+    ) -> result::Result<CreditLine<T::AccountId, T::Balance, T::AssetId>, DispatchError> {
+        let line = Self::credit_line(line_id).ok_or(Error::<T>::InvalidCreditLineId)?;
+        ensure!(line.who == *who, Error::<T>::NotCreditLineOwner);
+        Ok(line)
+    }
+
+    /// the credit at the current price, no credit at all once the asset is removed from the credit assets
+    fn refresh_credit(line: &mut CreditLine<T::AccountId, T::Balance, T::AssetId>) -> DispatchResult {
+        let params = Self::credit_asset(&line.asset_id).unwrap_or_default();
+        let price = Self::pledge_price(&line.asset_id);
+        let overflow = |_| Error::<T>::Overflow;
+        line.ltv = params.ltv;
+        line.price = price;
+        line.credit = balance_mul_div(
+            line.amount,
+            checked_mul(price, u128::from(params.ltv)).map_err(overflow)?,
+            u128::from(LTV_PREC) * u128::from(PRICE_PREC),
+        )
+        .map_err(overflow)?;
+        Ok(())
+    }
+
+    /// credit lines accrue interest by the interest model, or the stability fee alone without a model,
+    /// there is nothing to charge upfront, the settled line is written back
+    fn settle_credit_interest(
+        line: &mut CreditLine<T::AccountId, T::Balance, T::AssetId>,
+    ) -> DispatchResult {
+        Self::accrue_interest();
+        let interest = Self::line_interest(line);
+        if !interest.is_zero() {
+            let loan_asset_id = Self::loan_asset_id();
+            <rio_assets::Module<T>>::mint(
+                system::RawOrigin::Root.into(),
+                loan_asset_id,
+                Self::profit_pool(),
+                interest,
+            )?;
+            line.drawn += interest;
+            <CreditLines<T>>::insert(line.id, line.clone());
+            Self::increase_debt(&line.asset_id, interest);
+            <TotalProfit<T>>::mutate(|v| *v += interest);
+            Self::feed_stability_fund(loan_asset_id, interest);
+        }
+        CreditLineBorrowIndex::insert(line.id, Self::borrow_index());
+        CreditLineFeeIndex::insert(line.id, Self::credit_fee_index());
+        Ok(())
+    }
+
+    /// Immutable
+    /// pending changes in the order they apply
+    pub fn pending_param_changes(
+    ) -> Vec<(ParamChangeId, ParamChange<RiskParam<T::Balance>, T::BlockNumber>)> {
+        RiskTimelock::<T>::pending()
+    }
+
+    /// Immutable
+    /// the global thresholds once all the pending changes apply
+    fn pending_global_risk() -> BasketRisk<T::Balance> {
+        let mut risk = Self::global_risk();
+        for (_, change) in Self::pending_param_changes() {
+            let _ = Self::check_param_change(&mut risk, &change.param);
+        }
+        risk
+    }
+
+    /// update the thresholds with the change, and check they are still in order
+    fn check_param_change(
+        risk: &mut BasketRisk<T::Balance>,
+        param: &RiskParam<T::Balance>,
+    ) -> DispatchResult {
+        match *param {
+            RiskParam::GlobalLtvLimit(ltv) => risk.ltv_limit = ltv,
+            RiskParam::GlobalWarningThreshold(ltv) => risk.warning_threshold = ltv,
+            RiskParam::GlobalLiquidationThreshold(ltv) => risk.liquidation_threshold = ltv,
+            RiskParam::PenaltyRate(rate) => {
+                ensure!(rate <= LTV_PREC, Error::<T>::InvalidRate);
+                return Ok(());
+            }
+            RiskParam::LoanCap(_) => return Ok(()),
+        }
+        ensure!(
+            risk.ltv_limit < risk.warning_threshold
+                && risk.warning_threshold < risk.liquidation_threshold
+                && risk.liquidation_threshold <= LTV::from(LTV_PREC),
+            Error::<T>::InvalidThresholds
+        );
+        Ok(())
+    }
+
+    /// the change is checked again with the current parameters, it could be out of order
+    /// if a change before it is cancelled
+    pub fn apply_param_change(param: RiskParam<T::Balance>) -> DispatchResult {
+        Self::check_param_change(&mut Self::global_risk(), &param)?;
+        match param {
+            RiskParam::GlobalLtvLimit(ltv) => GlobalLTVLimit::put(ltv),
+            RiskParam::GlobalWarningThreshold(ltv) => {
+                GlobalWarningThreshold::put(ltv);
+                Self::recheck_loans(None);
+            }
+            RiskParam::GlobalLiquidationThreshold(ltv) => {
+                GlobalLiquidationThreshold::put(ltv);
+                Self::recheck_loans(None);
+            }
+            RiskParam::PenaltyRate(rate) => PenaltyRate::put(rate),
+            RiskParam::LoanCap(Some(cap)) => <LoanCap<T>>::put(cap),
+            RiskParam::LoanCap(None) => <LoanCap<T>>::kill(),
+        }
+        Ok(())
+    }
+
+    /// loans holding `asset_id`, or any asset on the global parameters if None, go into the check queue,
+    /// the check indexes them again by the new thresholds
+    fn recheck_loans(asset_id: Option<T::AssetId>) {
+        let loans = <Loans<T>>::enumerate()
+            .filter(|(_, loan)| {
+                Self::collaterals_of(loan).iter().any(|(id, _)| match &asset_id {
+                    Some(asset_id) => id == asset_id,
+                    None => !<CollateralParamsOf<T>>::exists(id),
+                })
+            })
+            .map(|(loan_id, _)| loan_id)
+            .collect::<Vec<_>>();
+        if !loans.is_empty() {
+            CheckQueue::mutate(|v| v.extend(loans));
+        }
+    }
+
+    fn apply_due_param_changes(now: T::BlockNumber) {
+        for (id, param) in RiskTimelock::<T>::take_due(now) {
+            match Self::apply_param_change(param) {
+                Ok(()) => Self::deposit_event(RawEvent::ParamChangeApplied(id)),
+                Err(_) => Self::deposit_event(RawEvent::ParamChangeRejected(id)),
+            }
+        }
     }
 
     fn check_loan_in_liquidation(loan_id: &LoanId) -> bool {
         LiquidatingLoans::get().contains(loan_id)
     }
 
+    /// health is computed on the aggregate value of the collateral basket
     fn check_loan_health(
         loan: &Loan<T::AccountId, T::Balance, T::Moment>,
         now: T::Moment,
-        btc_price: Price,
-        liquidation: LTV,
-        warning: LTV,
     ) -> LoanHealth {
-        let current_ltv = <Loan<T::AccountId, T::Balance, T::Moment>>::get_ltv(
-            loan.collateral_balance_available,
-            loan.loan_balance_total,
-            btc_price,
-        );
+        // a basket worth more than the precision is far from any threshold
+        if let Ok(risk) = Self::basket_risk(&Self::collaterals_of(loan)) {
+            let current_ltv = Self::basket_ltv(Self::loan_debt(loan), &risk);
 
-        if current_ltv >= liquidation {
-            return LoanHealth::Liquidating(current_ltv);
-        }
+            if current_ltv >= risk.liquidation_threshold {
+                return LoanHealth::Liquidating(current_ltv);
+            }
 
-        if current_ltv >= warning {
-            return LoanHealth::Warning(current_ltv);
+            if current_ltv >= risk.warning_threshold {
+                return LoanHealth::Warning(current_ltv);
+            }
         }
 
         if loan.due_extend <= now {
@@ -1193,6 +3839,42 @@ impl<T: Trait> rio_assets::traits::OnAssetTransfer<T::AssetId, T::AccountId, T::
 /// implement the rio_price::OnChange hook to be aware of the price changes
 impl<T: Trait> rio_price::OnChange for Module<T> {
     fn on_change(p: rio_price::Price) {
+        Self::on_price_drop(None, Self::current_btc_price(), p);
         CurrentBTCPrice::put(p);
     }
+
+    fn on_feed_change(feed: FeedId, p: rio_price::Price) {
+        // no collateral is worth nothing, the last price stays
+        if p.is_zero() {
+            return;
+        }
+        let mut old = None;
+        for (asset_id, params) in <CollateralParamsOf<T>>::enumerate() {
+            if params.price_feed == Some(feed) {
+                old = old.or(Some(Self::collateral_price(&asset_id)));
+                <CollateralPrices<T>>::insert(asset_id, p);
+            }
+        }
+        for (asset_id, params) in <CreditAssets<T>>::enumerate() {
+            if params.price_feed == Some(feed) {
+                <CollateralPrices<T>>::insert(asset_id, p);
+            }
+        }
+        if let Some(old) = old {
+            Self::on_price_drop(Some(feed), old, p);
+        }
+    }
+}
+
+/// a reserve deficit stops new borrowing, any other reason stops every function
+impl<T: Trait> TripBreakers for Module<T> {
+    fn trip_breakers(reason: TripReason) {
+        let functions = match reason {
+            TripReason::ReserveDeficit => vec![LoanFunction::Borrow],
+            _ => LoanFunction::all(),
+        };
+        for function in functions {
+            Self::trip(function, reason);
+        }
+    }
 }
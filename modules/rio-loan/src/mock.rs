@@ -1,4 +1,3 @@
-#![cfg(test)]
 #![allow(dead_code)]
 
 use super::*;
@@ -192,11 +191,21 @@ impl rio_assets::Trait for TestRuntime {
     type BeforeAssetTransfer = ();
     type BeforeAssetBurn = ();
 }
+impl rio_bridge::Trait for TestRuntime {
+    type Event = ();
+    type ParamChangeDelay = ParamChangeDelay;
+}
+parameter_types! {
+    pub const ParamChangeDelay: u64 = 10;
+}
 impl Trait for TestRuntime {
     type Event = ();
+    type Kyc = RioBridge;
+    type ParamChangeDelay = ParamChangeDelay;
 }
 
 pub type RioLoanTest = Module<TestRuntime>;
+pub type RioBridge = rio_bridge::Module<TestRuntime>;
 
 pub type SystemTest = system::Module<TestRuntime>;
 
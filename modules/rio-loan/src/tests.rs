@@ -12,8 +12,8 @@ use sp_runtime::{
 };
 
 use crate::mock::{
-    constants::*, new_test_ext, Call, ExtBuilder, Origin, RioLoanTest, SystemTest, TestEvent,
-    TestRuntime,
+    constants::*, new_test_ext, Call, ExtBuilder, Origin, RioBridge, RioLoanTest, SystemTest,
+    TestEvent, TestRuntime,
 };
 
 #[test]
@@ -40,6 +40,51 @@ fn resume_works() {
     });
 }
 
+#[test]
+fn circuit_breaker_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::trip_breaker(
+            system::RawOrigin::Root.into(),
+            LoanFunction::Borrow
+        ));
+        assert!(!RioLoanTest::is_tripped(LoanFunction::Liquidation));
+        assert_noop!(
+            RioLoanTest::apply(Origin::signed(ALICE), 1_00000000, 4000_00000000, package_id),
+            Error::<TestRuntime>::FunctionTripped
+        );
+        assert_ok!(RioLoanTest::reset_breaker(
+            system::RawOrigin::Root.into(),
+            LoanFunction::Borrow
+        ));
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+
+        // a reserve deficit only stops new borrowing
+        <RioLoanTest as TripBreakers>::trip_breakers(TripReason::ReserveDeficit);
+        assert!(RioLoanTest::is_tripped(LoanFunction::Borrow));
+        assert!(!RioLoanTest::is_tripped(LoanFunction::Liquidation));
+        <RioLoanTest as TripBreakers>::trip_breakers(TripReason::InvariantBroken);
+        assert_eq!(RioLoanTest::breakers().len(), 2);
+    });
+}
+
 #[test]
 fn create_package_works() {
     ExtBuilder::default().build().execute_with(|| {
@@ -153,6 +198,10 @@ fn draw_works() {
         assert_eq!(profit, 96000000);
         assert_eq!(user_got + profit, 4000_00000000);
 
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 0),
+            Error::<TestRuntime>::ZeroNotAllowed
+        );
         assert_ok!(RioLoanTest::draw(
             Origin::signed(ALICE),
             loan_id,
@@ -236,31 +285,1760 @@ fn liquidate_loan_works() {
     });
 }
 
-/// TODO: try to figure out how to lower btc price to trigger liquidation
 #[test]
-fn add_collateral_works() {}
+fn liquidation_returns_basket() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        rio_price::FeedPrice::insert(1, 400_0000);
+        assert_ok!(RioLoanTest::set_collateral_params(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            Some(CollateralParams {
+                ltv_limit: 5000,
+                warning_threshold: 7000,
+                liquidation_threshold: 8000,
+                minimum_collateral: 1_00000000,
+                price_feed: Some(1),
+            })
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        assert_ok!(RioLoanTest::add_collateral_asset(
+            Origin::signed(ALICE),
+            loan_id,
+            RBTC_ASSET_ID,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 1));
 
-fn next_block() {
-    SystemTest::set_block_number(SystemTest::block_number() + 1);
-    RioLoanTest::on_initialize(SystemTest::block_number());
+        next_block();
+
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![loan_id]);
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            BOB,
+            5000_00000000
+        ));
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_ok!(RioLoanTest::mark_loan_liquidated(&loan, BOB, 5000_00000000));
+        // the debt is covered, so the rest of the basket goes back to the owner
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &ALICE),
+            1_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &PAWN_SHOP),
+            0
+        );
+        assert_eq!(RioLoanTest::loan_basket(loan_id), vec![]);
+    });
 }
 
 #[test]
-fn expiration_penalty() {
-    let loan: Loan<u64, u128, u64> = Loan {
-        id: 1,
-        package_id: 1,
-        who: 1,
-        due: 1,
-        due_extend: 1,
-        collateral_balance_original: 100000000,
-        collateral_balance_available: 100000000,
-        loan_balance_total: 520000000000,
-        status: LoanHealth::Well,
+fn interest_model_rate() {
+    let model = InterestRateModel {
+        base_rate: 100_0000,
+        slope_low: 2000_0000,
+        slope_high: 10000_0000,
+        kink: 8000,
+        blocks_per_year: 100,
     };
+    assert_eq!(model.yearly_rate(0), 100_0000);
+    assert_eq!(model.yearly_rate(4000), 900_0000);
+    assert_eq!(model.yearly_rate(8000), 1700_0000);
+    assert_eq!(model.yearly_rate(9000), 2700_0000);
+    // utilization is capped at 100%
+    assert_eq!(model.yearly_rate(20000), 3700_0000);
+}
+
+#[test]
+fn interest_accrues_by_borrow_index() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(RioLoanTest::apply_param_change(RiskParam::LoanCap(Some(
+            10000_00000000
+        ))));
+        assert_ok!(RioLoanTest::set_interest_model(
+            system::RawOrigin::Root.into(),
+            Some(InterestRateModel {
+                base_rate: 0,
+                slope_low: 2000_0000,
+                slope_high: 10000_0000,
+                kink: 8000,
+                blocks_per_year: 100,
+            })
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        // nothing is charged upfront
+        let profit_pool = RioLoanTest::profit_pool();
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &profit_pool),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &ALICE),
+            4000_00000000
+        );
+        assert_eq!(RioLoanTest::utilization(), 4000);
+        // checked again in a day for the interest, long before it gets due
+        assert_eq!(RioLoanTest::loan_index_keys(loan_id).0, Some(RECHECK_HOURS));
+
+        // 8% a year, 100 blocks a year
+        next_block();
+        assert_eq!(RioLoanTest::borrow_index(), BORROW_INDEX_PREC + BORROW_INDEX_PREC * 8 / 10000);
+        let loan = RioLoanTest::get_loan_by_id(loan_id);
+        assert_eq!(RioLoanTest::accrued_interest(&loan), 3_20000000);
+        assert_eq!(RioLoanTest::loan_debt(&loan), 4003_20000000);
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            ALICE,
+            3_20000000
+        ));
+        assert_ok!(RioLoanTest::repay(Origin::signed(ALICE), loan_id));
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &profit_pool),
+            3_20000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &ALICE),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &ALICE),
+            1_00000000
+        );
+        assert_eq!(RioLoanTest::loan_borrow_index(loan_id), None);
+    });
+}
+
+#[test]
+fn repay_partial_and_withdraw_collateral_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+
+        assert_noop!(
+            RioLoanTest::repay_partial(Origin::signed(BOB), loan_id, 1000_00000000),
+            Error::<TestRuntime>::NotLoanOwner
+        );
+        assert_noop!(
+            RioLoanTest::repay_partial(Origin::signed(ALICE), loan_id, 4000_00000000),
+            Error::<TestRuntime>::UseRepayToPayOff
+        );
+        assert_ok!(RioLoanTest::repay_partial(
+            Origin::signed(ALICE),
+            loan_id,
+            1000_00000000
+        ));
+        let loan = RioLoanTest::get_loan_by_id(loan_id);
+        assert_eq!(loan.loan_balance_total, 3000_00000000);
+        assert_eq!(RioLoanTest::total_loan(), 3000_00000000);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &ALICE),
+            3000_00000000 - 96000000
+        );
+
+        // LTV would be 7500
+        assert_noop!(
+            RioLoanTest::withdraw_collateral(Origin::signed(ALICE), loan_id, 50000000),
+            Error::<TestRuntime>::OverLTVLimit
+        );
+        assert_ok!(RioLoanTest::withdraw_collateral(
+            Origin::signed(ALICE),
+            loan_id,
+            30000000
+        ));
+        let loan = RioLoanTest::get_loan_by_id(loan_id);
+        assert_eq!(loan.collateral_balance_available, 70000000);
+        assert_eq!(RioLoanTest::total_collateral(), 70000000);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &ALICE),
+            30000000
+        );
+    });
+}
+
+#[test]
+fn multi_collateral_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            ALICE,
+            10_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+
+        assert_noop!(
+            RioLoanTest::add_collateral_asset(Origin::signed(ALICE), loan_id, RBTC_ASSET_ID, 10_00000000),
+            Error::<TestRuntime>::NotCollateralAsset
+        );
+        assert_noop!(
+            RioLoanTest::set_collateral_params(
+                system::RawOrigin::Root.into(),
+                RBTC_ASSET_ID,
+                Some(CollateralParams {
+                    ltv_limit: 8000,
+                    warning_threshold: 7000,
+                    liquidation_threshold: 8000,
+                    minimum_collateral: 1_00000000,
+                    price_feed: Some(1),
+                })
+            ),
+            Error::<TestRuntime>::InvalidThresholds
+        );
+        let rbtc_params = CollateralParams {
+            ltv_limit: 5000,
+            warning_threshold: 7000,
+            liquidation_threshold: 8000,
+            minimum_collateral: 1_00000000,
+            price_feed: Some(1),
+        };
+        // the feed has no price yet
+        assert_noop!(
+            RioLoanTest::set_collateral_params(
+                system::RawOrigin::Root.into(),
+                RBTC_ASSET_ID,
+                Some(rbtc_params.clone())
+            ),
+            Error::<TestRuntime>::NoPrice
+        );
+        rio_price::FeedPrice::insert(1, 300_0000);
+        assert_ok!(RioLoanTest::set_collateral_params(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            Some(rbtc_params)
+        ));
+        assert_eq!(RioLoanTest::collateral_price(RBTC_ASSET_ID), 300_0000);
+        <RioLoanTest as rio_price::OnChange>::on_feed_change(1, 400_0000);
+        assert_eq!(RioLoanTest::collateral_price(RBTC_ASSET_ID), 400_0000);
+        // a zero price is ignored
+        <RioLoanTest as rio_price::OnChange>::on_feed_change(1, 0);
+        assert_eq!(RioLoanTest::collateral_price(RBTC_ASSET_ID), 400_0000);
+
+        assert_ok!(RioLoanTest::add_collateral_asset(
+            Origin::signed(ALICE),
+            loan_id,
+            RBTC_ASSET_ID,
+            10_00000000
+        ));
+        assert_eq!(
+            RioLoanTest::loan_basket(loan_id),
+            vec![(RBTC_ASSET_ID, 10_00000000)]
+        );
+
+        // 8000 worth of SBTC and 4000 worth of RBTC
+        let loan = RioLoanTest::get_loan_by_id(loan_id);
+        let risk = RioLoanTest::basket_risk(&RioLoanTest::collaterals_of(&loan)).unwrap();
+        assert_eq!(risk.value, 12000_00000000);
+        assert_eq!(risk.ltv_limit, 6000);
+        assert_eq!(RioLoanTest::basket_ltv(loan.loan_balance_total, &risk), 3333);
+        // the solvency values the whole basket, not only the SBTC
+        assert_eq!(RioLoanTest::solvency().collateral_value, 12000_00000000);
+        assert_eq!(RioLoanTest::system_debt().collateral_value, 12000_00000000);
+
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 3300_00000000),
+            Error::<TestRuntime>::ShortOfCredit
+        );
+        assert_ok!(RioLoanTest::draw(
+            Origin::signed(ALICE),
+            loan_id,
+            3000_00000000
+        ));
 
+        // LTV would be 8750 without RBTC
+        assert_noop!(
+            RioLoanTest::withdraw_collateral_asset(
+                Origin::signed(ALICE),
+                loan_id,
+                RBTC_ASSET_ID,
+                10_00000000
+            ),
+            Error::<TestRuntime>::OverLTVLimit
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            ALICE,
+            96000000 + 72000000
+        ));
+        assert_ok!(RioLoanTest::repay(Origin::signed(ALICE), loan_id));
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &ALICE),
+            10_00000000
+        );
+        assert_eq!(RioLoanTest::loan_basket(loan_id), vec![]);
+    });
+}
+
+#[test]
+fn collateral_auction_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioLoanTest::set_auction_params(
+            system::RawOrigin::Root.into(),
+            10,
+            11000,
+            8000
+        ));
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 1));
+
+        next_block();
+
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![loan_id]);
+        let start = SystemTest::block_number();
+        assert_eq!(
+            RioLoanTest::auction(loan_id),
+            Some(CollateralAuction { loan_id, start })
+        );
+
+        // the collateral is worth 8000 again, and the price falls to 95% after 5 blocks
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 8000_0000));
+        assert_eq!(RioLoanTest::auction_price(loan_id), Some(8800_00000000));
+        for _ in 0..5 {
+            next_block();
+        }
+        assert_eq!(RioLoanTest::auction_price(loan_id), Some(7600_00000000));
+        assert_noop!(
+            RioLoanTest::restart_auction(Origin::signed(BOB), loan_id),
+            Error::<TestRuntime>::AuctionNotOver
+        );
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            BOB,
+            7600_00000000
+        ));
+        assert_noop!(
+            RioLoanTest::bid(Origin::signed(BOB), loan_id, 7000_00000000),
+            Error::<TestRuntime>::OverMaxPrice
+        );
+        // nothing moves when the pawn shop can't hand over the collateral
+        assert_ok!(<rio_assets::Module<TestRuntime>>::burn(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            PAWN_SHOP,
+            1
+        ));
+        assert_noop!(
+            RioLoanTest::bid(Origin::signed(BOB), loan_id, 7600_00000000),
+            Error::<TestRuntime>::NotEnoughCollateralInShop
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            PAWN_SHOP,
+            1
+        ));
+        let rio_alice = <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &ALICE);
+        let profit = <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &PROFIT_POOL);
+        assert_ok!(RioLoanTest::bid(
+            Origin::signed(BOB),
+            loan_id,
+            7600_00000000
+        ));
+
+        // 4000 pays off the debt, 13% of the rest is the penalty
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            1_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &BOB),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &PROFIT_POOL),
+            profit + 468_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &ALICE),
+            rio_alice + 3132_00000000
+        );
+        assert_eq!(RioLoanTest::total_loan(), 0);
+        assert_eq!(RioLoanTest::auction(loan_id), None);
+        assert!(RioLoanTest::liquidating_loans().is_empty());
+        assert!(RioLoanTest::loans_by_account(&ALICE).is_empty());
+    });
+}
+
+/// TODO: try to figure out how to lower btc price to trigger liquidation
+#[test]
+fn keeper_liquidation_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        let loan_id = RioLoanTest::loans_by_account(&ALICE)[0];
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            BOB,
+            5000_00000000
+        ));
+        assert_noop!(
+            RioLoanTest::set_keeper_params(Origin::ROOT, 500, 0),
+            Error::<TestRuntime>::InvalidCloseFactor
+        );
+        assert_ok!(RioLoanTest::set_keeper_params(Origin::ROOT, 500, 5000));
+        assert_noop!(
+            RioLoanTest::liquidate(Origin::signed(BOB), loan_id, 1000_00000000),
+            Error::<TestRuntime>::NotLiquidatable
+        );
+
+        // LTV 4000 / 4400 is over the liquidation threshold
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4400_0000));
+        next_block();
+        assert_eq!(RioLoanTest::liquidating_loans().len(), 0);
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert!(match loan.status {
+            LoanHealth::Liquidating(_) => true,
+            _ => false,
+        });
+
+        assert_noop!(
+            RioLoanTest::liquidate(Origin::signed(BOB), loan_id, 2001_00000000),
+            Error::<TestRuntime>::OverCloseFactor
+        );
+        // nothing moves when the pawn shop can't hand over the seized collateral
+        assert_ok!(<rio_assets::Module<TestRuntime>>::burn(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            PAWN_SHOP,
+            52272729
+        ));
+        assert_noop!(
+            RioLoanTest::liquidate(Origin::signed(BOB), loan_id, 2000_00000000),
+            Error::<TestRuntime>::NotEnoughCollateralInShop
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            PAWN_SHOP,
+            52272729
+        ));
+        assert_ok!(RioLoanTest::liquidate(
+            Origin::signed(BOB),
+            loan_id,
+            2000_00000000
+        ));
+        // 2000 RIO plus the 5% discount, at 4400 per BTC
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &BOB),
+            3000_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            47727272
+        );
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_eq!(loan.loan_balance_total, 2000_00000000);
+        assert_eq!(loan.collateral_balance_available, 52272728);
+        assert!(match loan.status {
+            LoanHealth::Warning(_) => true,
+            _ => false,
+        });
+        assert_eq!(RioLoanTest::total_loan(), 2000_00000000);
+        assert_eq!(RioLoanTest::total_collateral(), 52272728);
+
+        // healthy again
+        assert_noop!(
+            RioLoanTest::liquidate(Origin::signed(BOB), loan_id, 100_00000000),
+            Error::<TestRuntime>::NotLiquidatable
+        );
+
+        // under water, keepers can't bring it back, the module liquidates it
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 1));
+        next_block();
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![loan_id]);
+        assert_noop!(
+            RioLoanTest::liquidate(Origin::signed(BOB), loan_id, 100_00000000),
+            Error::<TestRuntime>::LoanInLiquidation
+        );
+    });
+}
+
+#[test]
+fn indexed_health_checks_work() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        for who in &[ALICE, BOB] {
+            assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+                system::RawOrigin::Root.into(),
+                SBTC_ASSET_ID,
+                *who,
+                1_00000000
+            ));
+            assert_ok!(RioLoanTest::apply(
+                Origin::signed(*who),
+                1_00000000,
+                4000_00000000,
+                package_id
+            ));
+        }
+        let alice_loan = RioLoanTest::loans_by_account(&ALICE)[0];
+        let bob_loan = RioLoanTest::loans_by_account(&BOB)[0];
+
+        // both get warned at 5000
+        assert_eq!(
+            RioLoanTest::price_index((None, 50)),
+            vec![alice_loan, bob_loan]
+        );
+        assert_eq!(RioLoanTest::price_buckets(None), vec![50]);
+
+        // not crossed
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 6000_0000));
+        assert!(RioLoanTest::check_queue().is_empty());
+
+        assert_noop!(
+            RioLoanTest::set_check_weight_limit(Origin::ROOT, LOAN_CHECK_WEIGHT),
+            Error::<TestRuntime>::CheckWeightTooLow
+        );
+        // one check which writes in a block
+        assert_ok!(RioLoanTest::set_check_weight_limit(
+            Origin::ROOT,
+            LOAN_CHECK_WEIGHT + LOAN_UPDATE_WEIGHT
+        ));
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 1));
+        assert_eq!(RioLoanTest::check_queue(), vec![alice_loan, bob_loan]);
+        assert!(RioLoanTest::price_buckets(None).is_empty());
+
+        next_block();
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![alice_loan]);
+        assert_eq!(RioLoanTest::check_queue(), vec![bob_loan]);
+        assert_eq!(RioLoanTest::loan_index_keys(alice_loan), (None, None));
+
+        next_block();
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![alice_loan, bob_loan]);
+        assert!(RioLoanTest::check_queue().is_empty());
+    });
+}
+
+#[test]
+fn bad_debt_covered_by_stability_fund() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioLoanTest::set_stability_fund(Origin::ROOT, Some(TEAM), 10001),
+            Error::<TestRuntime>::InvalidRate
+        );
+        assert_ok!(RioLoanTest::set_stability_fund(Origin::ROOT, Some(TEAM), 5000));
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        let loan_id = RioLoanTest::loans_by_account(&ALICE)[0];
+        // half of the interest
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &TEAM),
+            48000000
+        );
+        assert!(RioLoanTest::solvency().solvent);
+
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 1));
+        next_block();
+        assert_eq!(RioLoanTest::liquidating_loans(), vec![loan_id]);
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            BOB,
+            3000_00000000
+        ));
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_ok!(RioLoanTest::mark_loan_liquidated(&loan, BOB, 3000_00000000));
+        // the loan is off the books, the shortfall is only in the bad debt
+        assert_eq!(RioLoanTest::total_loan(), 0);
+        assert_eq!(RioLoanTest::total_collateral(), 0);
+        assert_eq!(RioLoanTest::collateral_debt(SBTC_ASSET_ID), 0);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &BOB),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &TEAM),
+            0
+        );
+        assert_eq!(RioLoanTest::bad_debt(), 1000_00000000 - 48000000);
+        let solvency = RioLoanTest::solvency();
+        assert_eq!(solvency.bad_debt, 1000_00000000 - 48000000);
+        assert!(!solvency.solvent);
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            TEAM,
+            1000_00000000
+        ));
+        assert_ok!(RioLoanTest::set_stability_fund(Origin::ROOT, Some(TEAM), 5000));
+        assert_eq!(RioLoanTest::bad_debt(), 0);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &TEAM),
+            48000000
+        );
+    });
+}
+
+#[test]
+fn rollover_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        let new_package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            20,
+            200,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        let loan_id = RioLoanTest::loans_by_account(&ALICE)[0];
+        assert_noop!(
+            RioLoanTest::rollover(Origin::signed(ALICE), loan_id, new_package_id),
+            Error::<TestRuntime>::LoanNotDue
+        );
+
+        // 10 days later
+        <timestamp::Module<TestRuntime>>::set_timestamp(864_000_000);
+        assert_noop!(
+            RioLoanTest::rollover(Origin::signed(BOB), loan_id, new_package_id),
+            Error::<TestRuntime>::NotLoanOwner
+        );
+        assert_noop!(
+            RioLoanTest::rollover(Origin::signed(ALICE), loan_id, 100),
+            Error::<TestRuntime>::InvalidPackageId
+        );
+        assert_ok!(RioLoanTest::rollover(
+            Origin::signed(ALICE),
+            loan_id,
+            new_package_id
+        ));
+
+        // 20 days of interest at the new package's rate, no penalty out of the collateral
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_eq!(loan.package_id, new_package_id);
+        assert_eq!(loan.loan_balance_total, 4038_40000000);
+        assert_eq!(loan.collateral_balance_available, 1_00000000);
+        assert_eq!(loan.due, 2_592_000_000);
+        assert_eq!(loan.due_extend, 2_764_800_000);
+        assert_eq!(loan.status, LoanHealth::Well);
+        assert_eq!(RioLoanTest::total_loan(), 4038_40000000);
+    });
+}
+
+#[test]
+fn loan_positions_and_quote_work() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_eq!(RioLoanTest::quote_apply(ALICE, 1_00000000, 100), None);
+        assert_eq!(RioLoanTest::quote_apply(ALICE, 1_0000000, package_id), None);
+        let quote = Some(LoanQuote {
+            collateral_amount: 1_00000000,
+            loan_amount: 5200_00000000,
+            interest: 124800000,
+            received: 5198_75200000,
+            liquidation_price: Some(5777_7777),
+        });
+        assert_eq!(RioLoanTest::quote_apply(ALICE, 1_00000000, package_id), quote);
+        // the same checks as apply
+        assert_ok!(RioLoanTest::set_account_loan_cap(
+            Origin::ROOT,
+            ALICE,
+            Some(5000_00000000)
+        ));
+        assert_eq!(RioLoanTest::quote_apply(ALICE, 1_00000000, package_id), None);
+        assert_eq!(RioLoanTest::quote_apply(BOB, 1_00000000, package_id), quote);
+        assert_ok!(RioLoanTest::set_debt_ceiling(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            Some(5000_00000000)
+        ));
+        assert_eq!(RioLoanTest::quote_apply(BOB, 1_00000000, package_id), None);
+        assert_ok!(RioLoanTest::set_account_loan_cap(Origin::ROOT, ALICE, None));
+        assert_ok!(RioLoanTest::set_debt_ceiling(Origin::ROOT, SBTC_ASSET_ID, None));
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        let loan_id = RioLoanTest::loans_by_account(&ALICE)[0];
+        assert_eq!(
+            RioLoanTest::loan_positions(ALICE),
+            vec![LoanPosition {
+                loan_id,
+                package_id,
+                collaterals: vec![(SBTC_ASSET_ID, 1_00000000)],
+                debt: 4000_00000000,
+                ltv: 5000,
+                health: LoanHealth::Well,
+                liquidation_price: Some(4444_4444),
+                time_to_due: 864_000_000,
+                time_to_due_extend: 1_036_800_000,
+                drawable: 1200_00000000,
+            }]
+        );
+        assert_eq!(RioLoanTest::loan_positions(BOB), vec![]);
+
+        // the drawable amount is exactly what draw allows
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 1200_00000001),
+            Error::<TestRuntime>::ShortOfCredit
+        );
+        assert_ok!(RioLoanTest::draw(Origin::signed(ALICE), loan_id, 1200_00000000));
+        let position = &RioLoanTest::loan_positions(ALICE)[0];
+        assert_eq!(position.ltv, 6500);
+        assert_eq!(position.drawable, 0);
+    });
+}
+
+#[test]
+fn credit_line_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let assets = |who| {
+            (
+                <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &who),
+                <rio_assets::Module<TestRuntime>>::reserved_balance(&RBTC_ASSET_ID, &who),
+                <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &who),
+            )
+        };
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_noop!(
+            RioLoanTest::open_credit_line(Origin::signed(ALICE), RBTC_ASSET_ID, 1_00000000),
+            Error::<TestRuntime>::NotCreditAsset
+        );
+        assert_noop!(
+            RioLoanTest::set_credit_asset(
+                Origin::ROOT,
+                RBTC_ASSET_ID,
+                Some(CreditParams {
+                    ltv: 10000,
+                    price_feed: None
+                })
+            ),
+            Error::<TestRuntime>::InvalidLtv
+        );
+        // the saving share asset at the BTC price
+        assert_ok!(RioLoanTest::set_credit_asset(
+            Origin::ROOT,
+            RBTC_ASSET_ID,
+            Some(CreditParams {
+                ltv: 5000,
+                price_feed: None
+            })
+        ));
+
+        let line_id = RioLoanTest::next_credit_line_id();
+        assert_ok!(RioLoanTest::open_credit_line(
+            Origin::signed(ALICE),
+            RBTC_ASSET_ID,
+            1_00000000
+        ));
+        // the pledge stays in the account as reserved balance
+        assert_eq!(assets(ALICE), (0, 1_00000000, 0));
+        let line = RioLoanTest::credit_line(line_id).unwrap();
+        assert_eq!(line.credit, 4000_00000000);
+        assert_eq!(RioLoanTest::credit_lines_by_account(ALICE), vec![line_id]);
+
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(BOB), line_id, 1),
+            Error::<TestRuntime>::NotCreditLineOwner
+        );
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(ALICE), line_id, 4000_00000001),
+            Error::<TestRuntime>::ShortOfCredit
+        );
+        assert_ok!(RioLoanTest::draw_credit(
+            Origin::signed(ALICE),
+            line_id,
+            3000_00000000
+        ));
+        assert_eq!(assets(ALICE), (0, 1_00000000, 3000_00000000));
+        assert_eq!(RioLoanTest::total_loan(), 3000_00000000);
+
+        assert_noop!(
+            RioLoanTest::unpledge(Origin::signed(ALICE), line_id, 5000_0000),
+            Error::<TestRuntime>::OverLTVLimit
+        );
+        assert_ok!(RioLoanTest::unpledge(
+            Origin::signed(ALICE),
+            line_id,
+            2000_0000
+        ));
+        assert_eq!(assets(ALICE), (2000_0000, 8000_0000, 3000_00000000));
+        assert_eq!(RioLoanTest::credit_line(line_id).unwrap().credit, 3200_00000000);
+
+        // the credit follows the price
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4000_0000));
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(ALICE), line_id, 1),
+            Error::<TestRuntime>::ShortOfCredit
+        );
+        assert_ok!(RioLoanTest::pledge(Origin::signed(ALICE), line_id, 2000_0000));
+        assert_eq!(RioLoanTest::credit_line(line_id).unwrap().credit, 2000_00000000);
+
+        assert_noop!(
+            RioLoanTest::close_credit_line(Origin::signed(ALICE), line_id),
+            Error::<TestRuntime>::CreditLineInDebt
+        );
+        // only the drawn balance is repaid
+        assert_ok!(RioLoanTest::repay_credit(
+            Origin::signed(ALICE),
+            line_id,
+            5000_00000000
+        ));
+        assert_eq!(assets(ALICE), (0, 1_00000000, 0));
+        assert_eq!(RioLoanTest::total_loan(), 0);
+
+        assert_ok!(RioLoanTest::close_credit_line(Origin::signed(ALICE), line_id));
+        assert_eq!(assets(ALICE), (1_00000000, 0, 0));
+        assert_eq!(RioLoanTest::credit_line(line_id), None);
+        assert_eq!(RioLoanTest::credit_lines_by_account(ALICE), vec![]);
+    });
+}
+
+#[test]
+fn credit_line_liquidation_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            BOB,
+            2000_00000000
+        ));
+        assert_ok!(RioLoanTest::set_credit_asset(
+            Origin::ROOT,
+            RBTC_ASSET_ID,
+            Some(CreditParams {
+                ltv: 5000,
+                price_feed: None
+            })
+        ));
+        let line_id = RioLoanTest::next_credit_line_id();
+        assert_ok!(RioLoanTest::open_credit_line(
+            Origin::signed(ALICE),
+            RBTC_ASSET_ID,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::draw_credit(
+            Origin::signed(ALICE),
+            line_id,
+            3000_00000000
+        ));
+        assert_eq!(RioLoanTest::total_pledged(RBTC_ASSET_ID), 1_00000000);
+        // the pledge is counted in the collateral
+        assert_eq!(RioLoanTest::solvency().collateral_value, 8000_00000000);
+        assert_noop!(
+            RioLoanTest::liquidate_credit_line(Origin::signed(BOB), line_id, 1000_00000000),
+            Error::<TestRuntime>::NotLiquidatable
+        );
+
+        // credit of 2500 for 3000 drawn
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 5000_0000));
+        let line = RioLoanTest::credit_line(line_id).unwrap();
+        assert_eq!(
+            RioLoanTest::line_debt_and_credit(&line),
+            Some((3000_00000000, 2500_00000000))
+        );
+        assert_noop!(
+            RioLoanTest::liquidate_credit_line(Origin::signed(BOB), line_id, 1500_00000001),
+            Error::<TestRuntime>::OverCloseFactor
+        );
+        assert_ok!(RioLoanTest::liquidate_credit_line(
+            Origin::signed(BOB),
+            line_id,
+            1500_00000000
+        ));
+
+        // 1500 plus the 5% discount at 5000
+        let line = RioLoanTest::credit_line(line_id).unwrap();
+        assert_eq!(line.drawn, 1500_00000000);
+        assert_eq!(line.amount, 6850_0000);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &BOB),
+            3150_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::reserved_balance(&RBTC_ASSET_ID, &ALICE),
+            6850_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &BOB),
+            500_00000000
+        );
+        assert_eq!(RioLoanTest::total_loan(), 1500_00000000);
+        assert_eq!(RioLoanTest::total_pledged(RBTC_ASSET_ID), 6850_0000);
+        assert_noop!(
+            RioLoanTest::liquidate_credit_line(Origin::signed(BOB), line_id, 100_00000000),
+            Error::<TestRuntime>::NotLiquidatable
+        );
+    });
+}
+
+#[test]
+fn borrower_tiers_work() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(Origin::ROOT, 10, 100, 1));
+        assert_ok!(RioLoanTest::create_package(Origin::ROOT, 30, 100, 1));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            2_00000000
+        ));
+        assert_noop!(
+            RioLoanTest::set_borrower_tier(
+                Origin::ROOT,
+                None,
+                Some(BorrowerTier {
+                    max_ltv: 0,
+                    packages: vec![],
+                    cap: None
+                })
+            ),
+            Error::<TestRuntime>::InvalidLtv
+        );
+        // accounts not in the KYC list
+        assert_ok!(RioLoanTest::set_borrower_tier(
+            Origin::ROOT,
+            None,
+            Some(BorrowerTier {
+                max_ltv: 2500,
+                packages: vec![package_id],
+                cap: Some(1000_00000000)
+            })
+        ));
+        assert_ok!(RioLoanTest::set_borrower_tier(
+            Origin::ROOT,
+            Some(BlackOrWhite::White),
+            Some(BorrowerTier {
+                max_ltv: 5000,
+                packages: vec![],
+                cap: None
+            })
+        ));
+
+        assert_noop!(
+            RioLoanTest::apply(Origin::signed(ALICE), 1_00000000, 1000_00000000, package_id + 1),
+            Error::<TestRuntime>::PackageNotAllowed
+        );
+        assert_noop!(
+            RioLoanTest::apply(Origin::signed(ALICE), 1_00000000, 4000_00000000, package_id),
+            Error::<TestRuntime>::OverLTVLimit
+        );
+        assert_noop!(
+            RioLoanTest::apply(Origin::signed(ALICE), 1_00000000, 2000_00000000, package_id),
+            Error::<TestRuntime>::OverAccountCap
+        );
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            1000_00000000,
+            package_id
+        ));
+        assert_eq!(RioLoanTest::outstanding_debt(&ALICE), 1000_00000000);
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 1),
+            Error::<TestRuntime>::OverAccountCap
+        );
+
+        // the account's own cap goes before the tier's
+        assert_ok!(RioLoanTest::set_account_loan_cap(
+            Origin::ROOT,
+            ALICE,
+            Some(1500_00000000)
+        ));
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 1000_00000000),
+            Error::<TestRuntime>::OverAccountCap
+        );
+        assert_ok!(RioLoanTest::draw(Origin::signed(ALICE), loan_id, 500_00000000));
+        assert_ok!(RioLoanTest::set_account_loan_cap(
+            Origin::ROOT,
+            ALICE,
+            Some(10000_00000000)
+        ));
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 500_00000001),
+            Error::<TestRuntime>::ShortOfCredit
+        );
+
+        // verified by rio-bridge
+        RioBridge::mark(ALICE, BlackOrWhite::White);
+        assert_ok!(RioLoanTest::draw(Origin::signed(ALICE), loan_id, 2000_00000000));
+        assert_eq!(RioLoanTest::loan_positions(ALICE)[0].drawable, 500_00000000);
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            1000_00000000,
+            package_id + 1
+        ));
+        assert_eq!(RioLoanTest::outstanding_debt(&ALICE), 4500_00000000);
+    });
+}
+
+#[test]
+fn credit_line_follows_borrower_tier() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioLoanTest::set_borrower_tier(
+            Origin::ROOT,
+            None,
+            Some(BorrowerTier {
+                max_ltv: 2500,
+                packages: vec![],
+                cap: Some(1000_00000000)
+            })
+        ));
+        assert_ok!(RioLoanTest::set_credit_asset(
+            Origin::ROOT,
+            RBTC_ASSET_ID,
+            Some(CreditParams {
+                ltv: 5000,
+                price_feed: None
+            })
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            BOB,
+            1_00000000
+        ));
+        let line_id = RioLoanTest::next_credit_line_id();
+        assert_ok!(RioLoanTest::open_credit_line(
+            Origin::signed(BOB),
+            RBTC_ASSET_ID,
+            1_00000000
+        ));
+        assert_eq!(RioLoanTest::credit_line(line_id).unwrap().credit, 4000_00000000);
+
+        // half of the credit at the max LTV of the tier
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(BOB), line_id, 2000_00000001),
+            Error::<TestRuntime>::OverLTVLimit
+        );
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(BOB), line_id, 1000_00000001),
+            Error::<TestRuntime>::OverAccountCap
+        );
+        assert_ok!(RioLoanTest::draw_credit(
+            Origin::signed(BOB),
+            line_id,
+            1000_00000000
+        ));
+        // the drawn balance counts in the cap
+        assert_eq!(RioLoanTest::outstanding_debt(&BOB), 1000_00000000);
+        assert_noop!(
+            RioLoanTest::draw_credit(Origin::signed(BOB), line_id, 1),
+            Error::<TestRuntime>::OverAccountCap
+        );
+    });
+}
+
+#[test]
+fn auto_top_up_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(Origin::ROOT, 10, 100, 1));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            2_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+
+        let policy = TopUpPolicy {
+            max_amount: 5000_0000,
+            target_ltv: 5000,
+        };
+        assert_noop!(
+            RioLoanTest::set_top_up_policy(Origin::signed(BOB), loan_id, Some(policy.clone())),
+            Error::<TestRuntime>::NotLoanOwner
+        );
+        assert_noop!(
+            RioLoanTest::set_top_up_policy(
+                Origin::signed(ALICE),
+                loan_id,
+                Some(TopUpPolicy {
+                    max_amount: 5000_0000,
+                    target_ltv: 0
+                })
+            ),
+            Error::<TestRuntime>::InvalidLtv
+        );
+        // a top up to the warning threshold doesn't get the loan out of the warning
+        assert_noop!(
+            RioLoanTest::set_top_up_policy(
+                Origin::signed(ALICE),
+                loan_id,
+                Some(TopUpPolicy {
+                    max_amount: 5000_0000,
+                    target_ltv: 8000
+                })
+            ),
+            Error::<TestRuntime>::InvalidLtv
+        );
+        assert_ok!(RioLoanTest::set_top_up_policy(
+            Origin::signed(ALICE),
+            loan_id,
+            Some(policy)
+        ));
+
+        // 0.6667 SBTC needed to get back to 50%, but the policy allows 0.5 only
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4800_0000));
+        next_block();
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_eq!(loan.collateral_balance_available, 1_50000000);
+        assert_eq!(loan.status, LoanHealth::Well);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &ALICE),
+            5000_0000
+        );
+        assert_eq!(RioLoanTest::top_up_policy(loan_id).unwrap().max_amount, 0);
+        assert_eq!(RioLoanTest::loan_positions(ALICE)[0].ltv, 5555);
+
+        // nothing left to pull
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 3200_0000));
+        next_block();
+        let loan = RioLoanTest::get_loan_by_id(&loan_id);
+        assert_eq!(loan.collateral_balance_available, 1_50000000);
+        assert_eq!(loan.status, LoanHealth::Warning(8333));
+
+        assert_eq!(
+            RioLoanTest::top_up_amount(4000_00000000, &Default::default(), 5000, 8000_0000),
+            Ok(1_00000001)
+        );
+    });
+}
+
+#[test]
+fn threshold_changes_recheck_loans() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(Origin::ROOT, 10, 100, 1));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        // warned, liquidated at 4444
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4800_0000));
+        next_block();
+        assert_eq!(RioLoanTest::get_loan_by_id(loan_id).status, LoanHealth::Warning(8333));
+        assert_eq!(RioLoanTest::loan_index_keys(loan_id).1, Some((None, 44)));
+
+        // liquidated at 4705 from now on
+        assert_ok!(RioLoanTest::apply_param_change(
+            RiskParam::GlobalLiquidationThreshold(8500)
+        ));
+        assert_eq!(RioLoanTest::check_queue(), vec![loan_id]);
+        next_block();
+        assert_eq!(RioLoanTest::loan_index_keys(loan_id).1, Some((None, 47)));
+
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4600_0000));
+        next_block();
+        assert_eq!(
+            RioLoanTest::get_loan_by_id(loan_id).status,
+            LoanHealth::Liquidating(8695)
+        );
+
+        // the same for the parameters of an asset
+        assert_ok!(RioLoanTest::set_collateral_params(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            None
+        ));
+        assert_eq!(RioLoanTest::check_queue(), vec![loan_id]);
+    });
+}
+
+#[test]
+fn recovered_loans_are_well() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(Origin::ROOT, 10, 100, 1));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 4800_0000));
+        next_block();
+        assert_eq!(RioLoanTest::get_loan_by_id(loan_id).status, LoanHealth::Warning(8333));
+        // watched every hour
+        assert_eq!(RioLoanTest::loan_index_keys(loan_id).0, Some(WATCH_HOURS));
+
+        // the price going up doesn't queue it
+        assert_ok!(RioLoanTest::set_price(Origin::ROOT, 8000_0000));
+        assert!(RioLoanTest::check_queue().is_empty());
+
+        <timestamp::Module<TestRuntime>>::set_timestamp(2 * DUE_INDEX_UNIT);
+        next_block();
+        assert_eq!(RioLoanTest::get_loan_by_id(loan_id).status, LoanHealth::Well);
+        // not watched any more
+        assert!(RioLoanTest::loan_index_keys(loan_id).0 > Some(2 + WATCH_HOURS));
+    });
+}
+
+#[test]
+fn param_changes_are_timelocked() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioLoanTest::schedule_param_change(
+                Origin::ROOT,
+                RiskParam::GlobalWarningThreshold(9500)
+            ),
+            Error::<TestRuntime>::InvalidThresholds
+        );
+        assert_noop!(
+            RioLoanTest::schedule_param_change(Origin::ROOT, RiskParam::PenaltyRate(10001)),
+            Error::<TestRuntime>::InvalidRate
+        );
+        // checked with the pending change before it
+        assert_ok!(RioLoanTest::schedule_param_change(
+            Origin::ROOT,
+            RiskParam::GlobalLiquidationThreshold(9600)
+        ));
+        assert_ok!(RioLoanTest::schedule_param_change(
+            Origin::ROOT,
+            RiskParam::GlobalWarningThreshold(9500)
+        ));
+        let cap_change = RioLoanTest::next_param_change_id();
+        assert_ok!(RioLoanTest::schedule_param_change(
+            Origin::ROOT,
+            RiskParam::LoanCap(Some(1000_00000000))
+        ));
+        assert_ok!(RioLoanTest::cancel_param_change(Origin::ROOT, cap_change));
+        assert_noop!(
+            RioLoanTest::cancel_param_change(Origin::ROOT, cap_change),
+            Error::<TestRuntime>::InvalidParamChangeId
+        );
+        assert_eq!(RioLoanTest::pending_param_changes().len(), 2);
+        assert_eq!(RioLoanTest::pending_param_changes()[0].1.eta, 10);
+
+        for _ in 0..9 {
+            next_block();
+        }
+        assert_eq!(RioLoanTest::global_warning_threshold(), 8000);
+        assert_eq!(RioLoanTest::global_liquidation_threshold(), 9000);
+        next_block();
+        assert_eq!(RioLoanTest::global_warning_threshold(), 9500);
+        assert_eq!(RioLoanTest::global_liquidation_threshold(), 9600);
+        assert_eq!(RioLoanTest::loan_cap(), None);
+        assert!(RioLoanTest::pending_param_changes().is_empty());
+
+        // out of order once the change before it is cancelled
+        let liquidation_change = RioLoanTest::next_param_change_id();
+        assert_ok!(RioLoanTest::schedule_param_change(
+            Origin::ROOT,
+            RiskParam::GlobalLiquidationThreshold(9800)
+        ));
+        assert_ok!(RioLoanTest::schedule_param_change(
+            Origin::ROOT,
+            RiskParam::GlobalWarningThreshold(9700)
+        ));
+        assert_ok!(RioLoanTest::cancel_param_change(Origin::ROOT, liquidation_change));
+        for _ in 0..10 {
+            next_block();
+        }
+        assert_eq!(RioLoanTest::global_warning_threshold(), 9500);
+        assert!(RioLoanTest::pending_param_changes().is_empty());
+    });
+}
+
+#[test]
+fn stablecoin_debt_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(RioLoanTest::apply_param_change(RiskParam::LoanCap(Some(
+            10000_00000000
+        ))));
+        assert_ok!(RioLoanTest::set_interest_model(
+            Origin::ROOT,
+            Some(InterestRateModel {
+                base_rate: 0,
+                slope_low: 2000_0000,
+                slope_high: 10000_0000,
+                kink: 8000,
+                blocks_per_year: 100,
+            })
+        ));
+        assert_noop!(
+            RioLoanTest::set_stability_fee(Origin::ROOT, INTEREST_RATE_PREC),
+            Error::<TestRuntime>::InvalidInterestRate
+        );
+        assert_ok!(RioLoanTest::set_stability_fee(Origin::ROOT, 200_0000));
+        assert_ok!(RioLoanTest::set_stability_fund(Origin::ROOT, Some(TEAM), 5000));
+        assert_ok!(RioLoanTest::set_surplus_buffer_cap(Origin::ROOT, Some(1_00000000)));
+        assert_ok!(RioLoanTest::set_debt_ceiling(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            Some(5000_00000000)
+        ));
+        assert_eq!(
+            RioLoanTest::system_debt(),
+            SystemDebt {
+                debt_by_collateral: vec![(SBTC_ASSET_ID, 0, Some(5000_00000000))],
+                ..Default::default()
+            }
+        );
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            2_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        assert_eq!(RioLoanTest::collateral_debt(SBTC_ASSET_ID), 4000_00000000);
+        // the second loan would take the debt against SBTC to 8000
+        assert_noop!(
+            RioLoanTest::apply(Origin::signed(ALICE), 1_00000000, 4000_00000000, package_id),
+            Error::<TestRuntime>::OverDebtCeiling
+        );
+        assert_noop!(
+            RioLoanTest::draw(Origin::signed(ALICE), loan_id, 1000_00000001),
+            Error::<TestRuntime>::OverDebtCeiling
+        );
+        assert_eq!(
+            RioLoanTest::system_debt(),
+            SystemDebt {
+                total_debt: 4000_00000000,
+                collateral_value: 8000_00000000,
+                collateralization_ratio: Some(20000),
+                surplus: 0,
+                deficit: 0,
+                debt_by_collateral: vec![(SBTC_ASSET_ID, 4000_00000000, Some(5000_00000000))],
+            }
+        );
+
+        // 8% by the model and 2% of stability fee, 100 blocks a year
+        next_block();
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            ALICE,
+            4_00000000
+        ));
+        assert_ok!(RioLoanTest::repay(Origin::signed(ALICE), loan_id));
+        assert_eq!(RioLoanTest::total_profit(), 4_00000000);
+        // half of the interest goes to the fund, up to the cap
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &TEAM),
+            1_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(
+                &RIO_ASSET_ID,
+                &RioLoanTest::profit_pool()
+            ),
+            3_00000000
+        );
+        assert_eq!(
+            RioLoanTest::system_debt(),
+            SystemDebt {
+                surplus: 1_00000000,
+                debt_by_collateral: vec![(SBTC_ASSET_ID, 0, Some(5000_00000000))],
+                ..Default::default()
+            }
+        );
+    });
+}
+
+#[test]
+fn stability_fee_on_fixed_rate_loans() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(RioLoanTest::set_stability_fee(Origin::ROOT, 365_0000));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        <timestamp::Module<TestRuntime>>::set_timestamp(1000);
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        assert_eq!(RioLoanTest::loan_fee_index(loan_id), Some(BORROW_INDEX_PREC));
+
+        // 3.65% a year, for a day before the loan gets due
+        <timestamp::Module<TestRuntime>>::set_timestamp(1000 + YEAR_IN_MS / 365);
+        next_block();
+        let loan = RioLoanTest::get_loan_by_id(loan_id);
+        assert_eq!(RioLoanTest::loan_debt(&loan), 4000_40000000);
+
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RIO_ASSET_ID,
+            ALICE,
+            1_36000000
+        ));
+        assert_ok!(RioLoanTest::repay(Origin::signed(ALICE), loan_id));
+        assert_eq!(RioLoanTest::total_profit(), 1_36000000);
+        assert_eq!(RioLoanTest::loan_fee_index(loan_id), None);
+        assert_eq!(RioLoanTest::collateral_debt(SBTC_ASSET_ID), 0);
+    });
+}
+
+#[test]
+fn stability_fee_on_credit_lines_without_model() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioLoanTest::set_stability_fee(Origin::ROOT, 365_0000));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            RBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::set_credit_asset(
+            Origin::ROOT,
+            RBTC_ASSET_ID,
+            Some(CreditParams {
+                ltv: 5000,
+                price_feed: None
+            })
+        ));
+        <timestamp::Module<TestRuntime>>::set_timestamp(1000);
+        let line_id = RioLoanTest::next_credit_line_id();
+        assert_ok!(RioLoanTest::open_credit_line(
+            Origin::signed(ALICE),
+            RBTC_ASSET_ID,
+            1_00000000
+        ));
+        assert_ok!(RioLoanTest::draw_credit(
+            Origin::signed(ALICE),
+            line_id,
+            3000_00000000
+        ));
+        assert_eq!(RioLoanTest::credit_line_fee_index(line_id), BORROW_INDEX_PREC);
+
+        // 3.65% a year for a day, the borrow index doesn't grow without a model
+        <timestamp::Module<TestRuntime>>::set_timestamp(1000 + YEAR_IN_MS / 365);
+        next_block();
+        let line = RioLoanTest::credit_line(line_id).unwrap();
+        assert_eq!(
+            RioLoanTest::line_debt_and_credit(&line),
+            Some((3000_30000000, 4000_00000000))
+        );
+        assert_eq!(RioLoanTest::borrow_index(), BORROW_INDEX_PREC);
+
+        // settled when drawing more
+        assert_ok!(RioLoanTest::draw_credit(Origin::signed(ALICE), line_id, 1));
+        assert_eq!(RioLoanTest::credit_line(line_id).unwrap().drawn, 3000_30000001);
+        assert_eq!(RioLoanTest::total_profit(), 30000000);
+        assert_eq!(RioLoanTest::collateral_debt(RBTC_ASSET_ID), 3000_30000001);
+    });
+}
+
+#[test]
+fn migration_books_running_loans() {
+    ExtBuilder::default().build().execute_with(|| {
+        let package_id = RioLoanTest::next_loan_package_id();
+        assert_ok!(RioLoanTest::create_package(
+            system::RawOrigin::Root.into(),
+            10,
+            100,
+            1
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            system::RawOrigin::Root.into(),
+            SBTC_ASSET_ID,
+            ALICE,
+            1_00000000
+        ));
+        let loan_id = RioLoanTest::next_loan_id();
+        assert_ok!(RioLoanTest::apply(
+            Origin::signed(ALICE),
+            1_00000000,
+            4000_00000000,
+            package_id
+        ));
+        // as if the loan was created before the bookings and the indexes
+        CollateralDebt::<TestRuntime>::remove(SBTC_ASSET_ID);
+        LoanFeeIndex::remove(loan_id);
+        RioLoanTest::unindex_loan(loan_id);
+        assert_eq!(RioLoanTest::loan_index_keys(loan_id), (None, None));
+
+        RioLoanTest::migrate();
+        assert_eq!(RioLoanTest::collateral_debt(SBTC_ASSET_ID), 4000_00000000);
+        assert_eq!(RioLoanTest::loan_fee_index(loan_id), Some(BORROW_INDEX_PREC));
+        assert!(RioLoanTest::loan_index_keys(loan_id).0.is_some());
+        assert_eq!(RioLoanTest::storage_version(), 2);
+
+        // done only once
+        CollateralDebt::<TestRuntime>::remove(SBTC_ASSET_ID);
+        RioLoanTest::migrate();
+        assert_eq!(RioLoanTest::collateral_debt(SBTC_ASSET_ID), 0);
+    });
+}
+
+#[test]
+fn add_collateral_works() {}
+
+fn next_block() {
+    SystemTest::set_block_number(SystemTest::block_number() + 1);
+    RioLoanTest::on_initialize(SystemTest::block_number());
+}
+
+#[test]
+fn expiration_penalty() {
+    let loan: Loan<u64, u128, u64> = Loan {
+        id: 1,
+        package_id: 1,
+        who: 1,
+        due: 1,
+        due_extend: 1,
+        collateral_balance_original: 100000000,
+        collateral_balance_available: 100000000,
+        loan_balance_total: 520000000000,
+        status: LoanHealth::Well,
+    };
+
+    assert_eq!(
+        Ok(1300000 as u128),
+        loan.expiration_penalty(200 as u32, 80000000 as u128)
+    );
+    assert_eq!(
+        Err(MathError::DivisionByZero),
+        loan.expiration_penalty(200 as u32, 0)
+    );
+    assert_eq!(
+        Err(MathError::DivisionByZero),
+        Loan::<u64, u128, u64>::get_ltv(0, 520000000000, 80000000)
+    );
+    assert_eq!(Ok(6500), Loan::<u64, u128, u64>::get_ltv(100000000, 520000000000, 80000000));
+    // u128::max_value() * 10000 * 200 used to overflow before being divided
+    let loan = Loan {
+        loan_balance_total: u128::max_value() / 1000,
+        ..loan
+    };
     assert_eq!(
-        1300000 as u128,
+        Ok(u128::max_value() / 1000 / 400000),
         loan.expiration_penalty(200 as u32, 80000000 as u128)
     );
 }
@@ -10,6 +10,8 @@ use support::{
 };
 
 pub type Price = u128;
+/// feeds of prices other than BTC, e.g. one for each collateral asset
+pub type FeedId = u32;
 
 pub trait Trait: system::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
@@ -31,6 +33,7 @@ pub const PRICE_PRECISION: u32 = 10000;
 
 pub trait OnChange {
     fn on_change(price: Price) {}
+    fn on_feed_change(_feed: FeedId, _price: Price) {}
 }
 impl OnChange for () {}
 
@@ -38,6 +41,11 @@ decl_storage! {
     trait Store for Module<T: Trait> as RioPriceStorate {
         CurrentPrice get(current_price): Price;
         PriceReports get(price_reports): Vec<PriceReport<T::AccountId>>;
+        /// feeds which have ever been reported
+        Feeds get(feeds): Vec<FeedId>;
+        /// the median of the reports of each feed, read by the modules which add assets priced by the feed
+        pub FeedPrice get(feed_price): map FeedId => Price;
+        FeedReports get(feed_reports): map FeedId => Vec<PriceReport<T::AccountId>>;
     }
 }
 
@@ -52,6 +60,13 @@ decl_module! {
             Ok(())
         }
 
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn report_feed(origin, feed: FeedId, price: Price) -> Result {
+            let who = T::ReportOrigin::ensure_origin(origin)?;
+            Self::add_feed_price(who, feed, price);
+            Ok(())
+        }
+
         fn on_finalize() {
             let old_price = Self::current_price();
             let mut prices: Vec<Price> = Self::price_reports().iter().map(|x| x.price).collect();
@@ -72,6 +87,10 @@ decl_module! {
                 .collect();
 
             <PriceReports<T>>::put(reports);
+
+            for feed in Self::feeds() {
+                Self::update_feed(feed);
+            }
         }
     }
 }
@@ -109,6 +128,43 @@ impl<T: Trait> Module<T> {
     }
 }
 
+impl<T: Trait> Module<T> {
+    fn add_feed_price(who: T::AccountId, feed: FeedId, price: Price) {
+        if !Self::feeds().contains(&feed) {
+            Feeds::mutate(|v| v.push(feed));
+        }
+        <FeedReports<T>>::mutate(feed, |reports| {
+            match reports.iter_mut().find(|x| x.reporter == who) {
+                Some(report) => report.price = price,
+                None => reports.push(PriceReport {
+                    reporter: who.clone(),
+                    price,
+                }),
+            }
+        });
+
+        T::OracleMixedIn::on_witnessed(&who);
+        Self::deposit_event(RawEvent::FeedPriceReported(who, feed, price));
+    }
+
+    /// same as BTC price, take the median of the reports
+    fn update_feed(feed: FeedId) {
+        let mut prices: Vec<Price> = Self::feed_reports(feed).iter().map(|x| x.price).collect();
+        if prices.len() > 0 {
+            let median_price = median(&mut prices);
+            if Self::feed_price(feed) != median_price {
+                FeedPrice::insert(feed, median_price);
+                T::OnChange::on_feed_change(feed, median_price);
+                Self::deposit_event(RawEvent::FeedPriceChanged(feed, median_price));
+            }
+        }
+
+        <FeedReports<T>>::mutate(feed, |reports| {
+            reports.retain(|x| T::OracleMixedIn::is_valid(&x.reporter))
+        });
+    }
+}
+
 fn mean(numbers: &Vec<Price>) -> Price {
     let sum: Price = numbers.iter().sum();
     sum as Price / numbers.len() as Price
@@ -136,5 +192,7 @@ decl_event!(
     {
         PriceReported(AccountId, Price),
         PriceChanged(Price),
+        FeedPriceReported(AccountId, FeedId, Price),
+        FeedPriceChanged(FeedId, Price),
     }
 );
@@ -0,0 +1,269 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[allow(unused_imports)]
+use codec::{Decode, Encode};
+use rstd::prelude::*;
+#[allow(unused_imports)]
+use sp_runtime::traits::{Saturating, Zero};
+use support::{
+    decl_error, decl_event, decl_module, decl_storage, dispatch::DispatchResult, ensure,
+    weights::SimpleDispatchInfo,
+};
+use system::{ensure_root, ensure_signed};
+
+use rio_support::breaker::{TripBreakers, TripReason};
+
+mod mock;
+mod tests;
+
+/// height of the BTC block the custody balance is attested at
+pub type BtcHeight = u32;
+
+/// custody balance compared against the liabilities on chain
+/// all balances are in terms of SBTC, which is 1:1 BTC
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ReserveReport<Balance, BlockNumber> {
+    pub btc_height: BtcHeight,
+    /// block on RIO when the quorum is reached
+    pub at: BlockNumber,
+    /// the lowest custody balance among the attestations
+    pub custody: Balance,
+    /// SBTC total issuance, this is what the custody should cover
+    pub sbtc_issuance: Balance,
+    /// SBTC held by the saving collection account
+    pub saving_collected: Balance,
+    /// SBTC held by the bridge waiting to be withdrawn
+    pub pending_withdraw: Balance,
+    /// saving shares backed by the collection account
+    pub shares_circulation: Balance,
+    /// SBTC issuance not covered by the custody
+    pub deficit: Balance,
+}
+
+pub trait Trait: rio_saving::Trait + rio_bridge::Trait {
+    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// modules whose breakers are tripped on a deficit, e.g. (RioSaving, RioBridge, RioLoan)
+    type Breakers: TripBreakers;
+}
+
+decl_storage! {
+    trait Store for Module<T: Trait> as RioReserve {
+        /// accounts which are authorized to attest the custody balance
+        Attesters get(attesters) config() : Vec<T::AccountId>;
+        /// number of attestations needed for a height to be checked, never less than a majority of the attesters
+        Quorum get(quorum) config() : u32;
+        /// a deficit above this would trip the breakers of T::Breakers if AutoPause is on
+        DeficitThreshold get(deficit_threshold) config() : T::Balance;
+        /// whether to trip the breakers on a deficit, the other functions and modules keep running
+        AutoPause get(auto_pause) config() : bool;
+        /// attestations of heights which haven't reached the quorum yet, pruned once a higher height is checked
+        Attestations get(attestations) : linked_map BtcHeight => Vec<(T::AccountId, T::Balance)>;
+        /// heights at or below it are no longer accepted
+        LastCheckedHeight get(last_checked_height) : BtcHeight;
+        LastReport get(last_report) : Option<ReserveReport<T::Balance, T::BlockNumber>>;
+    }
+}
+
+decl_module! {
+    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
+        fn deposit_event() = default;
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn add_attester(origin, who: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(!Self::is_attester(&who), Error::<T>::AlreadyAttester);
+            <Attesters<T>>::mutate(|v| v.push(who));
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn remove_attester(origin, who: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::is_attester(&who), Error::<T>::NotAttester);
+            ensure!((Self::quorum() as usize) < Self::attesters().len(), Error::<T>::QuorumUnreachable);
+            <Attesters<T>>::mutate(|v| v.retain(|a| *a != who));
+            // the votes of the attester no longer count
+            let attested = <Attestations<T>>::enumerate()
+                .filter(|(_, v)| v.iter().any(|(a, _)| *a == who))
+                .map(|(height, _)| height)
+                .collect::<Vec<_>>();
+            for height in attested {
+                let mut attestations = Self::attestations(height);
+                attestations.retain(|(a, _)| *a != who);
+                if attestations.is_empty() {
+                    <Attestations<T>>::remove(height);
+                } else {
+                    <Attestations<T>>::insert(height, attestations);
+                }
+            }
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_quorum(origin, quorum: u32) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(quorum > 0, Error::<T>::ZeroQuorum);
+            let attesters = Self::attesters().len() as u32;
+            ensure!(quorum <= attesters, Error::<T>::QuorumUnreachable);
+            ensure!(quorum * 2 > attesters, Error::<T>::QuorumNotMajority);
+            Quorum::put(quorum);
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_deficit_threshold(origin, threshold: T::Balance) -> DispatchResult {
+            ensure_root(origin)?;
+            <DeficitThreshold<T>>::put(threshold);
+            Ok(())
+        }
+
+        #[weight = SimpleDispatchInfo::FreeOperational]
+        pub fn set_auto_pause(origin, auto_pause: bool) -> DispatchResult {
+            ensure_root(origin)?;
+            AutoPause::put(auto_pause);
+            Ok(())
+        }
+
+        /// custody is the BTC balance held by the custodians at btc_height
+        #[weight = SimpleDispatchInfo::FixedNormal(0)]
+        pub fn attest(origin, btc_height: BtcHeight, custody: T::Balance) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Self::is_attester(&who), Error::<T>::NotAttester);
+            ensure!(btc_height > Self::last_checked_height(), Error::<T>::HeightChecked);
+
+            let mut attestations = Self::attestations(btc_height);
+            ensure!(attestations.iter().all(|(a, _)| *a != who), Error::<T>::AlreadyAttested);
+            attestations.push((who.clone(), custody));
+
+            if (attestations.len() as u32) < Self::quorum_needed() {
+                <Attestations<T>>::insert(btc_height, attestations);
+                Self::deposit_event(RawEvent::Attested(who, btc_height, custody));
+                return Ok(());
+            }
+
+            // be conservative, take the lowest one
+            let lowest = attestations
+                .iter()
+                .map(|(_, c)| *c)
+                .min()
+                .unwrap_or_else(Zero::zero);
+            Self::check_reserve(btc_height, lowest);
+            Self::deposit_event(RawEvent::Attested(who, btc_height, custody));
+            // attestations of the lower heights can never be checked
+            let checked = <Attestations<T>>::enumerate()
+                .map(|(height, _)| height)
+                .filter(|height| *height <= btc_height)
+                .collect::<Vec<_>>();
+            for height in checked {
+                <Attestations<T>>::remove(height);
+            }
+            LastCheckedHeight::put(btc_height);
+            Ok(())
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    pub fn is_attester(who: &T::AccountId) -> bool {
+        Self::attesters().contains(who)
+    }
+
+    /// Immutable
+    /// the quorum, raised to a majority of the attesters if they have been added since it was set
+    pub fn quorum_needed() -> u32 {
+        rstd::cmp::max(Self::quorum(), Self::attesters().len() as u32 / 2 + 1)
+    }
+
+    /// Immutable
+    /// compare the custody with the liabilities at current block
+    pub fn make_report(
+        btc_height: BtcHeight,
+        custody: T::Balance,
+    ) -> ReserveReport<T::Balance, T::BlockNumber> {
+        let sbtc = <rio_bridge::Module<T>>::asset_id();
+        let sbtc_issuance = <rio_assets::Module<T>>::total_issuance(&sbtc);
+        let saving_collected = <rio_assets::Module<T>>::free_balance(
+            &sbtc,
+            &<rio_saving::Module<T>>::collection_account_id(),
+        );
+        let pending_withdraw = <rio_assets::Module<T>>::free_balance(
+            &sbtc,
+            &<rio_bridge::Module<T>>::pending_withdraw_vault(),
+        );
+
+        ReserveReport {
+            btc_height,
+            at: <system::Module<T>>::block_number(),
+            custody,
+            sbtc_issuance,
+            saving_collected,
+            pending_withdraw,
+            shares_circulation: <rio_saving::Module<T>>::shares_circulation(),
+            deficit: sbtc_issuance.saturating_sub(custody),
+        }
+    }
+
+    fn check_reserve(btc_height: BtcHeight, custody: T::Balance) {
+        let report = Self::make_report(btc_height, custody);
+
+        Self::deposit_event(RawEvent::ReserveChecked(
+            btc_height,
+            report.custody,
+            report.sbtc_issuance,
+        ));
+
+        if !report.deficit.is_zero() {
+            Self::deposit_event(RawEvent::ReserveDeficit(
+                btc_height,
+                report.custody,
+                report.sbtc_issuance,
+                report.deficit,
+            ));
+        }
+        if Self::auto_pause() && report.deficit > Self::deficit_threshold() {
+            T::Breakers::trip_breakers(TripReason::ReserveDeficit);
+            Self::deposit_event(RawEvent::BreakersTripped(btc_height, report.deficit));
+        }
+
+        <LastReport<T>>::put(report);
+    }
+}
+
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// account is already an attester
+        AlreadyAttester,
+        /// account is not an attester
+        NotAttester,
+        /// quorum is zero
+        ZeroQuorum,
+        /// quorum is more than the attesters
+        QuorumUnreachable,
+        /// quorum is not a majority of the attesters
+        QuorumNotMajority,
+        /// the btc height has been checked
+        HeightChecked,
+        /// attester has attested the height
+        AlreadyAttested,
+    }
+}
+
+decl_event!(
+    pub enum Event<T>
+    where
+        AccountId = <T as system::Trait>::AccountId,
+        Balance = <T as rio_assets::Trait>::Balance,
+    {
+        // (attester, BTC height, custody)
+        Attested(AccountId, BtcHeight, Balance),
+        // (BTC height, custody, SBTC issuance)
+        ReserveChecked(BtcHeight, Balance, Balance),
+        // (BTC height, custody, SBTC issuance, deficit)
+        ReserveDeficit(BtcHeight, Balance, Balance, Balance),
+        // breakers of T::Breakers are tripped due to the deficit
+        BreakersTripped(BtcHeight, Balance),
+    }
+);
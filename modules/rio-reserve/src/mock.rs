@@ -0,0 +1,198 @@
+#![cfg(test)]
+#![allow(dead_code)]
+
+use primitives::H256;
+use support::{impl_outer_origin, parameter_types};
+// The testing primitives are very useful for avoiding having to work with signatures
+// or public keys. `u64` is used as the `AccountId` and no `Signature`s are required.
+use crate::{GenesisConfig, Module, Trait};
+
+#[allow(unused_imports)]
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, IdentityLookup, OnFinalize, OnInitialize},
+    Perbill,
+};
+
+pub mod constants {
+    use super::TestRuntime;
+
+    pub const DECIMALS: u128 = 100000000; // satoshi
+
+    pub const ROOT: <TestRuntime as system::Trait>::AccountId = 1;
+    pub const ALICE: <TestRuntime as system::Trait>::AccountId = 2;
+    pub const BOB: <TestRuntime as system::Trait>::AccountId = 3;
+    pub const CHRIS: <TestRuntime as system::Trait>::AccountId = 4;
+    pub const DAVE: <TestRuntime as system::Trait>::AccountId = 5;
+
+    pub const COLLECTION_ACCOUNT_ID: <TestRuntime as system::Trait>::AccountId = 999;
+    pub const PENDING_WITHDRAW_VAULT: <TestRuntime as system::Trait>::AccountId = 998;
+
+    pub const SBTC_ASSET_ID: <TestRuntime as rio_assets::Trait>::AssetId = 7;
+}
+
+use self::constants::*;
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct TestRuntime;
+
+impl_outer_origin! {
+    pub enum Origin for TestRuntime {}
+}
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const MaximumBlockWeight: u32 = 1024;
+    pub const MaximumBlockLength: u32 = 2 * 1024;
+    pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+impl system::Trait for TestRuntime {
+    type Origin = Origin;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Call = ();
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = ();
+    type BlockHashCount = BlockHashCount;
+    type MaximumBlockWeight = MaximumBlockWeight;
+    type MaximumBlockLength = MaximumBlockLength;
+    type AvailableBlockRatio = AvailableBlockRatio;
+    type Version = ();
+}
+parameter_types! {
+    pub const MinimumPeriod: u64 = 1000;
+}
+impl timestamp::Trait for TestRuntime {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+}
+impl sudo::Trait for TestRuntime {
+    type Event = ();
+    type Proposal = sudo::Call<TestRuntime>;
+}
+
+pub struct KeyProvider;
+impl rio_assets::traits::RootKeyProvider for KeyProvider {
+    type AccountId = u64;
+
+    fn root_key() -> Self::AccountId {
+        ROOT
+    }
+}
+impl rio_assets::Trait for TestRuntime {
+    type Event = ();
+    type Balance = u128;
+    type AssetId = u32;
+    type RootKey = KeyProvider;
+    type BeforeAssetTransfer = ();
+    type BeforeAssetCreate = ();
+    type BeforeAssetMint = ();
+    type BeforeAssetBurn = ();
+    type OnAssetTransfer = ();
+    type OnAssetCreate = ();
+    type OnAssetMint = ();
+    type OnAssetBurn = ();
+}
+
+pub struct NoOracle;
+impl rio_oracle::OracleMixedIn<TestRuntime> for NoOracle {
+    fn on_witnessed(_who: &u64) {}
+    fn is_valid(_who: &u64) -> bool {
+        true
+    }
+}
+impl rio_price::Trait for TestRuntime {
+    type Event = ();
+    type OracleMixedIn = NoOracle;
+    type ReportOrigin = system::EnsureSigned<u64>;
+    type OnChange = ();
+}
+parameter_types! {
+    pub const ParamChangeDelay: u64 = 10;
+}
+impl rio_saving::Trait for TestRuntime {
+    type Event = ();
+    type ParamChangeDelay = ParamChangeDelay;
+}
+impl rio_bridge::Trait for TestRuntime {
+    type Event = ();
+    type ParamChangeDelay = ParamChangeDelay;
+}
+impl Trait for TestRuntime {
+    type Event = ();
+    type Breakers = (RioSavingTest, RioBridgeTest);
+}
+
+pub type RioReserveTest = Module<TestRuntime>;
+pub type RioSavingTest = rio_saving::Module<TestRuntime>;
+pub type RioBridgeTest = rio_bridge::Module<TestRuntime>;
+pub type RioAssetsTest = rio_assets::Module<TestRuntime>;
+
+pub struct ExtBuilder {}
+
+impl Default for ExtBuilder {
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+impl ExtBuilder {
+    pub fn build(self) -> runtime_io::TestExternalities {
+        new_test_ext()
+    }
+}
+
+// This function basically just builds a genesis storage key/value store according to
+// our desired mockup.
+pub fn new_test_ext() -> runtime_io::TestExternalities {
+    let mut t = system::GenesisConfig::default()
+        .build_storage::<TestRuntime>()
+        .unwrap();
+
+    rio_assets::GenesisConfig::<TestRuntime> {
+        symbols: vec![(SBTC_ASSET_ID, "SBTC".as_bytes().to_vec(), vec![], vec![])],
+        root: ROOT,
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    rio_bridge::GenesisConfig::<TestRuntime> {
+        threshold: 30_0000_0000,
+        asset_id: SBTC_ASSET_ID,
+        admins: vec![(ROOT, rio_bridge::Auth::All)],
+        pending_withdraw_vault: PENDING_WITHDRAW_VAULT,
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    rio_saving::GenesisConfig::<TestRuntime> {
+        current_phase_id: 1,
+        phase_infos: vec![],
+        collection_account_id: COLLECTION_ACCOUNT_ID,
+        collection_asset_id: SBTC_ASSET_ID,
+        share_asset_id: 0,
+        team_account_id: 0,
+        profit_asset_id: 0,
+        profit_pool: 0,
+        reserved_mint_wallet: 0,
+        reserved_mint_asset_id: 0,
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    GenesisConfig::<TestRuntime> {
+        attesters: vec![ALICE, BOB, CHRIS],
+        quorum: 2,
+        deficit_threshold: 1 * DECIMALS,
+        auto_pause: true,
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+
+    t.into()
+}
@@ -0,0 +1,200 @@
+#![cfg(test)]
+
+use super::*;
+use support::{assert_noop, assert_ok};
+
+use rio_bridge::BridgeFunction;
+use rio_saving::SavingFunction;
+
+use crate::mock::{
+    constants::*, ExtBuilder, Origin, RioAssetsTest, RioBridgeTest, RioReserveTest, RioSavingTest,
+};
+
+fn issue_sbtc(who: u64, amount: u128) {
+    assert_ok!(RioAssetsTest::mint(
+        system::RawOrigin::Root.into(),
+        SBTC_ASSET_ID,
+        who,
+        amount
+    ));
+}
+
+#[test]
+fn attest_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        issue_sbtc(DAVE, 8 * DECIMALS);
+        issue_sbtc(COLLECTION_ACCOUNT_ID, 2 * DECIMALS);
+
+        assert_noop!(
+            RioReserveTest::attest(Origin::signed(DAVE), 100, 10 * DECIMALS),
+            Error::<TestRuntime>::NotAttester
+        );
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(CHRIS),
+            99,
+            10 * DECIMALS
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(ALICE),
+            100,
+            10 * DECIMALS
+        ));
+        assert_noop!(
+            RioReserveTest::attest(Origin::signed(ALICE), 100, 10 * DECIMALS),
+            Error::<TestRuntime>::AlreadyAttested
+        );
+        // quorum is not reached yet
+        assert_eq!(RioReserveTest::last_report(), None);
+
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(BOB),
+            100,
+            11 * DECIMALS
+        ));
+        let report = RioReserveTest::last_report().unwrap();
+        assert_eq!(report.btc_height, 100);
+        assert_eq!(report.custody, 10 * DECIMALS);
+        assert_eq!(report.sbtc_issuance, 10 * DECIMALS);
+        assert_eq!(report.saving_collected, 2 * DECIMALS);
+        assert_eq!(report.deficit, 0);
+        assert_eq!(RioReserveTest::attestations(100).len(), 0);
+        // the lower height is pruned
+        assert_eq!(RioReserveTest::attestations(99).len(), 0);
+
+        assert_noop!(
+            RioReserveTest::attest(Origin::signed(CHRIS), 100, 10 * DECIMALS),
+            Error::<TestRuntime>::HeightChecked
+        );
+    });
+}
+
+#[test]
+fn deficit_under_threshold_does_not_pause() {
+    ExtBuilder::default().build().execute_with(|| {
+        issue_sbtc(DAVE, 10 * DECIMALS);
+
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(ALICE),
+            100,
+            9_50000000
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(BOB),
+            100,
+            10 * DECIMALS
+        ));
+        assert_eq!(RioReserveTest::last_report().unwrap().deficit, 5000_0000);
+        assert!(RioSavingTest::breakers().is_empty());
+        assert!(RioBridgeTest::breakers().is_empty());
+    });
+}
+
+#[test]
+fn deficit_auto_pause() {
+    ExtBuilder::default().build().execute_with(|| {
+        issue_sbtc(DAVE, 10 * DECIMALS);
+
+        assert_ok!(RioReserveTest::set_auto_pause(
+            system::RawOrigin::Root.into(),
+            false
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(ALICE),
+            100,
+            5 * DECIMALS
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(BOB),
+            100,
+            5 * DECIMALS
+        ));
+        assert_eq!(RioReserveTest::last_report().unwrap().deficit, 5 * DECIMALS);
+        assert!(RioSavingTest::breakers().is_empty());
+
+        assert_ok!(RioReserveTest::set_auto_pause(
+            system::RawOrigin::Root.into(),
+            true
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(ALICE),
+            101,
+            5 * DECIMALS
+        ));
+        assert_ok!(RioReserveTest::attest(
+            Origin::signed(CHRIS),
+            101,
+            5 * DECIMALS
+        ));
+        // only the breakers are tripped, not the whole modules
+        assert_eq!(RioSavingTest::paused(), false);
+        assert_eq!(RioBridgeTest::paused(), false);
+        assert!(RioSavingTest::is_tripped(SavingFunction::Staking));
+        assert!(RioSavingTest::is_tripped(SavingFunction::Redeem));
+        assert!(!RioSavingTest::is_tripped(SavingFunction::Bonus));
+        assert!(RioBridgeTest::is_tripped(BridgeFunction::Deposit));
+        assert!(RioBridgeTest::is_tripped(BridgeFunction::Withdraw));
+    });
+}
+
+#[test]
+fn manage_attesters() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioReserveTest::add_attester(Origin::signed(ALICE), DAVE),
+            "bad origin: expected to be a root origin"
+        );
+        assert_ok!(RioReserveTest::add_attester(
+            system::RawOrigin::Root.into(),
+            DAVE
+        ));
+        assert!(RioReserveTest::is_attester(&DAVE));
+        assert_ok!(RioReserveTest::remove_attester(
+            system::RawOrigin::Root.into(),
+            ALICE
+        ));
+        assert!(!RioReserveTest::is_attester(&ALICE));
+        assert_noop!(
+            RioReserveTest::set_quorum(system::RawOrigin::Root.into(), 0),
+            Error::<TestRuntime>::ZeroQuorum
+        );
+        assert_noop!(
+            RioReserveTest::set_quorum(system::RawOrigin::Root.into(), 4),
+            Error::<TestRuntime>::QuorumUnreachable
+        );
+        assert_noop!(
+            RioReserveTest::set_quorum(system::RawOrigin::Root.into(), 1),
+            Error::<TestRuntime>::QuorumNotMajority
+        );
+    });
+}
+
+#[test]
+fn quorum_stays_a_majority() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioReserveTest::add_attester(
+            system::RawOrigin::Root.into(),
+            DAVE
+        ));
+        // 2 out of 4 is not a majority
+        assert_eq!(RioReserveTest::quorum_needed(), 3);
+        assert_ok!(RioReserveTest::attest(Origin::signed(ALICE), 100, 10 * DECIMALS));
+        assert_ok!(RioReserveTest::attest(Origin::signed(BOB), 100, 10 * DECIMALS));
+        assert_eq!(RioReserveTest::last_report(), None);
+
+        // the votes of a removed attester are dropped
+        assert_ok!(RioReserveTest::remove_attester(
+            system::RawOrigin::Root.into(),
+            BOB
+        ));
+        assert_eq!(RioReserveTest::attestations(100), vec![(ALICE, 10 * DECIMALS)]);
+        assert_ok!(RioReserveTest::remove_attester(
+            system::RawOrigin::Root.into(),
+            DAVE
+        ));
+        // 2 attesters are left for the quorum of 2
+        assert_noop!(
+            RioReserveTest::remove_attester(system::RawOrigin::Root.into(), ALICE),
+            Error::<TestRuntime>::QuorumUnreachable
+        );
+    });
+}
@@ -0,0 +1,104 @@
+//! Runtime API definition for the rio-saving module.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use rstd::prelude::*;
+use rstd::vec;
+use sp_runtime::RuntimeDebug;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+pub use rio_support::breaker::{Breaker, TripReason};
+
+/// keep in line with `rio_saving::PhaseId`
+pub type PhaseId = u32;
+
+/// a locked share package of an account, which is released at each phase change
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct UnreleasedShares<Balance, AssetId> {
+    pub phase_id: PhaseId,
+    pub asset_id: AssetId,
+    pub terms_left: u32,
+    pub total_balance: Balance,
+}
+
+/// how much of an IOU asset the account holds and how much of it can be redeemed right now
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct RedeemableIou<Balance, AssetId> {
+    pub phase_id: PhaseId,
+    pub iou_asset_id: AssetId,
+    pub iou_balance: Balance,
+    /// the largest amount that passes `check_can_redeem`
+    pub redeemable: Balance,
+    /// share asset deducted from free balance when redeeming `redeemable`
+    pub free_share_required: Balance,
+    /// share asset deducted from the unreleased packs when redeeming `redeemable`
+    pub locked_share_required: Balance,
+}
+
+/// saving position of a single account
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SavingPosition<Balance, AssetId> {
+    /// tracked share asset used for bonus distribution
+    pub shares: Balance,
+    /// share asset transferred into the collection account in advance
+    pub share_asset_collected: Balance,
+    /// bonus the account would get if the bonus was dispatched at this block
+    pub pending_bonus: Balance,
+    pub unreleased: Vec<UnreleasedShares<Balance, AssetId>>,
+    pub redeemable: Vec<RedeemableIou<Balance, AssetId>>,
+}
+
+/// module level saving state
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct SavingState<Balance> {
+    pub current_phase_id: PhaseId,
+    pub num_of_phases_left: u32,
+    pub quota: Balance,
+    pub quota_used: Balance,
+    pub quota_remaining: Balance,
+    pub shares_circulation: Balance,
+    pub paused: bool,
+}
+
+/// functions of rio-saving which can be paused by their own circuit breaker
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum SavingFunction {
+    Staking,
+    Redeem,
+    Bonus,
+}
+impl SavingFunction {
+    pub fn all() -> Vec<SavingFunction> {
+        vec![
+            SavingFunction::Staking,
+            SavingFunction::Redeem,
+            SavingFunction::Bonus,
+        ]
+    }
+}
+
+sp_api::decl_runtime_apis! {
+    pub trait SavingApi<AccountId, Balance, AssetId, BlockNumber> where
+        AccountId: Codec,
+        Balance: Codec,
+        AssetId: Codec,
+        BlockNumber: Codec,
+    {
+        fn saving_position(who: AccountId) -> SavingPosition<Balance, AssetId>;
+        fn saving_state() -> SavingState<Balance>;
+        /// breakers which are still tripped
+        fn breakers() -> Vec<(SavingFunction, Breaker<BlockNumber>)>;
+    }
+}
@@ -0,0 +1,118 @@
+//! RPC interface for the rio-saving module.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use rio_saving_rpc_runtime_api::{
+    Breaker, SavingApi as SavingRuntimeApi, SavingFunction, SavingPosition, SavingState,
+};
+
+#[rpc]
+pub trait SavingApi<BlockHash, AccountId, Balance, AssetId, BlockNumber> {
+    /// shares, unreleased packs, pending bonus and redeemable IOU of an account
+    #[rpc(name = "riosaving_position")]
+    fn saving_position(
+        &self,
+        who: AccountId,
+        at: Option<BlockHash>,
+    ) -> Result<SavingPosition<Balance, AssetId>>;
+
+    /// current phase and its quota
+    #[rpc(name = "riosaving_state")]
+    fn saving_state(&self, at: Option<BlockHash>) -> Result<SavingState<Balance>>;
+
+    /// circuit breakers which are still tripped
+    #[rpc(name = "riosaving_breakers")]
+    fn breakers(
+        &self,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<(SavingFunction, Breaker<BlockNumber>)>>;
+}
+
+/// A struct that implements the [`SavingApi`].
+pub struct Saving<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Saving<C, B> {
+    /// Create new `Saving` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Saving {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+    /// The call to runtime failed.
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+fn runtime_error<E: std::fmt::Debug>(message: &'static str) -> impl FnOnce(E) -> RpcError {
+    move |e| RpcError {
+        code: ErrorCode::ServerError(Error::RuntimeError.into()),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, AccountId, Balance, AssetId, BlockNumber>
+    SavingApi<<Block as BlockT>::Hash, AccountId, Balance, AssetId, BlockNumber>
+    for Saving<C, Block>
+where
+    Block: BlockT,
+    C: Send + Sync + 'static + ProvideRuntimeApi + HeaderBackend<Block>,
+    C::Api: SavingRuntimeApi<Block, AccountId, Balance, AssetId, BlockNumber>,
+    AccountId: Codec,
+    Balance: Codec,
+    AssetId: Codec,
+    BlockNumber: Codec,
+{
+    fn saving_position(
+        &self,
+        who: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<SavingPosition<Balance, AssetId>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.saving_position(&at, who)
+            .map_err(runtime_error("Unable to query saving position."))
+    }
+
+    fn saving_state(&self, at: Option<<Block as BlockT>::Hash>) -> Result<SavingState<Balance>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.saving_state(&at)
+            .map_err(runtime_error("Unable to query saving state."))
+    }
+
+    fn breakers(
+        &self,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<(SavingFunction, Breaker<BlockNumber>)>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+        api.breakers(&at)
+            .map_err(runtime_error("Unable to query saving breakers."))
+    }
+}
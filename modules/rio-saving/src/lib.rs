@@ -13,13 +13,33 @@ use sp_runtime::traits::{
     One, Saturating, SimpleArithmetic, Zero,
 };
 use support::{
-    decl_event, decl_module, decl_storage,
-    dispatch::{Parameter, Result as DispatchResult},
+    decl_error, decl_event, decl_module, decl_storage,
+    dispatch::{DispatchError, DispatchResult, Parameter},
     ensure,
+    traits::Get,
     weights::SimpleDispatchInfo,
 };
 #[allow(unused_imports)]
-use system::{ensure_root, ensure_signed, Error};
+use system::{ensure_root, ensure_signed};
+
+use rio_support::breaker::{Breaker, Breakers, TripBreakers, TripReason};
+use rio_support::fixed::{balance_mul_div, to_u128, MathError, MathResult};
+
+/// `a + b` on a balance
+fn checked_add<B: CheckedAdd>(a: B, b: B) -> MathResult<B> {
+    a.checked_add(&b).ok_or(MathError::Overflow)
+}
+
+/// `a * b` on a balance
+fn checked_mul<B: CheckedMul>(a: B, b: B) -> MathResult<B> {
+    a.checked_mul(&b).ok_or(MathError::Overflow)
+}
+pub use rio_support::timelock::{ParamChange, ParamChangeId};
+use rio_support::timelock::Timelock;
+
+pub use rio_saving_rpc_runtime_api::{
+    RedeemableIou, SavingFunction, SavingPosition, SavingState, UnreleasedShares,
+};
 
 mod mock;
 mod tests;
@@ -28,8 +48,22 @@ const DAY_IN_MILLI: u32 = 86400_000;
 const RESERVED_MINT_RATIO: u32 = 6500;
 const RESERVED_MINT_DIV: u32 = 10000;
 
+/// precision of the bonus weight of lock-up tiers, 100 means 1x
+pub const BONUS_WEIGHT_PREC: u32 = 100;
+/// precision of the early redemption penalty rate
+pub const PENALTY_RATE_PREC: u32 = 10000;
+
 pub type PhaseId = u32;
 
+type SavingBreakers<T> =
+    Breakers<FunctionBreakers<T>, SavingFunction, <T as system::Trait>::BlockNumber>;
+type SavingTimelock<T> = Timelock<
+    PendingParamChanges<T>,
+    NextParamChangeId,
+    SavingParam<<T as system::Trait>::AccountId, <T as rio_assets::Trait>::AssetId>,
+    <T as system::Trait>::BlockNumber,
+>;
+
 /// structure for phase defination
 #[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -51,6 +85,68 @@ pub struct IOU<AccountId, Balance, AssetId> {
     pub owner: AccountId,
 }
 
+/// lock-up tier chosen when staking
+/// a tiered saving gets more weight in bonus distribution until it matures,
+/// and redeeming it before maturity will be charged a penalty
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum LockTier {
+    Flexible,
+    Days30,
+    Days90,
+    Days180,
+}
+impl Default for LockTier {
+    fn default() -> Self {
+        LockTier::Flexible
+    }
+}
+impl LockTier {
+    pub fn lock_days(&self) -> u32 {
+        match self {
+            LockTier::Flexible => 0,
+            LockTier::Days30 => 30,
+            LockTier::Days90 => 90,
+            LockTier::Days180 => 180,
+        }
+    }
+
+    /// used when no weight has been set for the tier, in terms of BONUS_WEIGHT_PREC
+    pub fn default_bonus_weight(&self) -> u32 {
+        match self {
+            LockTier::Flexible => 100,
+            LockTier::Days30 => 110,
+            LockTier::Days90 => 130,
+            LockTier::Days180 => 150,
+        }
+    }
+}
+
+/// a tiered saving, `amount` is in terms of the IOU asset of the phase it was made in
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LockedPosition<Balance, AssetId, Moment> {
+    pub iou_asset_id: AssetId,
+    pub tier: LockTier,
+    pub amount: Balance,
+    /// share asset the saving is exchanged for, released or not
+    pub shares: Balance,
+    pub maturity: Moment,
+}
+
+/// snapshot taken at emergency shutdown, all settlements are made against it
+/// claims are counted in terms of the share asset, an IOU asset counts as the share asset it was exchanged for
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ShutdownInfo<Balance, BlockNumber> {
+    pub at: BlockNumber,
+    pub shares_circulation: Balance,
+    /// shares in circulation, unreleased packs, shares collected in advance and outstanding IOU assets
+    pub total_claims: Balance,
+    /// balance of the collection asset in the collection account
+    pub collection_balance: Balance,
+}
+
 #[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub enum ReleaseTrigger {
@@ -241,10 +337,29 @@ where
     }
 }
 
+/// admin parameters, which only change through the timelocked queue
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum SavingParam<AccountId, AssetId> {
+    ShareAssetId(AssetId),
+    IouAssetId(PhaseId, AssetId),
+    CollectionAccount(AccountId),
+    CollectionAssetId(AssetId),
+    ProfitAssetId(AssetId),
+    ProfitPool(AccountId),
+    TeamAccount(AccountId),
+    /// in terms of BONUS_WEIGHT_PREC
+    TierBonusWeight(LockTier, u32),
+    /// in terms of PENALTY_RATE_PREC
+    EarlyRedeemPenaltyRate(u32),
+}
+
 pub trait Trait:
     system::Trait + rio_assets::Trait + sudo::Trait + timestamp::Trait + rio_price::Trait
 {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+    /// blocks a parameter change waits in the queue
+    type ParamChangeDelay: Get<Self::BlockNumber>;
 }
 
 decl_storage! {
@@ -331,6 +446,27 @@ decl_storage! {
 
         /// RIO asset id
         ReservedMintAssetId get(reserved_mint_asset_id) config() : T::AssetId;
+
+        /// tiered savings of each account which are not redeemed yet
+        LockedPositions get(locked_positions) : map T::AccountId => Vec<LockedPosition<T::Balance, T::AssetId, T::Moment>>;
+
+        /// bonus weight of each lock-up tier, in terms of BONUS_WEIGHT_PREC, falls back to `LockTier::default_bonus_weight`
+        TierBonusWeight get(tier_bonus_weight) : map LockTier => Option<u32>;
+
+        /// the cut of the redeemed collection asset when a tiered saving is redeemed before maturity, in terms of PENALTY_RATE_PREC
+        EarlyRedeemPenaltyRate get(early_redeem_penalty_rate) : u32;
+
+        /// set once the module is shut down, no staking or redeemal is allowed after that but settlement
+        Shutdown get(shutdown) : Option<ShutdownInfo<T::Balance, T::BlockNumber>>;
+
+        /// circuit breakers of each function, a tripped function is paused while the others keep running
+        FunctionBreakers get(function_breaker) : map SavingFunction => Option<Breaker<T::BlockNumber>>;
+        /// tripped breakers resume by themselves after this many blocks if set
+        BreakerTimeout get(breaker_timeout) : Option<T::BlockNumber>;
+
+        /// admin parameter changes waiting for their block
+        pub PendingParamChanges get(pending_param_change) : linked_map ParamChangeId => Option<ParamChange<SavingParam<T::AccountId, T::AssetId>, T::BlockNumber>>;
+        NextParamChangeId get(next_param_change_id) : ParamChangeId;
     }
 
     add_extra_genesis {
@@ -342,10 +478,15 @@ decl_storage! {
 
 decl_module! {
     pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+        type Error = Error<T>;
+
         fn deposit_event() = default;
 
-        fn on_initialize(_height: T::BlockNumber) {
-            if !Self::paused() {
+        const ParamChangeDelay: T::BlockNumber = T::ParamChangeDelay::get();
+
+        fn on_initialize(height: T::BlockNumber) {
+            Self::apply_due_param_changes(height);
+            if !Self::paused() && !Self::is_tripped(SavingFunction::Bonus) {
                 if Self::check_bonus_time() {
                     Self::dispatch_bonus();
                 }
@@ -366,114 +507,108 @@ decl_module! {
             Ok(())
         }
 
-        #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_share_asset_id(origin, asset_id: T::AssetId) -> DispatchResult {
-            ensure_root(origin)?;
-            ensure!(<rio_assets::Module<T>>::asset_exists(&asset_id), "invalid asset id for rio saving share asset");
-            <ShareAssetId<T>>::put(asset_id);
-            Ok(())
-        }
-
-        // *** Caution
-        // set_current_phase_id may break all saving schedule
-        // ***
-        // pub fn set_current_phase_id(origin, new_phase_id: PhaseId) -> DispatchResult {
-        //     let _from = ensure_root(origin)?;
-        //     ensure!(<PhaseInfos<T>>::exists(new_phase_id), "Invalid phase id for Rio Saving");
-        //     let old_phase_id = Self::get_current_phase_id();
-        //     CurrentPhaseId::put(new_phase_id);
-        //     Self::deposit_event(RawEvent::PhaseChanged(old_phase_id, new_phase_id));
-        //     Ok(())
-        // }
-
-        #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_iou_asset_id_for_phase(origin, phase_id: PhaseId, asset_id: T::AssetId) -> DispatchResult {
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn trip_breaker(origin, function: SavingFunction) -> DispatchResult {
             ensure_root(origin)?;
-            ensure!(<PhaseInfos<T>>::exists(&phase_id), "invalid phase id for rio saving");
-            ensure!(<rio_assets::Module<T>>::asset_exists(&asset_id), "invalid iou asset id for rio saving");
-            if <IOUAssetPhaseId<T>>::exists(&asset_id) {
-                <IOUAssetPhaseId<T>>::remove(&asset_id);
-            }
-            <PhaseInfos<T>>::mutate(phase_id, |pi| {
-                pi.iou_asset_id = Some(asset_id);
-            });
-            <IOUAssetPhaseId<T>>::insert(&asset_id, phase_id);
+            Self::trip(function, TripReason::Manual);
             Ok(())
         }
 
-        #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_collection_account(origin, account_id: T::AccountId) -> DispatchResult {
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn reset_breaker(origin, function: SavingFunction) -> DispatchResult {
             ensure_root(origin)?;
-            <CollectionAccountId<T>>::put(account_id.clone());
+            SavingBreakers::<T>::reset(function);
+            Self::deposit_event(RawEvent::BreakerReset(function));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_collection_asset_id(origin, asset_id: T::AssetId) -> DispatchResult {
+        pub fn set_breaker_timeout(origin, timeout: Option<T::BlockNumber>) -> DispatchResult {
             ensure_root(origin)?;
-            ensure!(<rio_assets::Module<T>>::asset_exists(&asset_id), "invalid collection asset id");
-            <CollectionAssetId<T>>::put(asset_id);
+            match timeout {
+                Some(t) => <BreakerTimeout<T>>::put(t),
+                None => <BreakerTimeout<T>>::kill(),
+            }
             Ok(())
         }
 
+        /// the change applies after ParamChangeDelay blocks, pause, breakers and shutdown stay immediate
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_profit_asset_id(origin, asset_id: T::AssetId) -> DispatchResult {
+        pub fn schedule_param_change(origin, param: SavingParam<T::AccountId, T::AssetId>) -> DispatchResult {
             ensure_root(origin)?;
-            ensure!(<rio_assets::Module<T>>::asset_exists(&asset_id), "invalid collection asset id");
-            <ProfitAssetId<T>>::put(asset_id);
+            Self::check_param_change(&param)?;
+            let (id, eta) = SavingTimelock::<T>::schedule(
+                param,
+                <system::Module<T>>::block_number(),
+                T::ParamChangeDelay::get(),
+            );
+            Self::deposit_event(RawEvent::ParamChangeScheduled(id, eta));
             Ok(())
         }
 
         #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_profit_pool(origin, account_id: T::AccountId) -> DispatchResult {
+        pub fn cancel_param_change(origin, id: ParamChangeId) -> DispatchResult {
             ensure_root(origin)?;
-            <ProfitPool<T>>::put(account_id);
+            ensure!(SavingTimelock::<T>::cancel(id), Error::<T>::InvalidParamChangeId);
+            Self::deposit_event(RawEvent::ParamChangeCancelled(id));
             Ok(())
         }
 
-        #[weight = SimpleDispatchInfo::FreeOperational]
-        pub fn set_team_account_id(origin, account_id: T::AccountId) -> DispatchResult {
-            ensure_root(origin)?;
-            <TeamAccountId<T>>::put(account_id);
-            Ok(())
-        }
+        // *** Caution
+        // set_current_phase_id may break all saving schedule
+        // ***
+        // pub fn set_current_phase_id(origin, new_phase_id: PhaseId) -> DispatchResult {
+        //     let _from = ensure_root(origin)?;
+        //     ensure!(<PhaseInfos<T>>::exists(new_phase_id), "Invalid phase id for Rio Saving");
+        //     let old_phase_id = Self::get_current_phase_id();
+        //     CurrentPhaseId::put(new_phase_id);
+        //     Self::deposit_event(RawEvent::PhaseChanged(old_phase_id, new_phase_id));
+        //     Ok(())
+        // }
 
+        /// staking with `tier` other than `Flexible` locks the saving up until its maturity
         #[weight = SimpleDispatchInfo::FixedNormal(0)]
-        pub fn staking(origin, asset_id: T::AssetId, amount: T::Balance) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+        pub fn staking(origin, asset_id: T::AssetId, amount: T::Balance, tier: LockTier) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+            Self::ensure_not_tripped(SavingFunction::Staking)?;
             let who = ensure_signed(origin)?;
             let collection_account_id = Self::collection_account_id();
-            ensure!(<CollectionAssetId<T>>::get() == asset_id, "can't collect this asset");
-            ensure!(<rio_assets::Module<T>>::free_balance(&asset_id, &who) >= amount, "insufficient balance");
-            let staking_balance = Self::create_staking(who.clone(), amount)?;
+            ensure!(<CollectionAssetId<T>>::get() == asset_id, Error::<T>::InvalidCollectionAsset);
+            ensure!(<rio_assets::Module<T>>::free_balance(&asset_id, &who) >= amount, Error::<T>::InsufficientBalance);
+            let staking_balance = Self::create_staking_with_tier(who.clone(), amount, tier)?;
             <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &who, &collection_account_id, staking_balance)?;
             Self::create_reserved(Self::sbtc_to_reserved_mint(staking_balance))
         }
 
         #[weight = SimpleDispatchInfo::FixedNormal(0)]
-        pub fn sudo_staking(origin, asset_id: T::AssetId, amount: T::Balance, delegatee: T::AccountId) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+        pub fn sudo_staking(origin, asset_id: T::AssetId, amount: T::Balance, delegatee: T::AccountId, tier: LockTier) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+            Self::ensure_not_tripped(SavingFunction::Staking)?;
             ensure_root(origin)?;
             let collection_account_id = Self::collection_account_id();
-            ensure!(<CollectionAssetId<T>>::get() == asset_id, "can't collect this asset");
-            ensure!(<rio_assets::Module<T>>::free_balance(&asset_id, &delegatee) >= amount, "insufficient balance");
-            let staking_balance = Self::create_staking(delegatee.clone(), amount)?;
+            ensure!(<CollectionAssetId<T>>::get() == asset_id, Error::<T>::InvalidCollectionAsset);
+            ensure!(<rio_assets::Module<T>>::free_balance(&asset_id, &delegatee) >= amount, Error::<T>::InsufficientBalance);
+            let staking_balance = Self::create_staking_with_tier(delegatee.clone(), amount, tier)?;
             <rio_assets::Module<T>>::make_transfer_with_event(&asset_id, &delegatee, &collection_account_id, staking_balance)?;
             Self::create_reserved(Self::sbtc_to_reserved_mint(staking_balance))
         }
 
         #[weight = SimpleDispatchInfo::FixedNormal(0)]
         pub fn redeem(origin, iou_asset_id: T::AssetId, iou_asset_amount: T::Balance) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+            Self::ensure_not_tripped(SavingFunction::Redeem)?;
             let who = ensure_signed(origin)?;
             let share_asset_id = Self::share_asset_id();
             let collection_asset_id = Self::collection_asset_id();
             let collection_account_id = Self::collection_account_id();
-            ensure!(!share_asset_id.is_zero(), "fail to find share asset id");
-            ensure!(!collection_asset_id.is_zero(), "fail to find collection asset id");
-            ensure!(<IOUAssetPhaseId<T>>::exists(&iou_asset_id), "no such contract assets");
+            ensure!(!share_asset_id.is_zero(), Error::<T>::ShareAssetIdNotSet);
+            ensure!(!collection_asset_id.is_zero(), Error::<T>::CollectionAssetIdNotSet);
+            ensure!(<IOUAssetPhaseId<T>>::exists(&iou_asset_id), Error::<T>::UnknownIouAsset);
             let burn_reserved = Self::sbtc_to_reserved_mint(iou_asset_amount);
-            ensure!(<rio_assets::Module<T>>::free_balance(&Self::reserved_mint_asset_id(), &Self::reserved_mint_wallet()) >= burn_reserved, "reserved wallet is short");
+            ensure!(<rio_assets::Module<T>>::free_balance(&Self::reserved_mint_asset_id(), &Self::reserved_mint_wallet()) >= burn_reserved, Error::<T>::ReservedWalletShort);
             Self::check_can_redeem(iou_asset_id.clone(), who.clone(), iou_asset_amount)?;
             Self::burn_reserved(burn_reserved)?;
             <rio_assets::Module<T>>::make_transfer_with_event(&iou_asset_id, &who, &collection_account_id, iou_asset_amount)?;
@@ -493,14 +628,16 @@ decl_module! {
 
         #[weight = SimpleDispatchInfo::FixedNormal(0)]
         pub fn sudo_redeem(origin, iou_asset_id: T::AssetId, iou_asset_amount: T::Balance, delegatee: T::AccountId) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+            Self::ensure_not_tripped(SavingFunction::Redeem)?;
             ensure_root(origin)?;
             let share_asset_id = Self::share_asset_id();
             let collection_asset_id = Self::collection_asset_id();
             let collection_account_id = Self::collection_account_id();
-            ensure!(!share_asset_id.is_zero(), "fail to find share asset id");
-            ensure!(!collection_asset_id.is_zero(), "fail to find collection asset id");
-            ensure!(<IOUAssetPhaseId<T>>::exists(&iou_asset_id), "no such contract assets");
+            ensure!(!share_asset_id.is_zero(), Error::<T>::ShareAssetIdNotSet);
+            ensure!(!collection_asset_id.is_zero(), Error::<T>::CollectionAssetIdNotSet);
+            ensure!(<IOUAssetPhaseId<T>>::exists(&iou_asset_id), Error::<T>::UnknownIouAsset);
             Self::check_can_redeem(iou_asset_id.clone(), delegatee.clone(), iou_asset_amount)?;
             <rio_assets::Module<T>>::make_transfer_with_event(&iou_asset_id, &delegatee, &collection_account_id, iou_asset_amount)?;
             Self::make_redeem(
@@ -517,10 +654,36 @@ decl_module! {
             <rio_assets::Module<T>>::burn(system::RawOrigin::Root.into(), iou_asset_id.clone(), collection_account_id.clone(), iou_asset_amount)
         }
 
+        /// freeze the module for good and snapshot the claims against the collection account
+        #[weight = SimpleDispatchInfo::MaxNormal]
+        pub fn emergency_shutdown(origin) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+            let collection_balance = <rio_assets::Module<T>>::free_balance(&Self::collection_asset_id(), &Self::collection_account_id());
+            let info = ShutdownInfo {
+                at: <system::Module<T>>::block_number(),
+                shares_circulation: Self::shares_circulation(),
+                total_claims: Self::total_claims().map_err(|_| Error::<T>::Overflow)?,
+                collection_balance,
+            };
+            Self::deposit_event(RawEvent::Shutdown(info.shares_circulation, info.total_claims, info.collection_balance));
+            <Shutdown<T>>::put(info);
+            Ok(())
+        }
+
+        /// give up all share asset, unreleased packs and IOU assets for a pro-rata cut of the collection asset
+        #[weight = SimpleDispatchInfo::FixedNormal(0)]
+        pub fn settle(origin) -> DispatchResult {
+            ensure!(!Self::paused(), Error::<T>::Paused);
+            let who = ensure_signed(origin)?;
+            Self::make_settle(&who)
+        }
+
         #[weight = SimpleDispatchInfo::FreeOperational]
         pub fn force_release_bonus(origin) -> DispatchResult {
-            ensure!(!Self::paused(), "module is paused");
+            ensure!(!Self::paused(), Error::<T>::Paused);
             ensure_root(origin)?;
+            Self::ensure_not_tripped(SavingFunction::Bonus)?;
             Self::dispatch_bonus();
             Ok(())
         }
@@ -528,6 +691,76 @@ decl_module! {
 }
 
 impl<T: Trait> Module<T> {
+    /// Immutable
+    /// pending changes in the order they apply
+    pub fn pending_param_changes() -> Vec<(
+        ParamChangeId,
+        ParamChange<SavingParam<T::AccountId, T::AssetId>, T::BlockNumber>,
+    )> {
+        SavingTimelock::<T>::pending()
+    }
+
+    /// Immutable
+    fn check_param_change(param: &SavingParam<T::AccountId, T::AssetId>) -> DispatchResult {
+        let exists = |asset_id| <rio_assets::Module<T>>::asset_exists(asset_id);
+        match param {
+            SavingParam::ShareAssetId(asset_id) => {
+                ensure!(exists(asset_id), Error::<T>::InvalidShareAssetId)
+            }
+            SavingParam::IouAssetId(phase_id, asset_id) => {
+                ensure!(<PhaseInfos<T>>::exists(phase_id), Error::<T>::InvalidPhaseId);
+                ensure!(exists(asset_id), Error::<T>::InvalidIouAssetId);
+            }
+            SavingParam::CollectionAssetId(asset_id) | SavingParam::ProfitAssetId(asset_id) => {
+                ensure!(exists(asset_id), Error::<T>::InvalidCollectionAssetId)
+            }
+            SavingParam::TierBonusWeight(_, weight) => {
+                ensure!(*weight >= BONUS_WEIGHT_PREC, Error::<T>::BonusWeightTooLow)
+            }
+            SavingParam::EarlyRedeemPenaltyRate(rate) => {
+                ensure!(*rate < PENALTY_RATE_PREC, Error::<T>::InvalidPenaltyRate)
+            }
+            SavingParam::CollectionAccount(_)
+            | SavingParam::ProfitPool(_)
+            | SavingParam::TeamAccount(_) => {}
+        }
+        Ok(())
+    }
+
+    /// the change is checked again when it applies, e.g. the asset could be gone by then
+    pub fn apply_param_change(param: SavingParam<T::AccountId, T::AssetId>) -> DispatchResult {
+        Self::check_param_change(&param)?;
+        match param {
+            SavingParam::ShareAssetId(asset_id) => <ShareAssetId<T>>::put(asset_id),
+            SavingParam::IouAssetId(phase_id, asset_id) => {
+                if <IOUAssetPhaseId<T>>::exists(&asset_id) {
+                    <IOUAssetPhaseId<T>>::remove(&asset_id);
+                }
+                <PhaseInfos<T>>::mutate(phase_id, |pi| {
+                    pi.iou_asset_id = Some(asset_id);
+                });
+                <IOUAssetPhaseId<T>>::insert(&asset_id, phase_id);
+            }
+            SavingParam::CollectionAccount(account_id) => <CollectionAccountId<T>>::put(account_id),
+            SavingParam::CollectionAssetId(asset_id) => <CollectionAssetId<T>>::put(asset_id),
+            SavingParam::ProfitAssetId(asset_id) => <ProfitAssetId<T>>::put(asset_id),
+            SavingParam::ProfitPool(account_id) => <ProfitPool<T>>::put(account_id),
+            SavingParam::TeamAccount(account_id) => <TeamAccountId<T>>::put(account_id),
+            SavingParam::TierBonusWeight(tier, weight) => TierBonusWeight::insert(tier, weight),
+            SavingParam::EarlyRedeemPenaltyRate(rate) => EarlyRedeemPenaltyRate::put(rate),
+        }
+        Ok(())
+    }
+
+    fn apply_due_param_changes(now: T::BlockNumber) {
+        for (id, param) in SavingTimelock::<T>::take_due(now) {
+            match Self::apply_param_change(param) {
+                Ok(()) => Self::deposit_event(RawEvent::ParamChangeApplied(id)),
+                Err(_) => Self::deposit_event(RawEvent::ParamChangeRejected(id)),
+            }
+        }
+    }
+
     /// Immutable
     /// asset_id is the IOU asset that the transaction carries
     pub fn check_can_redeem(
@@ -551,7 +784,8 @@ impl<T: Trait> Module<T> {
                     required_share_asset,
                     num_of_phases,
                     num_of_phases_left,
-                );
+                )
+                .map_err(|_| Error::<T>::Overflow)?;
 
             // it's ok if potentially the redemption will deduct all from free balance
             if free_share_asset + returned_share_asset >= required_share_asset {
@@ -569,18 +803,268 @@ impl<T: Trait> Module<T> {
                 if free_share_asset_required + locked_share_asset_required
                     != unreleased + (free_share_asset + returned_share_asset)
                 {
-                    return Err("not enough available share asset");
+                    return Err(Error::<T>::NotEnoughShareAsset.into());
                 }
             }
 
             if required_share_asset > returned_share_asset + unreleased + free_share_asset {
-                return Err("exceed allowed redeem limits");
+                return Err(Error::<T>::ExceedRedeemLimit.into());
             }
         }
 
         Ok(())
     }
 
+    /// Immutable
+    /// the largest amount of `iou_asset_id` that `who` is able to redeem, which would pass `check_can_redeem`
+    pub fn max_redeemable(
+        iou_asset_id: T::AssetId,
+        who: &T::AccountId,
+        phase_info: &PhaseInfo<T::Balance, T::AssetId>,
+    ) -> T::Balance {
+        let iou_balance = <rio_assets::Module<T>>::free_balance(&iou_asset_id, who);
+        let share_asset_id = Self::share_asset_id();
+        if share_asset_id.is_zero() || iou_balance.is_zero() {
+            return iou_balance;
+        }
+        if phase_info.exchange.is_zero() {
+            return T::Balance::zero();
+        }
+
+        let returned_share_asset = <ShareAssetCollected<T>>::get(who);
+        let free_share_asset = <rio_assets::Module<T>>::free_balance(&share_asset_id, who);
+        let unreleased = <ShareUnreleasedList<T>>::get(who)
+            .iter()
+            .filter(|v| !v.empty && v.asset_id == share_asset_id && v.phase_id == phase_info.id)
+            .fold(T::Balance::zero(), |acc, v| acc.saturating_add(v.get_total_balance()));
+
+        let held = free_share_asset
+            .saturating_add(returned_share_asset)
+            .saturating_add(unreleased);
+        let upper = rstd::cmp::min(iou_balance, held / phase_info.exchange);
+        if Self::check_can_redeem(iou_asset_id, who.clone(), upper).is_ok() {
+            return upper;
+        }
+
+        // what can be covered by the free portion alone is always redeemable
+        rstd::cmp::min(
+            iou_balance,
+            free_share_asset.saturating_add(returned_share_asset) / phase_info.exchange,
+        )
+    }
+
+    /// Immutable
+    /// the cut of the profit asset in the profit pool that `who` would get if the bonus was dispatched at this block
+    pub fn pending_bonus(who: &T::AccountId) -> T::Balance {
+        let (weighted, total) = match Self::all_weighted_shares() {
+            Ok((weighted, total)) if !total.is_zero() => (weighted, total),
+            // nothing is dispatched either
+            _ => return T::Balance::zero(),
+        };
+        let total_profit =
+            <rio_assets::Module<T>>::free_balance(&Self::profit_asset_id(), &Self::profit_pool());
+        let (users, _) = Self::split_profit(total_profit);
+        weighted
+            .iter()
+            .find(|(user_id, _)| user_id == who)
+            .and_then(|(_, w)| Self::bonus_cut(*w, users, total).ok())
+            .unwrap_or_else(T::Balance::zero)
+    }
+
+    /// Immutable
+    /// backs `SavingApi::saving_position`
+    pub fn saving_position(who: T::AccountId) -> SavingPosition<T::Balance, T::AssetId> {
+        let num_of_phases = Self::num_of_phases();
+        let num_of_phases_left = Self::num_of_phases_left();
+
+        let unreleased = <ShareUnreleasedList<T>>::get(&who)
+            .iter()
+            .filter(|v| !v.empty)
+            .map(|v| UnreleasedShares {
+                phase_id: v.phase_id,
+                asset_id: v.asset_id,
+                terms_left: v.terms_left(),
+                total_balance: v.get_total_balance(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut redeemable = <PhaseInfos<T>>::enumerate()
+            .filter_map(|(phase_id, phase_info)| {
+                let iou_asset_id = phase_info.iou_asset_id?;
+                let iou_balance = <rio_assets::Module<T>>::free_balance(&iou_asset_id, &who);
+                if iou_balance.is_zero() {
+                    return None;
+                }
+                let amount = Self::max_redeemable(iou_asset_id, &who, &phase_info);
+                let required = checked_mul(amount, phase_info.exchange).ok()?;
+                let (free_share_required, locked_share_required) =
+                    Self::redeem_required_balances_in_ratio(
+                        phase_id,
+                        required,
+                        num_of_phases,
+                        num_of_phases_left,
+                    )
+                    .ok()?;
+                Some(RedeemableIou {
+                    phase_id,
+                    iou_asset_id,
+                    iou_balance,
+                    redeemable: amount,
+                    free_share_required,
+                    locked_share_required,
+                })
+            })
+            .collect::<Vec<_>>();
+        redeemable.sort_by_key(|v| v.phase_id);
+
+        SavingPosition {
+            shares: Self::account_shares(&who),
+            share_asset_collected: Self::share_asset_collected(&who),
+            pending_bonus: Self::pending_bonus(&who),
+            unreleased,
+            redeemable,
+        }
+    }
+
+    /// Immutable
+    /// backs `SavingApi::saving_state`
+    pub fn saving_state() -> SavingState<T::Balance> {
+        let current_phase_id = Self::current_phase_id();
+        let quota = Self::phase_info(current_phase_id).quota;
+        let quota_used = Self::used_quota();
+        SavingState {
+            current_phase_id,
+            num_of_phases_left: Self::num_of_phases_left(),
+            quota,
+            quota_used,
+            quota_remaining: quota.saturating_sub(quota_used),
+            shares_circulation: Self::shares_circulation(),
+            paused: Self::paused(),
+        }
+    }
+
+    /// Immutable
+    /// free and reserved balance, the reserved one is pledged to credit lines of rio-loan
+    fn held_balance(asset_id: &T::AssetId, who: &T::AccountId) -> T::Balance {
+        <rio_assets::Module<T>>::free_balance(asset_id, who)
+            .saturating_add(<rio_assets::Module<T>>::reserved_balance(asset_id, who))
+    }
+
+    /// Immutable
+    /// the claims of `who` in terms of the share asset
+    pub fn claims_of(who: &T::AccountId) -> MathResult<T::Balance> {
+        let share_asset_id = Self::share_asset_id();
+        let mut claims = checked_add(
+            Self::held_balance(&share_asset_id, who),
+            Self::share_asset_collected(who),
+        )?;
+        for v in <ShareUnreleasedList<T>>::get(who).iter() {
+            claims = checked_add(claims, v.get_total_balance())?;
+        }
+        for (_, info) in <PhaseInfos<T>>::enumerate() {
+            if let Some(id) = info.iou_asset_id {
+                let ious = checked_mul(Self::held_balance(&id, who), info.exchange)?;
+                claims = checked_add(claims, ious)?;
+            }
+        }
+        Ok(claims)
+    }
+
+    /// Immutable
+    /// the claims of all accounts in terms of the share asset
+    pub fn total_claims() -> MathResult<T::Balance> {
+        let collection_account_id = Self::collection_account_id();
+        let mut claims = Self::shares_circulation();
+        for v in <ShareUnreleasedList<T>>::enumerate().flat_map(|(_, list)| list.into_iter()) {
+            claims = checked_add(claims, v.get_total_balance())?;
+        }
+        for (_, collected) in <ShareAssetCollected<T>>::enumerate() {
+            claims = checked_add(claims, collected)?;
+        }
+        // IOU assets in the collection account are not burnt yet when redeemed by transfer
+        for (_, info) in <PhaseInfos<T>>::enumerate() {
+            if let Some(id) = info.iou_asset_id {
+                let outstanding = <rio_assets::Module<T>>::total_issuance(&id)
+                    .saturating_sub(<rio_assets::Module<T>>::free_balance(&id, &collection_account_id));
+                claims = checked_add(claims, checked_mul(outstanding, info.exchange)?)?;
+            }
+        }
+        Ok(claims)
+    }
+
+    /// burn everything `who` holds of this module and pay the pro-rata cut of the collection asset
+    fn make_settle(who: &T::AccountId) -> DispatchResult {
+        let info = Self::shutdown().ok_or(Error::<T>::NotShutDown)?;
+        let claims = Self::claims_of(who).map_err(|_| Error::<T>::Overflow)?;
+        ensure!(!claims.is_zero(), Error::<T>::NothingToSettle);
+        ensure!(!info.total_claims.is_zero(), Error::<T>::NothingToSettle);
+        let payout = to_u128(info.collection_balance)
+            .and_then(|collection_balance| {
+                balance_mul_div(claims, collection_balance, to_u128(info.total_claims)?)
+            })
+            .map_err(|_| Error::<T>::Overflow)?;
+
+        let share_asset_id = Self::share_asset_id();
+        let collection_asset_id = Self::collection_asset_id();
+        let collection_account_id = Self::collection_account_id();
+        let shares = Self::held_balance(&share_asset_id, who);
+        let collected = Self::share_asset_collected(who);
+        let ious = <PhaseInfos<T>>::enumerate()
+            .filter_map(|(_, info)| info.iou_asset_id)
+            .map(|id| (id, Self::held_balance(&id, who)))
+            .filter(|(_, ious)| !ious.is_zero())
+            .collect::<Vec<_>>();
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&share_asset_id, &collection_account_id) >= collected,
+            Error::<T>::NotEnoughShareAsset
+        );
+        ensure!(
+            <rio_assets::Module<T>>::free_balance(&collection_asset_id, &collection_account_id) >= payout,
+            Error::<T>::InsufficientBalance
+        );
+
+        // nothing is written above, and the burns below only take the balances checked above,
+        // pledges of credit lines are released and burnt as well
+        <rio_assets::Module<T>>::make_transfer_with_event(
+            &collection_asset_id,
+            &collection_account_id,
+            who,
+            payout,
+        )?;
+        if !shares.is_zero() {
+            <rio_assets::Module<T>>::unreserve(&share_asset_id, who, shares);
+            <rio_assets::Module<T>>::burn(
+                system::RawOrigin::Root.into(),
+                share_asset_id,
+                who.clone(),
+                shares,
+            )?;
+        }
+        if !collected.is_zero() {
+            <rio_assets::Module<T>>::burn(
+                system::RawOrigin::Root.into(),
+                share_asset_id,
+                collection_account_id.clone(),
+                collected,
+            )?;
+        }
+        for (iou_asset_id, ious) in ious {
+            <rio_assets::Module<T>>::unreserve(&iou_asset_id, who, ious);
+            <rio_assets::Module<T>>::burn(
+                system::RawOrigin::Root.into(),
+                iou_asset_id,
+                who.clone(),
+                ious,
+            )?;
+        }
+        <ShareAssetCollected<T>>::remove(who);
+        <ShareUnreleasedList<T>>::remove(who);
+        <LockedPositions<T>>::remove(who);
+
+        Self::deposit_event(RawEvent::Settled(who.clone(), claims, payout));
+        Ok(())
+    }
+
     /// make_redeem should only be called after check_can_redeem immediately
     /// asset_id is the IOU asset id which user has transferred to our collection account
     /// who is the user transferring the asset
@@ -593,6 +1077,19 @@ impl<T: Trait> Module<T> {
         amount: T::Balance,
         share_asset_id: &T::AssetId,
     ) -> DispatchResult {
+        Self::ensure_not_tripped(SavingFunction::Redeem)?;
+        // the IOU asset has been transferred into the collection account already
+        let iou_balance = <rio_assets::Module<T>>::free_balance(iou_asset_id, who) + amount;
+        let (early, positions_left) =
+            Self::redeem_locked_positions(who, iou_asset_id, amount, iou_balance)
+                .map_err(|_| Error::<T>::Overflow)?;
+        let penalty = balance_mul_div(
+            early,
+            u128::from(Self::early_redeem_penalty_rate()),
+            u128::from(PENALTY_RATE_PREC),
+        )
+        .map_err(|_| Error::<T>::Overflow)?;
+
         let phase_id = <IOUAssetPhaseId<T>>::get(iou_asset_id);
         let phase_info = Self::phase_info(phase_id);
         let returned_share_asset = <ShareAssetCollected<T>>::get(who);
@@ -605,7 +1102,8 @@ impl<T: Trait> Module<T> {
                 share_asset_required,
                 num_of_phases,
                 num_of_phases_left,
-            );
+            )
+            .map_err(|_| Error::<T>::Overflow)?;
         let mut share_asset_need_to_burn = T::Balance::zero();
         let mut free_share_asset_to_deduct = free_share_asset_required;
         let mut locked_share_asset_to_deduct = locked_share_asset_required;
@@ -656,7 +1154,7 @@ impl<T: Trait> Module<T> {
             let free_share_asset = <rio_assets::Module<T>>::free_balance(share_asset_id, who);
             ensure!(
                 free_share_asset + unrel_total_balance >= share_asset_required,
-                "total balance doesn't match"
+                Error::<T>::TotalBalanceMismatch
             );
 
             if free_share_asset < free_share_asset_to_deduct {
@@ -671,7 +1169,7 @@ impl<T: Trait> Module<T> {
         ensure!(
             free_share_asset_to_deduct + locked_share_asset_to_deduct + returned_share_asset
                 == free_share_asset_required + locked_share_asset_required,
-            "in & out don't match"
+            Error::<T>::InOutMismatch
         );
 
         <rio_assets::Module<T>>::make_transfer_with_event(
@@ -706,8 +1204,27 @@ impl<T: Trait> Module<T> {
             collection_asset_id,
             collection_account,
             who,
-            amount,
+            amount - penalty,
         )?;
+        if !penalty.is_zero() {
+            <rio_assets::Module<T>>::make_transfer_with_event(
+                collection_asset_id,
+                collection_account,
+                &Self::profit_pool(),
+                penalty,
+            )?;
+            Self::deposit_event(RawEvent::EarlyRedeemPenalty(
+                who.clone(),
+                *iou_asset_id,
+                early,
+                penalty,
+            ));
+        }
+        if positions_left.is_empty() {
+            <LockedPositions<T>>::remove(who);
+        } else {
+            <LockedPositions<T>>::insert(who, positions_left);
+        }
 
         Self::deposit_event(RawEvent::StakingRedeemed(
             who.clone(),
@@ -725,7 +1242,7 @@ impl<T: Trait> Module<T> {
         asset_id: &T::AssetId,
         who: &T::AccountId,
         change: Option<BalanceChange<T::Balance>>,
-    ) -> Result<Vec<ShareReleasePack<T::Balance, T::AssetId, T::AccountId>>, &'static str> {
+    ) -> Result<Vec<ShareReleasePack<T::Balance, T::AssetId, T::AccountId>>, DispatchError> {
         let mut new_list: Vec<ShareReleasePack<T::Balance, T::AssetId, T::AccountId>> =
             Vec::with_capacity(unreleased_list.len());
         let mut unreleased_total_balance = T::Balance::zero();
@@ -769,7 +1286,7 @@ impl<T: Trait> Module<T> {
             } else {
                 ensure!(
                     unreleased_total_balance >= change.get_value(),
-                    "not enought locked balance"
+                    Error::<T>::NotEnoughLockedBalance
                 );
                 if unreleased_total_balance > change.get_value() {
                     let mut aggregated = Self::create_share_release_pack(
@@ -820,18 +1337,32 @@ impl<T: Trait> Module<T> {
     pub fn create_staking(
         who: T::AccountId,
         balance: T::Balance,
-    ) -> result::Result<T::Balance, &'static str> {
-        ensure!(!balance.is_zero(), "saving can't be zero");
+    ) -> result::Result<T::Balance, DispatchError> {
+        Self::create_staking_with_tier(who, balance, LockTier::Flexible)
+    }
+
+    /// same as create_staking, but locks the saving up according to `tier`
+    pub fn create_staking_with_tier(
+        who: T::AccountId,
+        balance: T::Balance,
+        tier: LockTier,
+    ) -> result::Result<T::Balance, DispatchError> {
+        ensure!(!balance.is_zero(), Error::<T>::ZeroSaving);
+        ensure!(Self::shutdown().is_none(), Error::<T>::ShutDown);
+        Self::ensure_not_tripped(SavingFunction::Staking)?;
 
         let share_asset_id = Self::share_asset_id();
         let phase_id = Self::current_phase_id();
 
         ensure!(
             <PhaseInfos<T>>::exists(&phase_id),
-            "current phase id is invalid"
+            Error::<T>::InvalidCurrentPhase
         );
 
         let phase_info = <PhaseInfos<T>>::get(&phase_id);
+        let iou_asset_id = phase_info
+            .iou_asset_id
+            .ok_or(Error::<T>::IouAssetIdNotSet)?;
         let used_quota = Self::used_quota();
         let quota_will_be = used_quota.checked_add(&balance).expect("quota overflow");
         let mut next_phase = false;
@@ -851,7 +1382,7 @@ impl<T: Trait> Module<T> {
         };
 
         // assets in iou are yet to be minted
-        let iou = Self::create_iou(who.clone(), &phase_info, iou_balance);
+        let iou = Self::create_iou(who.clone(), iou_asset_id, iou_balance);
         // assets in share_pack are yet to be minted
         let mut share_pack = Self::create_release_pack_for_saving(
             who.clone(),
@@ -867,25 +1398,173 @@ impl<T: Trait> Module<T> {
         Self::deposit_event(RawEvent::StakingCreated(
             who.clone(),
             share_asset_balance,
-            phase_info.iou_asset_id.unwrap(),
+            iou_asset_id,
             iou_balance,
         ));
 
+        if tier != LockTier::Flexible {
+            Self::lock_up(&who, iou_asset_id, phase_info.exchange, iou_balance, tier);
+        }
+
         if next_phase {
             let shifted = Self::shift_next_phase(phase_id);
             if shifted.is_some() && next_phase_iou > <T::Balance>::zero() {
-                iou_balance += Self::create_staking(who.clone(), next_phase_iou)?;
+                iou_balance += Self::create_staking_with_tier(who.clone(), next_phase_iou, tier)?;
             }
         }
 
         Ok(iou_balance)
     }
 
+    fn lock_up(
+        who: &T::AccountId,
+        iou_asset_id: T::AssetId,
+        exchange: T::Balance,
+        iou_balance: T::Balance,
+        tier: LockTier,
+    ) {
+        let now = <timestamp::Module<T>>::get();
+        let maturity =
+            now + T::Moment::from(DAY_IN_MILLI) * T::Moment::from(tier.lock_days());
+        <LockedPositions<T>>::mutate(who, |v| {
+            v.push(LockedPosition {
+                iou_asset_id,
+                tier,
+                amount: iou_balance,
+                // the share pack is made of the same product, which has been checked
+                shares: iou_balance.saturating_mul(exchange),
+                maturity,
+            })
+        });
+        Self::deposit_event(RawEvent::StakingLocked(
+            who.clone(),
+            iou_asset_id,
+            iou_balance,
+            tier,
+            maturity,
+        ));
+    }
+
+    /// Immutable
+    pub fn bonus_weight(tier: LockTier) -> u32 {
+        Self::tier_bonus_weight(tier).unwrap_or_else(|| tier.default_bonus_weight())
+    }
+
+    /// Immutable
+    /// `shares` weighted by the positions of `who` which are still locked,
+    /// positions with higher weight take the priority, shares beyond all positions count as 1x
+    pub fn weighted_shares(
+        who: &T::AccountId,
+        shares: T::Balance,
+        now: T::Moment,
+    ) -> MathResult<T::Balance> {
+        let mut positions = <LockedPositions<T>>::get(who)
+            .into_iter()
+            .filter(|v| v.maturity > now)
+            .collect::<Vec<_>>();
+        positions.sort_by(|a, b| Self::bonus_weight(b.tier).cmp(&Self::bonus_weight(a.tier)));
+
+        let mut remaining = shares;
+        let mut weighted = T::Balance::zero();
+        for position in positions {
+            if remaining.is_zero() {
+                break;
+            }
+            let counted = rstd::cmp::min(position.shares, remaining);
+            remaining -= counted;
+            let w = balance_mul_div(
+                counted,
+                u128::from(Self::bonus_weight(position.tier)),
+                u128::from(BONUS_WEIGHT_PREC),
+            )?;
+            weighted = checked_add(weighted, w)?;
+        }
+        checked_add(weighted, remaining)
+    }
+
+    /// Immutable
+    /// returns the weighted shares of each account and the total of them
+    fn all_weighted_shares() -> MathResult<(Vec<(T::AccountId, T::Balance)>, T::Balance)> {
+        let now = <timestamp::Module<T>>::get();
+        let mut weighted = vec![];
+        let mut total = T::Balance::zero();
+        for (who, balance) in <AccountShares<T>>::enumerate() {
+            let w = Self::weighted_shares(&who, balance, now)?;
+            total = checked_add(total, w)?;
+            weighted.push((who, w));
+        }
+        Ok((weighted, total))
+    }
+
+    /// Immutable
+    /// the cut of `users` for `weighted` out of `total_weighted`
+    fn bonus_cut(
+        weighted: T::Balance,
+        users: T::Balance,
+        total_weighted: T::Balance,
+    ) -> MathResult<T::Balance> {
+        balance_mul_div(weighted, to_u128(users)?, to_u128(total_weighted)?)
+    }
+
+    /// Immutable
+    /// the balance of `iou_asset_id` held by `who` which is locked up at `now`
+    pub fn locked_iou(who: &T::AccountId, iou_asset_id: &T::AssetId, now: T::Moment) -> T::Balance {
+        <LockedPositions<T>>::get(who)
+            .into_iter()
+            .filter(|v| v.iou_asset_id == *iou_asset_id && v.maturity > now)
+            .fold(T::Balance::zero(), |accu, v| accu.saturating_add(v.amount))
+    }
+
+    /// Immutable
+    /// redeeming `amount` of `iou_asset_id` consumes the part of the balance which is not locked first,
+    /// returns the amount redeemed before maturity and the positions left after redeeming
+    /// iou_balance is the IOU balance of `who` before redeeming
+    fn redeem_locked_positions(
+        who: &T::AccountId,
+        iou_asset_id: &T::AssetId,
+        amount: T::Balance,
+        iou_balance: T::Balance,
+    ) -> MathResult<(
+        T::Balance,
+        Vec<LockedPosition<T::Balance, T::AssetId, T::Moment>>,
+    )> {
+        let now = <timestamp::Module<T>>::get();
+        // matured positions are flexible now, just drop them
+        let mut positions = <LockedPositions<T>>::get(who)
+            .into_iter()
+            .filter(|v| v.maturity > now)
+            .collect::<Vec<_>>();
+        positions.sort_by(|a, b| a.maturity.cmp(&b.maturity));
+
+        let locked = Self::locked_iou(who, iou_asset_id, now);
+        let early = amount.saturating_sub(iou_balance.saturating_sub(locked));
+
+        // the position closest to maturity goes first
+        let mut left = early;
+        for position in positions
+            .iter_mut()
+            .filter(|v| v.iou_asset_id == *iou_asset_id)
+        {
+            if left.is_zero() {
+                break;
+            }
+            let deduct = rstd::cmp::min(position.amount, left);
+            left -= deduct;
+            let amount_left = position.amount - deduct;
+            position.shares =
+                balance_mul_div(position.shares, to_u128(amount_left)?, to_u128(position.amount)?)?;
+            position.amount = amount_left;
+        }
+        positions.retain(|v| !v.amount.is_zero());
+
+        Ok((early, positions))
+    }
+
     /// mint_assets will call ShareReleasePack::release(&mut self), which cause some side-effect inside the share_pack
     fn mint_assets(
         iou_mapbe: Option<&IOU<T::AccountId, T::Balance, T::AssetId>>,
         share_pack_maybe: Option<&mut ShareReleasePack<T::Balance, T::AssetId, T::AccountId>>,
-    ) -> result::Result<(T::Balance, T::Balance), &'static str> {
+    ) -> result::Result<(T::Balance, T::Balance), DispatchError> {
         let mut iou_balance = T::Balance::zero();
         let mut share_asset_balance = T::Balance::zero();
         if let Some(iou) = iou_mapbe {
@@ -919,11 +1598,11 @@ impl<T: Trait> Module<T> {
     /// create_iou is called when a user deposit his BTC into rio saving program
     fn create_iou(
         owner: T::AccountId,
-        phase_info: &PhaseInfo<T::Balance, T::AssetId>,
+        asset_id: T::AssetId,
         saving: T::Balance,
     ) -> IOU<T::AccountId, T::Balance, T::AssetId> {
         IOU {
-            asset_id: phase_info.iou_asset_id.unwrap(),
+            asset_id,
             balance: saving,
             owner,
         }
@@ -956,9 +1635,9 @@ impl<T: Trait> Module<T> {
         asset_id: T::AssetId,
         balance: T::Balance,
         phase_info: &PhaseInfo<T::Balance, T::AssetId>,
-    ) -> Result<ShareReleasePack<T::Balance, T::AssetId, T::AccountId>, &'static str> {
+    ) -> Result<ShareReleasePack<T::Balance, T::AssetId, T::AccountId>, DispatchError> {
         if balance.is_zero() {
-            return Err("saving balance must not be zero");
+            return Err(Error::<T>::ZeroSavingBalance.into());
         }
 
         let share_balance = balance
@@ -967,7 +1646,7 @@ impl<T: Trait> Module<T> {
 
         let effective_phases_count = NumOfPhasesLeft::get();
         if effective_phases_count < 1 {
-            return Err("should not create share packages for the last phase");
+            return Err(Error::<T>::LastPhase.into());
         }
 
         Ok(Self::create_share_release_pack(
@@ -1082,7 +1761,8 @@ impl<T: Trait> Module<T> {
         if *from != collection_account_id {
             let from_balance = Self::account_shares(from);
             if from_balance < balance {
-                Self::_pause(line!());
+                // the tracked shares are off, so are the amounts staking and redeem go by
+                Self::trip_breakers(TripReason::InvariantBroken);
             } else if from_balance == balance {
                 <AccountShares<T>>::remove(from);
             } else {
@@ -1101,7 +1781,7 @@ impl<T: Trait> Module<T> {
                 <AccountShares<T>>::mutate(to, |v| {
                     let shares = v.checked_add(&balance);
                     if shares.is_none() {
-                        Self::_pause(line!());
+                        Self::trip(SavingFunction::Bonus, TripReason::Overflow);
                     } else {
                         *v = shares.unwrap();
                     }
@@ -1113,7 +1793,7 @@ impl<T: Trait> Module<T> {
             <SharesCirculation<T>>::mutate(|v| {
                 let shares = v.checked_sub(&balance);
                 if shares.is_none() {
-                    Self::_pause(line!());
+                    Self::trip(SavingFunction::Bonus, TripReason::Underflow);
                 } else {
                     *v = shares.unwrap();
                 }
@@ -1130,7 +1810,7 @@ impl<T: Trait> Module<T> {
         required_balance: T::Balance,
         total_phases: u32,
         phases_left: u32,
-    ) -> (T::Balance, T::Balance) {
+    ) -> MathResult<(T::Balance, T::Balance)> {
         // both works, just for refs
         // let free_share_asset_required = required_balance
         //     * T::Balance::from(1 + total_phases - (phase_id - 1) - phases_left)
@@ -1139,23 +1819,48 @@ impl<T: Trait> Module<T> {
         //     free_share_asset_required,
         //     required_balance - free_share_asset_required,
         // )
-        let locked_share_asset_required = required_balance * T::Balance::from(phases_left - 1)
-            / T::Balance::from(total_phases - phase_id + 1);
-        (
-            required_balance - locked_share_asset_required,
-            locked_share_asset_required,
-        )
+        let locked_terms = phases_left.checked_sub(1).ok_or(MathError::Underflow)?;
+        let terms = total_phases
+            .checked_sub(phase_id)
+            .and_then(|v| v.checked_add(1))
+            .ok_or(MathError::Underflow)?;
+        let locked_share_asset_required =
+            balance_mul_div(required_balance, u128::from(locked_terms), u128::from(terms))?;
+        let free_share_asset_required = required_balance
+            .checked_sub(&locked_share_asset_required)
+            .ok_or(MathError::Underflow)?;
+        Ok((free_share_asset_required, locked_share_asset_required))
     }
 
-    fn _pause(linum: u32) {
-        Paused::mutate(|v| {
-            *v = true;
-        });
-        Self::deposit_event(RawEvent::Paused(
-            linum,
+    /// trip the breaker of `function`, it resumes by itself if BreakerTimeout is set
+    fn trip(function: SavingFunction, reason: TripReason) {
+        let breaker = SavingBreakers::<T>::trip(
+            function,
+            reason,
             <system::Module<T>>::block_number(),
-            <system::Module<T>>::extrinsic_index().unwrap(),
-        ));
+            Self::breaker_timeout(),
+        );
+        Self::deposit_event(RawEvent::BreakerTripped(function, reason, breaker.resume_at));
+    }
+
+    /// Immutable
+    pub fn is_tripped(function: SavingFunction) -> bool {
+        SavingBreakers::<T>::is_tripped(function, <system::Module<T>>::block_number())
+    }
+
+    fn ensure_not_tripped(function: SavingFunction) -> DispatchResult {
+        ensure!(!Self::is_tripped(function), Error::<T>::FunctionTripped);
+        Ok(())
+    }
+
+    /// Immutable
+    /// backs `SavingApi::breakers`
+    pub fn breakers() -> Vec<(SavingFunction, Breaker<T::BlockNumber>)> {
+        let now = <system::Module<T>>::block_number();
+        SavingFunction::all()
+            .into_iter()
+            .filter_map(|f| SavingBreakers::<T>::get(f, now).map(|b| (f, b)))
+            .collect()
     }
 
     fn check_bonus_time() -> bool {
@@ -1176,52 +1881,138 @@ impl<T: Trait> Module<T> {
     }
 
     fn split_profit(amount: T::Balance) -> (T::Balance, T::Balance) {
-        let users = amount * T::Balance::from(8) / T::Balance::from(10);
+        let users = balance_mul_div(amount, 8, 10)
+            .unwrap_or_else(|_| amount / T::Balance::from(10) * T::Balance::from(8));
         (users, amount - users)
     }
 
+    /// Immutable
+    /// the profit asset, and the collection asset which the early redeem penalties are paid in
+    fn bonus_assets() -> Vec<T::AssetId> {
+        let profit_asset = Self::profit_asset_id();
+        let collection_asset = <CollectionAssetId<T>>::get();
+        if collection_asset == profit_asset {
+            vec![profit_asset]
+        } else {
+            vec![profit_asset, collection_asset]
+        }
+    }
+
     fn dispatch_bonus() {
         let team_account = Self::team_account_id();
         let profit_pool = Self::profit_pool();
-        let profit_asset = Self::profit_asset_id();
-        let total_profit = <rio_assets::Module<T>>::free_balance(&profit_asset, &profit_pool);
-        let circulation = Self::shares_circulation();
-        let (users, teams) = Self::split_profit(total_profit);
+        // the profit stays in the pool for the next dispatch
+        let (weighted, total_weighted) = match Self::all_weighted_shares() {
+            Ok(weighted) => weighted,
+            Err(_) => return,
+        };
 
-        // give the team their bonus cut
-        <rio_assets::Module<T>>::make_transfer_with_event(
-            &profit_asset,
-            &profit_pool,
-            &team_account,
-            teams,
-        )
-        .unwrap_or_default();
+        for asset_id in Self::bonus_assets() {
+            let total_profit = <rio_assets::Module<T>>::free_balance(&asset_id, &profit_pool);
+            let (users, teams) = Self::split_profit(total_profit);
 
-        // give each user his bonus cut
-        for (user_id, balance) in <AccountShares<T>>::enumerate() {
-            let user_bonus = balance * users / circulation;
+            // give the team their bonus cut
             <rio_assets::Module<T>>::make_transfer_with_event(
-                &profit_asset,
+                &asset_id,
                 &profit_pool,
-                &user_id,
-                user_bonus,
+                &team_account,
+                teams,
             )
             .unwrap_or_default();
+
+            // give each user his bonus cut, weighted by the lock-up tiers
+            for (user_id, balance) in weighted.iter() {
+                let user_bonus =
+                    Self::bonus_cut(*balance, users, total_weighted).unwrap_or_default();
+                <rio_assets::Module<T>>::make_transfer_with_event(
+                    &asset_id,
+                    &profit_pool,
+                    user_id,
+                    user_bonus,
+                )
+                .unwrap_or_default();
+            }
         }
 
         Self::deposit_event(RawEvent::Bonus());
     }
 }
 
+decl_error! {
+    pub enum Error for Module<T: Trait> {
+        /// share asset id is not a created asset
+        InvalidShareAssetId,
+        /// phase id is not configured
+        InvalidPhaseId,
+        /// iou asset id is not a created asset
+        InvalidIouAssetId,
+        /// collection or profit asset id is not a created asset
+        InvalidCollectionAssetId,
+        /// bonus weight is below 1x
+        BonusWeightTooLow,
+        /// penalty rate is not below 100%
+        InvalidPenaltyRate,
+        /// module is paused
+        Paused,
+        /// module is shut down
+        ShutDown,
+        /// asset is not the collection asset
+        InvalidCollectionAsset,
+        /// free balance is less than the staking amount
+        InsufficientBalance,
+        /// share asset id is not set
+        ShareAssetIdNotSet,
+        /// collection asset id is not set
+        CollectionAssetIdNotSet,
+        /// iou asset doesn't belong to any phase
+        UnknownIouAsset,
+        /// reserved mint wallet can't cover the burn
+        ReservedWalletShort,
+        /// not enough available share asset
+        NotEnoughShareAsset,
+        /// redeem amount exceeds the allowed limit
+        ExceedRedeemLimit,
+        /// module is not shut down
+        NotShutDown,
+        /// nothing to settle
+        NothingToSettle,
+        /// free and locked shares don't add up to the requirement
+        TotalBalanceMismatch,
+        /// deducted shares don't match the requirement
+        InOutMismatch,
+        /// not enough locked share balance
+        NotEnoughLockedBalance,
+        /// saving amount is zero
+        ZeroSaving,
+        /// current phase id is not configured
+        InvalidCurrentPhase,
+        /// saving balance is zero
+        ZeroSavingBalance,
+        /// no share packages are created for the last phase
+        LastPhase,
+        /// the circuit breaker of the function is tripped
+        FunctionTripped,
+        /// transfer to self
+        SelfTransfer,
+        /// arithmetic overflow or underflow
+        Overflow,
+        /// no pending parameter change of the id
+        InvalidParamChangeId,
+        /// iou asset id of the phase is not set
+        IouAssetIdNotSet,
+        /// the iou asset is locked up until its maturity, it can only be redeemed
+        IouLocked,
+    }
+}
+
 decl_event!(
     pub enum Event<T>
     where
         AccountId = <T as system::Trait>::AccountId,
         AssetId = <T as rio_assets::Trait>::AssetId,
         Balance = <T as rio_assets::Trait>::Balance,
-        LineNumber = u32,
-        ExtrinsicIndex = u32,
         BlockNumber = <T as system::Trait>::BlockNumber,
+        Moment = <T as timestamp::Trait>::Moment,
     {
         // fired when current phase changed
         PhaseChanged(PhaseId, PhaseId),
@@ -1230,8 +2021,26 @@ decl_event!(
         // (AccountId, RBTC balance, Phase contract, Phase contract balance a.k.a SBTC balance)
         StakingCreated(AccountId, Balance, AssetId, Balance),
         StakingRedeemed(AccountId, Balance, AssetId, Balance),
-
-        Paused(LineNumber, BlockNumber, ExtrinsicIndex),
+        // (AccountId, Phase contract, Phase contract balance, LockTier, maturity)
+        StakingLocked(AccountId, AssetId, Balance, LockTier, Moment),
+        // (AccountId, Phase contract, balance redeemed before maturity, penalty in collection asset)
+        EarlyRedeemPenalty(AccountId, AssetId, Balance, Balance),
+
+        // (shares circulation, total claims, collection balance)
+        Shutdown(Balance, Balance, Balance),
+        // (AccountId, claims, collection asset paid)
+        Settled(AccountId, Balance, Balance),
+
+        // (function, reason, block to resume at)
+        BreakerTripped(SavingFunction, TripReason, Option<BlockNumber>),
+        BreakerReset(SavingFunction),
+
+        // (change id, the block it applies)
+        ParamChangeScheduled(ParamChangeId, BlockNumber),
+        ParamChangeCancelled(ParamChangeId),
+        ParamChangeApplied(ParamChangeId),
+        // the parameter is no longer valid when it was due
+        ParamChangeRejected(ParamChangeId),
 
         Bonus(),
     }
@@ -1250,7 +2059,7 @@ impl<T: Trait> rio_assets::traits::OnAssetMint<T::AssetId, T::AccountId, T::Bala
                 <AccountShares<T>>::mutate(to, |v| {
                     let shares = v.checked_add(&balance);
                     if shares.is_none() {
-                        Self::_pause(line!());
+                        Self::trip(SavingFunction::Bonus, TripReason::Overflow);
                     } else {
                         *v = shares.unwrap();
                     }
@@ -1261,7 +2070,7 @@ impl<T: Trait> rio_assets::traits::OnAssetMint<T::AssetId, T::AccountId, T::Bala
             <SharesCirculation<T>>::mutate(|v| {
                 let shares = v.checked_add(&balance);
                 if shares.is_none() {
-                    Self::_pause(line!());
+                    Self::trip(SavingFunction::Bonus, TripReason::Overflow);
                 } else {
                     *v = shares.unwrap();
                 }
@@ -1288,7 +2097,7 @@ impl<T: Trait> rio_assets::traits::OnAssetBurn<T::AssetId, T::AccountId, T::Bala
             <AccountShares<T>>::mutate(from, |v| {
                 let shares = v.checked_sub(balance);
                 if shares.is_none() {
-                    Self::_pause(line!());
+                    Self::trip(SavingFunction::Bonus, TripReason::Underflow);
                 } else {
                     *v = shares.unwrap();
                 }
@@ -1296,7 +2105,7 @@ impl<T: Trait> rio_assets::traits::OnAssetBurn<T::AssetId, T::AccountId, T::Bala
             <SharesCirculation<T>>::mutate(|v| {
                 let shares = v.checked_sub(balance);
                 if shares.is_none() {
-                    Self::_pause(line!());
+                    Self::trip(SavingFunction::Bonus, TripReason::Underflow);
                 } else {
                     *v = shares.unwrap();
                 }
@@ -1308,6 +2117,19 @@ impl<T: Trait> rio_assets::traits::OnAssetBurn<T::AssetId, T::AccountId, T::Bala
     }
 }
 
+/// a reserve deficit stops new savings and redeemals, anything else stops all the functions
+impl<T: Trait> TripBreakers for Module<T> {
+    fn trip_breakers(reason: TripReason) {
+        let functions = match reason {
+            TripReason::ReserveDeficit => vec![SavingFunction::Staking, SavingFunction::Redeem],
+            _ => SavingFunction::all(),
+        };
+        for function in functions {
+            Self::trip(function, reason);
+        }
+    }
+}
+
 impl<T: Trait> rio_assets::traits::BeforeAssetTransfer<T::AssetId, T::AccountId, T::Balance>
     for Module<T>
 {
@@ -1321,8 +2143,22 @@ impl<T: Trait> rio_assets::traits::BeforeAssetTransfer<T::AssetId, T::AccountId,
             return Ok(());
         }
 
+        // neither staking nor redeemal by transfer is allowed after shutdown
+        if Self::shutdown().is_some() && *to == Self::collection_account_id() {
+            ensure!(*asset_id != Self::share_asset_id(), Error::<T>::ShutDown);
+            ensure!(*asset_id != Self::collection_asset_id(), Error::<T>::ShutDown);
+            ensure!(!<IOUAssetPhaseId<T>>::exists(&asset_id), Error::<T>::ShutDown);
+        }
+
         if <IOUAssetPhaseId<T>>::exists(&asset_id) && *to == Self::collection_account_id() {
+            Self::ensure_not_tripped(SavingFunction::Redeem)?;
             Self::check_can_redeem(*asset_id, from.clone(), *balance)?;
+        } else if <IOUAssetPhaseId<T>>::exists(&asset_id) {
+            // locked positions stay with their owner, redeeming early is the only way out
+            let now = <timestamp::Module<T>>::get();
+            let free = <rio_assets::Module<T>>::free_balance(asset_id, from)
+                .saturating_sub(Self::locked_iou(from, asset_id, now));
+            ensure!(free >= *balance, Error::<T>::IouLocked);
         }
 
         Ok(())
@@ -1345,7 +2181,7 @@ impl<T: Trait> rio_assets::traits::OnAssetTransfer<T::AssetId, T::AccountId, T::
             return Ok(());
         }
 
-        ensure!(from != to, "from == to in transfer");
+        ensure!(from != to, Error::<T>::SelfTransfer);
         let share_asset_id = <ShareAssetId<T>>::get();
         if share_asset_id.is_zero() {
             // share asset id is the core, our code would be dead without it
@@ -273,8 +273,12 @@ impl rio_assets::Trait for TestRuntime {
     type BeforeAssetTransfer = RioSavingTest;
     type BeforeAssetBurn = ();
 }
+parameter_types! {
+    pub const ParamChangeDelay: u64 = 10;
+}
 impl Trait for TestRuntime {
     type Event = ();
+    type ParamChangeDelay = ParamChangeDelay;
 }
 
 pub struct ExtBuilder {}
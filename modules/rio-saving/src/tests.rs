@@ -41,11 +41,10 @@ fn current_phase_id() {
 #[test]
 fn set_iou_asset_id() {
     ExtBuilder::default().build().execute_with(|| {
-        assert_ok!(RioSavingTest::set_iou_asset_id_for_phase(
-            Origin::ROOT,
+        assert_ok!(RioSavingTest::apply_param_change(SavingParam::IouAssetId(
             PHASE1,
             RSC5_ASSET_ID
-        ));
+        )));
         assert_eq!(
             RioSavingTest::phase_info(PHASE1).iou_asset_id,
             Some(RSC5_ASSET_ID)
@@ -53,12 +52,56 @@ fn set_iou_asset_id() {
     });
 }
 
+#[test]
+fn param_changes_are_timelocked() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioSavingTest::schedule_param_change(
+                Origin::signed(ALICE),
+                SavingParam::ProfitPool(BOB)
+            ),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            RioSavingTest::schedule_param_change(
+                Origin::ROOT,
+                SavingParam::TierBonusWeight(LockTier::Days30, 99)
+            ),
+            Error::<TestRuntime>::BonusWeightTooLow
+        );
+        assert_ok!(RioSavingTest::schedule_param_change(
+            Origin::ROOT,
+            SavingParam::ProfitPool(BOB)
+        ));
+        let rate_change = RioSavingTest::next_param_change_id();
+        assert_ok!(RioSavingTest::schedule_param_change(
+            Origin::ROOT,
+            SavingParam::EarlyRedeemPenaltyRate(500)
+        ));
+        assert_ok!(RioSavingTest::cancel_param_change(Origin::ROOT, rate_change));
+        assert_noop!(
+            RioSavingTest::cancel_param_change(Origin::ROOT, rate_change),
+            Error::<TestRuntime>::InvalidParamChangeId
+        );
+        assert_eq!(RioSavingTest::pending_param_changes().len(), 1);
+        assert_eq!(RioSavingTest::pending_param_changes()[0].1.eta, 10);
+
+        let profit_pool = RioSavingTest::profit_pool();
+        RioSavingTest::on_initialize(9);
+        assert_eq!(RioSavingTest::profit_pool(), profit_pool);
+        RioSavingTest::on_initialize(10);
+        assert_eq!(RioSavingTest::profit_pool(), BOB);
+        assert_eq!(RioSavingTest::early_redeem_penalty_rate(), 0);
+        assert!(RioSavingTest::pending_param_changes().is_empty());
+    });
+}
+
 #[test]
 fn create_staking() {
     ExtBuilder::default().build().execute_with(|| {
         assert_noop!(
             RioSavingTest::create_staking(ALICE, 0),
-            "saving can't be zero"
+            Error::<TestRuntime>::ZeroSaving
         );
         assert_ok!(RioSavingTest::create_staking(ALICE, 10 * DECIMALS));
         assert_eq!(
@@ -128,7 +171,8 @@ fn staking_by_staking() {
         assert_ok!(RioSavingTest::staking(
             Origin::signed(ALICE),
             SBTC_ASSET_ID,
-            10 * DECIMALS
+            10 * DECIMALS,
+            LockTier::Flexible
         ));
         assert_eq!(
             <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &ALICE),
@@ -146,7 +190,8 @@ fn staking_by_staking() {
             Origin::ROOT,
             SBTC_ASSET_ID,
             10 * DECIMALS,
-            ALICE
+            ALICE,
+            LockTier::Flexible
         ));
         assert_eq!(
             <rio_assets::Module<TestRuntime>>::free_balance(&RSC1_ASSET_ID, &ALICE),
@@ -377,7 +422,8 @@ fn redeem_501_sbtc_by_redeem() {
         assert_ok!(RioSavingTest::staking(
             Origin::signed(BOB),
             SBTC_ASSET_ID,
-            500_00000000
+            500_00000000,
+            LockTier::Flexible
         ));
 
         assert_ok!(RioSavingTest::redeem(
@@ -507,7 +553,7 @@ fn illegal_redeem() {
                 COLLECTION_ACCOUNT_ID,
                 1_10000000
             ),
-            "not enough available share asset"
+            Error::<TestRuntime>::NotEnoughShareAsset
         );
         assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
             Origin::ROOT,
@@ -522,7 +568,7 @@ fn illegal_redeem() {
                 COLLECTION_ACCOUNT_ID,
                 1_10000000
             ),
-            "not enough available share asset"
+            Error::<TestRuntime>::NotEnoughShareAsset
         );
         assert_noop!(
             <rio_assets::Module<TestRuntime>>::transfer(
@@ -531,7 +577,7 @@ fn illegal_redeem() {
                 COLLECTION_ACCOUNT_ID,
                 5000_00000000
             ),
-            "balance too low to send amount"
+            rio_assets::Error::<TestRuntime>::BalanceTooLow
         );
     });
 }
@@ -585,12 +631,17 @@ fn pause_works() {
             100 * DECIMALS
         ));
         assert_noop!(
-            RioSavingTest::staking(Origin::signed(ALICE), SBTC_ASSET_ID, 10 * DECIMALS),
-            "module is paused"
+            RioSavingTest::staking(
+                Origin::signed(ALICE),
+                SBTC_ASSET_ID,
+                10 * DECIMALS,
+                LockTier::Flexible
+            ),
+            Error::<TestRuntime>::Paused
         );
         assert_noop!(
             RioSavingTest::redeem(Origin::signed(ALICE), RSC1_ASSET_ID, 5000_0000),
-            "module is paused"
+            Error::<TestRuntime>::Paused
         );
         assert_ok!(RioSavingTest::resume(Origin::ROOT));
         assert_eq!(RioSavingTest::paused(), false);
@@ -630,6 +681,65 @@ fn share_asset_distribution() {
     });
 }
 
+#[test]
+fn saving_position_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioSavingTest::create_staking(ALICE, 1 * DECIMALS));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            RIO_ASSET_ID,
+            PROFIT_POOL,
+            1000_0000
+        ));
+
+        let position = RioSavingTest::saving_position(ALICE);
+        assert_eq!(position.shares, 2000_00000000);
+        assert_eq!(position.share_asset_collected, 0);
+        assert_eq!(position.pending_bonus, 800_0000);
+        assert_eq!(
+            position.unreleased,
+            vec![UnreleasedShares {
+                phase_id: PHASE1,
+                asset_id: RBTC_ASSET_ID,
+                terms_left: 4,
+                total_balance: 8000_00000000,
+            }]
+        );
+        assert_eq!(
+            position.redeemable,
+            vec![RedeemableIou {
+                phase_id: PHASE1,
+                iou_asset_id: RSC1_ASSET_ID,
+                iou_balance: 1 * DECIMALS,
+                redeemable: 1 * DECIMALS,
+                free_share_required: 2000_00000000,
+                locked_share_required: 8000_00000000,
+            }]
+        );
+
+        // the position tells exactly what redeem accepts
+        assert_ok!(RioSavingTest::check_can_redeem(
+            RSC1_ASSET_ID,
+            ALICE,
+            position.redeemable[0].redeemable
+        ));
+    });
+}
+
+#[test]
+fn saving_state_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioSavingTest::create_staking(ALICE, 1 * DECIMALS));
+        let state = RioSavingTest::saving_state();
+        assert_eq!(state.current_phase_id, PHASE1);
+        assert_eq!(state.num_of_phases_left, NUM_OF_PHASE);
+        assert_eq!(state.quota, PHASE1_QUOTA);
+        assert_eq!(state.quota_used, 1 * DECIMALS);
+        assert_eq!(state.quota_remaining, PHASE1_QUOTA - 1 * DECIMALS);
+        assert_eq!(state.paused, false);
+    });
+}
+
 // this is for future reference
 #[test]
 fn deposit_event_should_work() {
@@ -751,6 +861,384 @@ fn manually_release_bonus() {
     });
 }
 
+#[test]
+fn staking_with_lock_tier() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            ALICE,
+            100 * DECIMALS
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(ALICE),
+            SBTC_ASSET_ID,
+            10 * DECIMALS,
+            LockTier::Days30
+        ));
+        assert_eq!(
+            RioSavingTest::locked_positions(&ALICE),
+            vec![LockedPosition {
+                iou_asset_id: RSC1_ASSET_ID,
+                tier: LockTier::Days30,
+                amount: 10 * DECIMALS,
+                shares: 10 * DECIMALS * PHASE1_EXCHANGE,
+                maturity: 30 * DAY_IN_MILLI as u64,
+            }]
+        );
+
+        // a flexible saving isn't locked up
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(ALICE),
+            SBTC_ASSET_ID,
+            10 * DECIMALS,
+            LockTier::Flexible
+        ));
+        assert_eq!(RioSavingTest::locked_positions(&ALICE).len(), 1);
+
+        // only the flexible part can be transferred before maturity
+        assert_noop!(
+            <rio_assets::Module<TestRuntime>>::transfer(
+                Origin::signed(ALICE),
+                RSC1_ASSET_ID,
+                BOB,
+                15 * DECIMALS
+            ),
+            Error::<TestRuntime>::IouLocked
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::transfer(
+            Origin::signed(ALICE),
+            RSC1_ASSET_ID,
+            BOB,
+            10 * DECIMALS
+        ));
+        <timestamp::Module<TestRuntime>>::set_timestamp(30 * DAY_IN_MILLI as u64 + 1);
+        assert_ok!(<rio_assets::Module<TestRuntime>>::transfer(
+            Origin::signed(ALICE),
+            RSC1_ASSET_ID,
+            BOB,
+            10 * DECIMALS
+        ));
+    });
+}
+
+#[test]
+fn early_redeem_penalty() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioSavingTest::schedule_param_change(
+                Origin::ROOT,
+                SavingParam::EarlyRedeemPenaltyRate(PENALTY_RATE_PREC)
+            ),
+            Error::<TestRuntime>::InvalidPenaltyRate
+        );
+        assert_ok!(RioSavingTest::apply_param_change(
+            SavingParam::EarlyRedeemPenaltyRate(500)
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            BOB,
+            2_00000000
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(BOB),
+            SBTC_ASSET_ID,
+            1_00000000,
+            LockTier::Flexible
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(BOB),
+            SBTC_ASSET_ID,
+            1_00000000,
+            LockTier::Days90
+        ));
+
+        // the flexible part is redeemed first, and half of the locked part is redeemed early
+        assert_ok!(RioSavingTest::redeem(
+            Origin::signed(BOB),
+            RSC1_ASSET_ID,
+            1_50000000
+        ));
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            1_50000000 - 250_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &PROFIT_POOL),
+            250_0000
+        );
+        assert_eq!(RioSavingTest::locked_positions(&BOB)[0].amount, 5000_0000);
+
+        // the penalty is paid out with the bonus in the collection asset, BOB is the only one holding shares
+        RioSavingTest::dispatch_bonus();
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &PROFIT_POOL),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &TEAM),
+            50_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            1_50000000 - 50_0000
+        );
+
+        // no penalty after maturity
+        <timestamp::Module<TestRuntime>>::set_timestamp(91 * DAY_IN_MILLI as u64);
+        assert_ok!(RioSavingTest::redeem(
+            Origin::signed(BOB),
+            RSC1_ASSET_ID,
+            5000_0000
+        ));
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            2_00000000 - 50_0000
+        );
+        assert!(!<LockedPositions<TestRuntime>>::exists(&BOB));
+    });
+}
+
+#[test]
+fn bonus_weighted_by_lock_tier() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(RioSavingTest::create_staking(BOB, 10 * DECIMALS));
+        assert_ok!(RioSavingTest::create_staking_with_tier(
+            CHRIS,
+            10 * DECIMALS,
+            LockTier::Days180
+        ));
+        assert_eq!(
+            RioSavingTest::account_shares(&BOB),
+            RioSavingTest::account_shares(&CHRIS)
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            RIO_ASSET_ID,
+            PROFIT_POOL,
+            5000_0000,
+        ));
+        assert_eq!(RioSavingTest::pending_bonus(&CHRIS), 2400_0000);
+
+        RioSavingTest::dispatch_bonus();
+
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &TEAM),
+            1000_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &BOB),
+            1600_0000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RIO_ASSET_ID, &CHRIS),
+            2400_0000
+        );
+    });
+}
+
+#[test]
+fn emergency_shutdown_and_settle() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_noop!(
+            RioSavingTest::settle(Origin::signed(BOB)),
+            Error::<TestRuntime>::NotShutDown
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            BOB,
+            2_00000000
+        ));
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            CHRIS,
+            1_00000000
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(BOB),
+            SBTC_ASSET_ID,
+            1_00000000,
+            LockTier::Flexible
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(CHRIS),
+            SBTC_ASSET_ID,
+            1_00000000,
+            LockTier::Days90
+        ));
+
+        // pledged to a credit line, still a claim
+        assert_ok!(<rio_assets::Module<TestRuntime>>::set_reserve(
+            Origin::ROOT,
+            RSC1_ASSET_ID,
+            CHRIS,
+            5000_0000
+        ));
+        assert_ok!(RioSavingTest::emergency_shutdown(Origin::ROOT));
+        assert_noop!(
+            RioSavingTest::emergency_shutdown(Origin::ROOT),
+            Error::<TestRuntime>::ShutDown
+        );
+        let info = RioSavingTest::shutdown().unwrap();
+        assert_eq!(info.collection_balance, 2_00000000);
+        assert_eq!(info.shares_circulation, RioSavingTest::shares_circulation());
+        assert_eq!(
+            info.total_claims,
+            RioSavingTest::claims_of(&BOB).unwrap() + RioSavingTest::claims_of(&CHRIS).unwrap()
+        );
+
+        // no way in or out but settlement
+        assert_noop!(
+            RioSavingTest::staking(
+                Origin::signed(BOB),
+                SBTC_ASSET_ID,
+                1_00000000,
+                LockTier::Flexible
+            ),
+            Error::<TestRuntime>::ShutDown
+        );
+        assert_noop!(
+            RioSavingTest::redeem(Origin::signed(BOB), RSC1_ASSET_ID, 1_00000000),
+            Error::<TestRuntime>::ShutDown
+        );
+        assert_noop!(
+            <rio_assets::Module<TestRuntime>>::transfer(
+                Origin::signed(BOB),
+                SBTC_ASSET_ID,
+                COLLECTION_ACCOUNT_ID,
+                1_00000000
+            ),
+            Error::<TestRuntime>::ShutDown
+        );
+
+        // nothing is burnt when the collection account can't pay out
+        assert_ok!(<rio_assets::Module<TestRuntime>>::burn(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            COLLECTION_ACCOUNT_ID,
+            1_50000000
+        ));
+        assert_noop!(
+            RioSavingTest::settle(Origin::signed(BOB)),
+            Error::<TestRuntime>::InsufficientBalance
+        );
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            COLLECTION_ACCOUNT_ID,
+            1_50000000
+        ));
+
+        assert_ok!(RioSavingTest::settle(Origin::signed(BOB)));
+        assert_ok!(RioSavingTest::settle(Origin::signed(CHRIS)));
+        assert_noop!(
+            RioSavingTest::settle(Origin::signed(BOB)),
+            Error::<TestRuntime>::NothingToSettle
+        );
+
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &BOB),
+            2_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &CHRIS),
+            1_00000000
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RBTC_ASSET_ID, &CHRIS),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&RSC1_ASSET_ID, &CHRIS),
+            0
+        );
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::reserved_balance(&RSC1_ASSET_ID, &CHRIS),
+            0
+        );
+        assert_eq!(RioSavingTest::account_future_releases(&CHRIS).len(), 0);
+        assert_eq!(
+            <rio_assets::Module<TestRuntime>>::free_balance(&SBTC_ASSET_ID, &COLLECTION_ACCOUNT_ID),
+            0
+        );
+    });
+}
+
+#[test]
+fn circuit_breaker_works() {
+    ExtBuilder::default().build().execute_with(|| {
+        assert_ok!(<rio_assets::Module<TestRuntime>>::mint(
+            Origin::ROOT,
+            SBTC_ASSET_ID,
+            ALICE,
+            100 * DECIMALS
+        ));
+        assert_ok!(RioSavingTest::trip_breaker(
+            Origin::ROOT,
+            SavingFunction::Staking
+        ));
+        assert!(RioSavingTest::is_tripped(SavingFunction::Staking));
+        assert!(!RioSavingTest::is_tripped(SavingFunction::Redeem));
+        assert_noop!(
+            RioSavingTest::staking(
+                Origin::signed(ALICE),
+                SBTC_ASSET_ID,
+                10 * DECIMALS,
+                LockTier::Flexible
+            ),
+            Error::<TestRuntime>::FunctionTripped
+        );
+        assert_ok!(RioSavingTest::reset_breaker(
+            Origin::ROOT,
+            SavingFunction::Staking
+        ));
+        assert_ok!(RioSavingTest::staking(
+            Origin::signed(ALICE),
+            SBTC_ASSET_ID,
+            10 * DECIMALS,
+            LockTier::Flexible
+        ));
+
+        // resumes by itself after the timeout
+        assert_ok!(RioSavingTest::set_breaker_timeout(Origin::ROOT, Some(10)));
+        <system::Module<TestRuntime>>::set_block_number(1);
+        assert_ok!(RioSavingTest::trip_breaker(
+            Origin::ROOT,
+            SavingFunction::Redeem
+        ));
+        assert_eq!(
+            RioSavingTest::breakers(),
+            vec![(
+                SavingFunction::Redeem,
+                Breaker {
+                    reason: TripReason::Manual,
+                    tripped_at: 1,
+                    resume_at: Some(11),
+                }
+            )]
+        );
+        assert_noop!(
+            RioSavingTest::redeem(Origin::signed(ALICE), RSC1_ASSET_ID, 1 * DECIMALS),
+            Error::<TestRuntime>::FunctionTripped
+        );
+        <system::Module<TestRuntime>>::set_block_number(11);
+        assert!(!RioSavingTest::is_tripped(SavingFunction::Redeem));
+        assert_eq!(RioSavingTest::breakers(), vec![]);
+
+        // a reserve deficit leaves the bonus running
+        <RioSavingTest as TripBreakers>::trip_breakers(TripReason::ReserveDeficit);
+        assert!(RioSavingTest::is_tripped(SavingFunction::Staking));
+        assert!(RioSavingTest::is_tripped(SavingFunction::Redeem));
+        assert!(!RioSavingTest::is_tripped(SavingFunction::Bonus));
+        // while broken bookings stop everything
+        <RioSavingTest as TripBreakers>::trip_breakers(TripReason::InvariantBroken);
+        assert_eq!(RioSavingTest::breakers().len(), 3);
+    });
+}
+
 /// this is our ultimate test example
 #[test]
 fn save_12000_sbtc() {
@@ -0,0 +1,103 @@
+//! circuit breakers shared by Rio modules
+//!
+//! a module keeps its breakers in a storage map from its own function enum to `Breaker`,
+//! e.g. `Breakers get(breaker) : map SavingFunction => Option<Breaker<T::BlockNumber>>;`,
+//! then trips and checks them through `Breakers<Storage, Function, BlockNumber>`.
+//! modules implement `TripBreakers`, so that others (e.g. rio-reserve) can trip them without a dependency.
+
+use codec::{Decode, Encode, FullCodec, FullEncode};
+use core::marker::PhantomData;
+use core::ops::Add;
+use support::storage::StorageMap;
+
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// why a breaker is tripped
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum TripReason {
+    /// tripped by governance
+    Manual,
+    Overflow,
+    Underflow,
+    /// some tracked value doesn't match the actual one
+    InvariantBroken,
+    ReserveDeficit,
+}
+impl Default for TripReason {
+    fn default() -> Self {
+        TripReason::Manual
+    }
+}
+
+#[derive(Encode, Decode, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Breaker<BlockNumber> {
+    pub reason: TripReason,
+    pub tripped_at: BlockNumber,
+    /// resumes by itself from this block on, stays tripped until reset if none
+    pub resume_at: Option<BlockNumber>,
+}
+
+impl<BlockNumber: PartialOrd> Breaker<BlockNumber> {
+    pub fn is_tripped(&self, now: &BlockNumber) -> bool {
+        self.resume_at.as_ref().map_or(true, |at| now < at)
+    }
+}
+
+/// operations on the breakers kept in storage `S`
+pub struct Breakers<S, F, B>(PhantomData<(S, F, B)>);
+
+impl<S, F, B> Breakers<S, F, B>
+where
+    S: StorageMap<F, Breaker<B>, Query = Option<Breaker<B>>>,
+    F: FullEncode,
+    B: FullCodec + Copy + PartialOrd + Add<Output = B>,
+{
+    /// trip the breaker of `function`, a tripped one will be overwritten
+    pub fn trip(function: F, reason: TripReason, now: B, timeout: Option<B>) -> Breaker<B> {
+        let breaker = Breaker {
+            reason,
+            tripped_at: now,
+            resume_at: timeout.map(|t| now + t),
+        };
+        S::insert(function, breaker);
+        breaker
+    }
+
+    pub fn reset(function: F) {
+        S::remove(function);
+    }
+
+    /// the breaker of `function` if it's still tripped at `now`
+    pub fn get(function: F, now: B) -> Option<Breaker<B>> {
+        S::get(function).filter(|b| b.is_tripped(&now))
+    }
+
+    pub fn is_tripped(function: F, now: B) -> bool {
+        Self::get(function, now).is_some()
+    }
+}
+
+/// trip the breakers of the functions which are hit by `reason`
+pub trait TripBreakers {
+    fn trip_breakers(_reason: TripReason) {}
+}
+impl TripBreakers for () {}
+
+macro_rules! impl_trip_breakers_for_tuple {
+    ($($module:ident),+) => {
+        impl<$($module: TripBreakers),+> TripBreakers for ($($module,)+) {
+            fn trip_breakers(reason: TripReason) {
+                $($module::trip_breakers(reason);)+
+            }
+        }
+    };
+}
+
+impl_trip_breakers_for_tuple!(M1);
+impl_trip_breakers_for_tuple!(M1, M2);
+impl_trip_breakers_for_tuple!(M1, M2, M3);
+impl_trip_breakers_for_tuple!(M1, M2, M3, M4);
@@ -0,0 +1,150 @@
+//! overflow safe fixed point math
+//!
+//! `a * b / c` is computed with a 256 bits intermediate, so it only fails when the result itself
+//! doesn't fit in u128 or `c` is zero. Balances go through u128, which every Rio balance type converts into.
+
+use codec::{Decode, Encode};
+use core::convert::{TryFrom, TryInto};
+
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum MathError {
+    Overflow,
+    Underflow,
+    DivisionByZero,
+}
+
+impl From<MathError> for &'static str {
+    fn from(e: MathError) -> &'static str {
+        match e {
+            MathError::Overflow => "math overflow",
+            MathError::Underflow => "math underflow",
+            MathError::DivisionByZero => "division by zero",
+        }
+    }
+}
+
+pub type MathResult<T> = Result<T, MathError>;
+
+const LOW_MASK: u128 = u64::max_value() as u128;
+
+/// the full product of `a * b` as (high, low) halves
+fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    let (a1, a0) = (a >> 64, a & LOW_MASK);
+    let (b1, b0) = (b >> 64, b & LOW_MASK);
+    let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+    // less than 3 * 2^64, never overflows
+    let mid = (p00 >> 64) + (p01 & LOW_MASK) + (p10 & LOW_MASK);
+    let low = (p00 & LOW_MASK) | (mid << 64);
+    let high = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (high, low)
+}
+
+/// (high, low) / d, None if the quotient doesn't fit in u128
+fn div_wide(mut high: u128, mut low: u128, d: u128) -> Option<u128> {
+    if high >= d {
+        return None;
+    }
+    // shift and subtract, `high` stays below `d` as the remainder
+    let mut quotient = 0u128;
+    for _ in 0..128 {
+        let carry = high >> 127;
+        high = (high << 1) | (low >> 127);
+        low <<= 1;
+        quotient <<= 1;
+        if carry == 1 || high >= d {
+            high = high.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+/// `a * b / c`, rounding down
+pub fn mul_div(a: u128, b: u128, c: u128) -> MathResult<u128> {
+    if c == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let (high, low) = mul_wide(a, b);
+    div_wide(high, low, c).ok_or(MathError::Overflow)
+}
+
+pub fn checked_add(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_add(b).ok_or(MathError::Overflow)
+}
+
+pub fn checked_mul(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_mul(b).ok_or(MathError::Overflow)
+}
+
+pub fn checked_div(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_div(b).ok_or(MathError::DivisionByZero)
+}
+
+pub fn to_u128<B: TryInto<u128>>(b: B) -> MathResult<u128> {
+    b.try_into().map_err(|_| MathError::Overflow)
+}
+
+pub fn from_u128<B: TryFrom<u128>>(n: u128) -> MathResult<B> {
+    B::try_from(n).map_err(|_| MathError::Overflow)
+}
+
+/// `a * b / c` on a balance, rounding down
+pub fn balance_mul_div<B>(a: B, b: u128, c: u128) -> MathResult<B>
+where
+    B: TryInto<u128> + TryFrom<u128>,
+{
+    from_u128(mul_div(to_u128(a)?, b, c)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn wide_add(a: (u128, u128), b: u128) -> (u128, u128) {
+        let (low, carry) = a.1.overflowing_add(b);
+        (a.0 + carry as u128, low)
+    }
+
+    #[test]
+    fn mul_div_works() {
+        assert_eq!(mul_div(6, 7, 4), Ok(10));
+        assert_eq!(mul_div(u128::max_value(), u128::max_value(), u128::max_value()), Ok(u128::max_value()));
+        assert_eq!(mul_div(u128::max_value(), 2, 1), Err(MathError::Overflow));
+        assert_eq!(mul_div(1, 1, 0), Err(MathError::DivisionByZero));
+        assert_eq!(balance_mul_div::<u64>(u64::max_value(), 2, 1), Err(MathError::Overflow));
+        assert_eq!(balance_mul_div::<u64>(u64::max_value(), 2, 2), Ok(u64::max_value()));
+    }
+
+    proptest! {
+        #[test]
+        fn mul_div_is_exact(a in any::<u128>(), b in any::<u128>(), c in 1..=u128::max_value()) {
+            match mul_div(a, b, c) {
+                // q * c <= a * b < q * c + c
+                Ok(q) => {
+                    let product = mul_wide(a, b);
+                    let lower = mul_wide(q, c);
+                    prop_assert!(lower <= product);
+                    prop_assert!(product < wide_add(lower, c));
+                }
+                // the quotient is at least 2^128
+                Err(e) => {
+                    prop_assert_eq!(e, MathError::Overflow);
+                    prop_assert!(mul_wide(a, b).0 >= c);
+                }
+            }
+        }
+
+        #[test]
+        fn mul_div_matches_u128(a in any::<u64>(), b in any::<u64>(), c in 1..=u128::max_value()) {
+            let (a, b) = (a as u128, b as u128);
+            prop_assert_eq!(mul_div(a, b, c), Ok(a * b / c));
+        }
+
+        #[test]
+        fn mul_div_cancels(a in any::<u128>(), b in 1..=u128::max_value()) {
+            prop_assert_eq!(mul_div(a, b, b), Ok(a));
+        }
+    }
+}
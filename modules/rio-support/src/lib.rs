@@ -1,3 +1,6 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod breaker;
+pub mod fixed;
 pub mod rlog;
+pub mod timelock;
@@ -0,0 +1,76 @@
+//! timelocked parameter changes shared by Rio modules
+//!
+//! a module keeps its queue in its own storage, e.g.
+//! `PendingParamChanges get(pending_param_change) : linked_map ParamChangeId => Option<ParamChange<SavingParam, T::BlockNumber>>;`
+//! and `NextParamChangeId get(next_param_change_id) : ParamChangeId;`,
+//! then schedules, cancels and applies the changes through `Timelock<Queue, NextId, Param, BlockNumber>`.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use codec::{Decode, Encode, FullCodec};
+use core::marker::PhantomData;
+use core::ops::Add;
+use support::storage::{StorageLinkedMap, StorageValue};
+
+pub type ParamChangeId = u64;
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ParamChange<Param, BlockNumber> {
+    pub param: Param,
+    /// the change applies at the beginning of this block
+    pub eta: BlockNumber,
+}
+
+/// operations on the queue `Q` with the next id in `N`
+pub struct Timelock<Q, N, P, B>(PhantomData<(Q, N, P, B)>);
+
+impl<Q, N, P, B> Timelock<Q, N, P, B>
+where
+    Q: StorageLinkedMap<ParamChangeId, ParamChange<P, B>, Query = Option<ParamChange<P, B>>>,
+    N: StorageValue<ParamChangeId, Query = ParamChangeId>,
+    P: FullCodec,
+    B: FullCodec + Copy + PartialOrd + Add<Output = B>,
+{
+    /// queue the change to apply `delay` blocks after `now`, returns its id and the block it applies
+    pub fn schedule(param: P, now: B, delay: B) -> (ParamChangeId, B) {
+        let id = N::mutate(|v| {
+            let id = *v;
+            *v += 1;
+            id
+        });
+        let eta = now + delay;
+        Q::insert(id, ParamChange { param, eta });
+        (id, eta)
+    }
+
+    /// false if there is no pending change of the id
+    pub fn cancel(id: ParamChangeId) -> bool {
+        if !Q::exists(id) {
+            return false;
+        }
+        Q::remove(id);
+        true
+    }
+
+    /// pending changes in the order they apply
+    pub fn pending() -> Vec<(ParamChangeId, ParamChange<P, B>)> {
+        let mut changes: Vec<_> = Q::enumerate().collect();
+        changes.sort_by_key(|(id, _)| *id);
+        changes
+    }
+
+    /// take the changes due at `now` out of the queue, in the order they apply
+    pub fn take_due(now: B) -> Vec<(ParamChangeId, P)> {
+        let mut due = Vec::new();
+        for (id, change) in Self::pending() {
+            if change.eta > now {
+                break;
+            }
+            Q::remove(id);
+            due.push((id, change.param));
+        }
+        due
+    }
+}
@@ -19,7 +19,9 @@ use wasm_builder_runner::{build_current_project_with_rustflags, WasmBuilderSourc
 fn main() {
     build_current_project_with_rustflags(
         "wasm_binary.rs",
-        WasmBuilderSource::Crates("1.0.8"),
+        // the enabled features (saving, loan) are only forwarded to the wasm build since 1.0.10,
+        // otherwise the wasm runtime would miss the modules of the native one
+        WasmBuilderSource::Crates("1.0.10"),
         // This instructs LLD to export __heap_base as a global variable, which is used by the
         // external memory allocator.
         "-Clink-arg=--export=__heap_base",
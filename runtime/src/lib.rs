@@ -77,8 +77,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!(env!("CARGO_PKG_NAME")),
     impl_name: create_runtime_str!(env!("CARGO_PKG_NAME")),
     authoring_version: 1,
-    spec_version: 3,
-    impl_version: 3,
+    spec_version: 4,
+    impl_version: 4,
     apis: RUNTIME_API_VERSIONS,
 };
 
@@ -182,20 +182,35 @@ impl rio_assets::Trait for Runtime {
     type Balance = Balance;
     type AssetId = AssetId;
     type RootKey = KeyProvider;
-    // type BeforeAssetTransfer = RioSaving;
+    #[cfg(feature = "saving")]
+    type BeforeAssetTransfer = RioSaving;
+    #[cfg(not(feature = "saving"))]
     type BeforeAssetTransfer = ();
     type BeforeAssetCreate = ();
     type BeforeAssetMint = ();
     type BeforeAssetBurn = ();
-    // type OnAssetTransfer = OnAssetTransferHooks;
-    type OnAssetTransfer = ();
+    type OnAssetTransfer = OnAssetTransferHooks;
     type OnAssetCreate = ();
-    // type OnAssetMint = RioSaving;
-    // type OnAssetBurn = RioSaving;
+    #[cfg(feature = "saving")]
+    type OnAssetMint = RioSaving;
+    #[cfg(feature = "saving")]
+    type OnAssetBurn = RioSaving;
+    #[cfg(not(feature = "saving"))]
     type OnAssetMint = ();
+    #[cfg(not(feature = "saving"))]
     type OnAssetBurn = ();
 }
 
+/// asset transfers fan out to all the modules which are wired in
+#[cfg(all(feature = "saving", feature = "loan"))]
+pub type OnAssetTransferHooks = (RioSaving, RioLoan);
+#[cfg(all(feature = "saving", not(feature = "loan")))]
+pub type OnAssetTransferHooks = (RioSaving,);
+#[cfg(all(not(feature = "saving"), feature = "loan"))]
+pub type OnAssetTransferHooks = (RioLoan,);
+#[cfg(not(any(feature = "saving", feature = "loan")))]
+pub type OnAssetTransferHooks = ();
+
 impl rio_fee::Trait for Runtime {
     type Event = Event;
     type Currency = rio_assets::NativeAsset<Runtime>;
@@ -222,13 +237,34 @@ impl rio_transaction_payment::Trait for Runtime {
     type BuyFeeAsset = RioFee;
 }
 
-// impl rio_saving::Trait for Runtime {
-//     type Event = Event;
-// }
+parameter_types! {
+    pub const ParamChangeDelay: BlockNumber = 2 * DAYS;
+}
+
+#[cfg(feature = "saving")]
+impl rio_saving::Trait for Runtime {
+    type Event = Event;
+    type ParamChangeDelay = ParamChangeDelay;
+}
 
-// impl rio_loan::Trait for Runtime {
-//     type Event = Event;
-// }
+#[cfg(feature = "saving")]
+impl rio_reserve::Trait for Runtime {
+    type Event = Event;
+    type Breakers = ReserveBreakers;
+}
+
+/// a reserve deficit trips the breakers of all the modules holding SBTC
+#[cfg(all(feature = "saving", feature = "loan"))]
+pub type ReserveBreakers = (RioSaving, RioBridge, RioLoan);
+#[cfg(all(feature = "saving", not(feature = "loan")))]
+pub type ReserveBreakers = (RioSaving, RioBridge);
+
+#[cfg(feature = "loan")]
+impl rio_loan::Trait for Runtime {
+    type Event = Event;
+    type Kyc = RioBridge;
+    type ParamChangeDelay = ParamChangeDelay;
+}
 
 type OracleCollective = collective::Instance2;
 
@@ -279,45 +315,71 @@ impl rio_price::Trait for Runtime {
     type Event = Event;
     type OracleMixedIn = RioOracle;
     type ReportOrigin = collective::EnsureMember<AccountId, OracleCollective>;
-    // type OnChange = RioLoan;
+    #[cfg(feature = "loan")]
+    type OnChange = RioLoan;
+    #[cfg(not(feature = "loan"))]
     type OnChange = ();
 }
 
 impl rio_bridge::Trait for Runtime {
     type Event = Event;
+    type ParamChangeDelay = ParamChangeDelay;
 }
 
-construct_runtime!(
-    pub enum Runtime where
-        Block = Block,
-        NodeBlock = opaque::Block,
-        UncheckedExtrinsic = UncheckedExtrinsic
-    {
-        System: system::{Module, Call, Storage, Config, Event},
-        Timestamp: timestamp::{Module, Call, Storage, Inherent},
-        Aura: aura::{Module, Config<T>, Inherent(Timestamp)},
-        Grandpa: grandpa::{Module, Call, Storage, Config, Event},
-        RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
-        // may remove in future?
-        Indices: indices,
-        Sudo: sudo,
-
-        RioAssets: rio_assets::{Module, Storage, Call, Config<T>, Event<T>},
-        RioFee: rio_fee::{Module, Call, Storage, Config<T>, Event<T>},
-        RioTransactionPayment: rio_transaction_payment::{Module, Storage},
-
-        RioOracle: rio_oracle::{Module, Call, Storage, Event<T>},
-        RioOracleMembers: collective::<Instance2>::{Module, Call, Storage, Origin<T>, Event<T>},
-        RioPrice: rio_price::{Module, Call, Storage, Event<T>},
-
-        // RioSaving: rio_saving::{Module, Storage, Call, Config<T>, Event<T>},
-        // RioLoan: rio_loan::{Module, Call, Storage, Config<T>, Event<T>, Log},
-
-        RioBridge: rio_bridge::{Module, Call, Storage, Config<T>, Event<T>},
-
-        Utility: utility::{Module, Call, Event},
-    }
+/// `construct_runtime!` doesn't take `#[cfg]` on modules, modules behind cargo features are passed in
+macro_rules! construct_rio_runtime {
+    ($($feature_modules:tt)*) => {
+        construct_runtime!(
+            pub enum Runtime where
+                Block = Block,
+                NodeBlock = opaque::Block,
+                UncheckedExtrinsic = UncheckedExtrinsic
+            {
+                System: system::{Module, Call, Storage, Config, Event},
+                Timestamp: timestamp::{Module, Call, Storage, Inherent},
+                Aura: aura::{Module, Config<T>, Inherent(Timestamp)},
+                Grandpa: grandpa::{Module, Call, Storage, Config, Event},
+                RandomnessCollectiveFlip: randomness_collective_flip::{Module, Call, Storage},
+                // may remove in future?
+                Indices: indices,
+                Sudo: sudo,
+        
+                RioAssets: rio_assets::{Module, Storage, Call, Config<T>, Event<T>},
+                RioFee: rio_fee::{Module, Call, Storage, Config<T>, Event<T>},
+                RioTransactionPayment: rio_transaction_payment::{Module, Storage},
+        
+                RioOracle: rio_oracle::{Module, Call, Storage, Event<T>},
+                RioOracleMembers: collective::<Instance2>::{Module, Call, Storage, Origin<T>, Event<T>},
+                RioPrice: rio_price::{Module, Call, Storage, Event<T>},
+        
+                RioBridge: rio_bridge::{Module, Call, Storage, Config<T>, Event<T>},
+        
+                Utility: utility::{Module, Call, Event},
+        
+                // modules behind cargo features go last, so they don't shift the index of the others
+                $($feature_modules)*
+            }
+        );
+    };
+}
+
+#[cfg(all(feature = "saving", feature = "loan"))]
+construct_rio_runtime!(
+    RioSaving: rio_saving::{Module, Storage, Call, Config<T>, Event<T>},
+    RioReserve: rio_reserve::{Module, Call, Storage, Config<T>, Event<T>},
+    RioLoan: rio_loan::{Module, Call, Storage, Config<T>, Event<T>},
+);
+#[cfg(all(feature = "saving", not(feature = "loan")))]
+construct_rio_runtime!(
+    RioSaving: rio_saving::{Module, Storage, Call, Config<T>, Event<T>},
+    RioReserve: rio_reserve::{Module, Call, Storage, Config<T>, Event<T>},
 );
+#[cfg(all(not(feature = "saving"), feature = "loan"))]
+construct_rio_runtime!(
+    RioLoan: rio_loan::{Module, Call, Storage, Config<T>, Event<T>},
+);
+#[cfg(not(any(feature = "saving", feature = "loan")))]
+construct_rio_runtime!();
 
 /// The address format for describing accounts.
 pub type Address = <Indices as StaticLookup>::Source;
@@ -476,4 +538,103 @@ impl_runtime_apis! {
                   RioTransactionPayment::query_info(uxt, len)
             }
       }
+
+    // always implemented so that the node doesn't depend on the features,
+    // everything is empty when rio-saving is not wired in
+    impl rio_saving_rpc_runtime_api::SavingApi<
+        Block,
+        AccountId,
+        Balance,
+        AssetId,
+        BlockNumber,
+    > for Runtime {
+        #[allow(unused_variables)]
+        fn saving_position(who: AccountId) -> rio_saving_rpc_runtime_api::SavingPosition<Balance, AssetId> {
+            #[cfg(feature = "saving")]
+            return RioSaving::saving_position(who);
+            #[cfg(not(feature = "saving"))]
+            return Default::default();
+        }
+
+        fn saving_state() -> rio_saving_rpc_runtime_api::SavingState<Balance> {
+            #[cfg(feature = "saving")]
+            return RioSaving::saving_state();
+            #[cfg(not(feature = "saving"))]
+            return Default::default();
+        }
+
+        fn breakers() -> Vec<(
+            rio_saving_rpc_runtime_api::SavingFunction,
+            rio_saving_rpc_runtime_api::Breaker<BlockNumber>,
+        )> {
+            #[cfg(feature = "saving")]
+            return RioSaving::breakers();
+            #[cfg(not(feature = "saving"))]
+            return Vec::new();
+        }
+    }
+
+    impl rio_bridge_rpc_runtime_api::BridgeApi<Block, BlockNumber> for Runtime {
+        fn breakers() -> Vec<(
+            rio_bridge_rpc_runtime_api::BridgeFunction,
+            rio_bridge_rpc_runtime_api::Breaker<BlockNumber>,
+        )> {
+            RioBridge::breakers()
+        }
+    }
+
+    impl rio_loan_rpc_runtime_api::LoanApi<
+        Block,
+        AccountId,
+        Balance,
+        AssetId,
+        Moment,
+        BlockNumber,
+    > for Runtime {
+        fn solvency() -> rio_loan_rpc_runtime_api::Solvency<Balance> {
+            #[cfg(feature = "loan")]
+            return RioLoan::solvency();
+            #[cfg(not(feature = "loan"))]
+            return Default::default();
+        }
+
+        fn system_debt() -> rio_loan_rpc_runtime_api::SystemDebt<Balance, AssetId> {
+            #[cfg(feature = "loan")]
+            return RioLoan::system_debt();
+            #[cfg(not(feature = "loan"))]
+            return Default::default();
+        }
+
+        #[allow(unused_variables)]
+        fn loan_positions(
+            who: AccountId,
+        ) -> Vec<rio_loan_rpc_runtime_api::LoanPosition<Balance, AssetId, Moment>> {
+            #[cfg(feature = "loan")]
+            return RioLoan::loan_positions(who);
+            #[cfg(not(feature = "loan"))]
+            return Vec::new();
+        }
+
+        #[allow(unused_variables)]
+        fn quote_apply(
+            who: AccountId,
+            collateral_amount: Balance,
+            package_id: rio_loan_rpc_runtime_api::LoanPackageId,
+        ) -> Option<rio_loan_rpc_runtime_api::LoanQuote<Balance>> {
+            #[cfg(feature = "loan")]
+            return RioLoan::quote_apply(who, collateral_amount, package_id);
+            #[cfg(not(feature = "loan"))]
+            return None;
+        }
+
+        fn breakers() -> Vec<(
+            rio_loan_rpc_runtime_api::LoanFunction,
+            rio_loan_rpc_runtime_api::Breaker<BlockNumber>,
+        )> {
+            #[cfg(feature = "loan")]
+            return RioLoan::breakers();
+            #[cfg(not(feature = "loan"))]
+            return Vec::new();
+        }
+    }
 }
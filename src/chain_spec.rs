@@ -12,6 +12,10 @@ use runtime::{
     self, AuraConfig, GenesisConfig, GrandpaConfig, IndicesConfig, RioAssetsConfig,
     RioBridgeConfig, RioFeeConfig, SudoConfig, SystemConfig, WASM_BINARY,
 };
+#[cfg(feature = "loan")]
+use runtime::RioLoanConfig;
+#[cfg(feature = "saving")]
+use runtime::{RioReserveConfig, RioSavingConfig};
 use sc_service;
 use serde_json as json;
 use sp_runtime::traits::{IdentifyAccount, Verify};
@@ -326,40 +330,49 @@ fn testnet_genesis(
                 (AssetId::from(106 as u32), b"RSC5".to_vec(), vec![], vec![]),
             ],
         }),
-        // rio_loan: Some(RioLoanConfig {
-        //     current_btc_price: 8000_0000,
-        //     collateral_asset_id: 10,
-        //     loan_asset_id: 1000,
-        //     global_ltv_limit: 6500,
-        //     global_liquidation_threshold: 9000,
-        //     global_warning_threshold: 8000,
-        //     next_loan_id: 1,
-        //     next_loan_package_id: 1,
-        //     pawn_shop: get_account_id_from_seed::<sr25519::Public>("999999999999"),
-        //     profit_pool: get_account_id_from_seed::<sr25519::Public>("88888888"),
-        //     penalty_rate: 200,
-        //     liquidation_account: get_account_id_from_seed::<sr25519::Public>("Bob"),
-        //     minimum_collateral: 2000_0000,
-        //     liquidation_penalty: 1300,
-        // }),
-        // rio_saving: Some(RioSavingConfig {
-        //     current_phase_id: 1,
-        //     collection_asset_id: 10,
-        //     share_asset_id: 2,
-        //     phase_infos: vec![
-        //         (100_00000000, 10000, 3),
-        //         (400_00000000, 8000, 4),
-        //         (1000_00000000, 5000, 5),
-        //         (5000_00000000, 2000, 6),
-        //         (10000_00000000, 1000, 7),
-        //     ],
-        //     collection_account_id: get_account_id_from_seed::<sr25519::Public>("Alice"),
-        //     team_account_id: get_account_id_from_seed::<sr25519::Public>("Team"),
-        //     profit_pool: get_account_id_from_seed::<sr25519::Public>("88888888"),
-        //     profit_asset_id: 8,
-        //     reserved_mint_wallet: get_account_id_from_seed::<sr25519::Public>("reserved wallet"),
-        //     reserved_mint_asset_id: 8,
-        // }),
+        #[cfg(feature = "loan")]
+        rio_loan: Some(RioLoanConfig {
+            current_btc_price: 8000_0000,
+            collateral_asset_id: AssetId::from(runtime::SBTC),
+            loan_asset_id: AssetId::from(runtime::SUSDT),
+            global_ltv_limit: 6500,
+            global_liquidation_threshold: 9000,
+            global_warning_threshold: 8000,
+            next_loan_id: 1,
+            next_loan_package_id: 1,
+            pawn_shop: get_account_id_from_seed::<sr25519::Public>("999999999999"),
+            profit_pool: get_account_id_from_seed::<sr25519::Public>("88888888"),
+            penalty_rate: 200,
+            liquidation_account: get_account_id_from_seed::<sr25519::Public>("Bob"),
+            minimum_collateral: 2000_0000,
+            liquidation_penalty: 1300,
+        }),
+        #[cfg(feature = "saving")]
+        rio_saving: Some(RioSavingConfig {
+            current_phase_id: 1,
+            collection_asset_id: AssetId::from(runtime::SBTC),
+            share_asset_id: AssetId::from(101 as u32),
+            phase_infos: vec![
+                (100_00000000, 10000, AssetId::from(102 as u32)),
+                (400_00000000, 8000, AssetId::from(103 as u32)),
+                (1000_00000000, 5000, AssetId::from(104 as u32)),
+                (5000_00000000, 2000, AssetId::from(105 as u32)),
+                (10000_00000000, 1000, AssetId::from(106 as u32)),
+            ],
+            collection_account_id: get_account_id_from_seed::<sr25519::Public>("Alice"),
+            team_account_id: get_account_id_from_seed::<sr25519::Public>("Team"),
+            profit_pool: get_account_id_from_seed::<sr25519::Public>("88888888"),
+            profit_asset_id: AssetId::from(runtime::RFUEL),
+            reserved_mint_wallet: get_account_id_from_seed::<sr25519::Public>("reserved wallet"),
+            reserved_mint_asset_id: AssetId::from(runtime::RFUEL),
+        }),
+        #[cfg(feature = "saving")]
+        rio_reserve: Some(RioReserveConfig {
+            attesters: vec![get_account_id_from_seed::<sr25519::Public>("Alice")],
+            quorum: 1,
+            deficit_threshold: 1_00000000,
+            auto_pause: true,
+        }),
         rio_bridge: Some(RioBridgeConfig {
             asset_id: AssetId::from(runtime::SBTC),
             threshold: 30_0000_0000,
@@ -438,54 +451,66 @@ fn beta_genesis(
                 (AssetId::from(106 as u32), b"RSC5".to_vec(), vec![], vec![]),
             ],
         }),
-        // rio_loan: Some(RioLoanConfig {
-        //     current_btc_price: 8000_0000,
-        //     collateral_asset_id: 1,
-        //     loan_asset_id: 8,
-        //     global_ltv_limit: 6500,
-        //     global_liquidation_threshold: 9000,
-        //     global_warning_threshold: 8000,
-        //     next_loan_id: 1,
-        //     next_loan_package_id: 1,
-        //     pawn_shop: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     profit_pool: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     penalty_rate: 200,
-        //     liquidation_account: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     minimum_collateral: 2000_0000,
-        //     liquidation_penalty: 1300,
-        // }),
-        // rio_saving: Some(RioSavingConfig {
-        //     current_phase_id: 1,
-        //     collection_asset_id: 1,
-        //     share_asset_id: 2,
-        //     phase_infos: vec![
-        //         (100_00000000, 10000, 3),
-        //         (400_00000000, 8000, 4),
-        //         (1000_00000000, 5000, 5),
-        //         (5000_00000000, 2000, 6),
-        //         (10000_00000000, 1000, 7),
-        //     ],
-        //     collection_account_id: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     team_account_id: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     profit_pool: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     profit_asset_id: 8,
-        //     reserved_mint_wallet: get_account_id_from_seed::<sr25519::Public>(
-        //         "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
-        //     ),
-        //     reserved_mint_asset_id: 8,
-        // }),
+        #[cfg(feature = "loan")]
+        rio_loan: Some(RioLoanConfig {
+            current_btc_price: 8000_0000,
+            collateral_asset_id: AssetId::from(runtime::SBTC),
+            loan_asset_id: AssetId::from(runtime::SUSDT),
+            global_ltv_limit: 6500,
+            global_liquidation_threshold: 9000,
+            global_warning_threshold: 8000,
+            next_loan_id: 1,
+            next_loan_package_id: 1,
+            pawn_shop: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            profit_pool: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            penalty_rate: 200,
+            liquidation_account: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            minimum_collateral: 2000_0000,
+            liquidation_penalty: 1300,
+        }),
+        #[cfg(feature = "saving")]
+        rio_saving: Some(RioSavingConfig {
+            current_phase_id: 1,
+            collection_asset_id: AssetId::from(runtime::SBTC),
+            share_asset_id: AssetId::from(101 as u32),
+            phase_infos: vec![
+                (100_00000000, 10000, AssetId::from(102 as u32)),
+                (400_00000000, 8000, AssetId::from(103 as u32)),
+                (1000_00000000, 5000, AssetId::from(104 as u32)),
+                (5000_00000000, 2000, AssetId::from(105 as u32)),
+                (10000_00000000, 1000, AssetId::from(106 as u32)),
+            ],
+            collection_account_id: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            team_account_id: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            profit_pool: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            profit_asset_id: AssetId::from(runtime::RFUEL),
+            reserved_mint_wallet: get_account_id_from_seed::<sr25519::Public>(
+                "0x183606b482851e67aa356e598b2efb699391e26ec3c087f648e0fc1a22a83f98",
+            ),
+            reserved_mint_asset_id: AssetId::from(runtime::RFUEL),
+        }),
+        #[cfg(feature = "saving")]
+        rio_reserve: Some(RioReserveConfig {
+            attesters: vec![
+                // 5FJ4MeWhQtvBZxjFkgFm9ojbw2jhqjmjoYo9Jr3xSU7j2Uyk
+                hex!["8ee3e606982e20e495b622d091a99d4cdbc669afd8c08efb1acfb375b2e9f61a"].into(),
+            ],
+            quorum: 1,
+            deficit_threshold: 1_00000000,
+            auto_pause: true,
+        }),
         rio_bridge: Some(RioBridgeConfig {
             asset_id: AssetId::from(runtime::SBTC),
             threshold: 30_0000_0000,
@@ -20,6 +20,9 @@ native_executor_instance!(
     runtime::native_version,
 );
 
+/// rpc methods of the Rio modules, served beside the substrate ones
+pub type RpcExtension = jsonrpc_core::IoHandler<sc_rpc::Metadata>;
+
 construct_simple_protocol! {
     /// Demo protocol attachment for substrate.
     pub struct NodeProtocol where Block = Block { }
@@ -72,6 +75,18 @@ macro_rules! new_full_start {
             import_setup = Some((grandpa_block_import, grandpa_link));
 
             Ok(import_queue)
+        })?
+        .with_rpc_extensions(|client, _pool, _backend, _fetcher, _remote_blockchain|
+            -> Result<crate::service::RpcExtension, _> {
+            use rio_bridge_rpc::{Bridge, BridgeApi};
+            use rio_loan_rpc::{Loan, LoanApi};
+            use rio_saving_rpc::{Saving, SavingApi};
+
+            let mut io = jsonrpc_core::IoHandler::default();
+            io.extend_with(SavingApi::to_delegate(Saving::new(client.clone())));
+            io.extend_with(LoanApi::to_delegate(Loan::new(client.clone())));
+            io.extend_with(BridgeApi::to_delegate(Bridge::new(client)));
+            Ok(io)
         })?;
 
         (builder, import_setup, inherent_data_providers)